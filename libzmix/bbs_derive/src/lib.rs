@@ -0,0 +1,58 @@
+//! `#[derive(BbsMessages)]` for `zmix::signatures::bbs::messages::ToMessages`
+//!
+//! Every field becomes one message, in declaration order, through
+//! `zmix::signatures::bbs::messages::ToMessage`. Reordering the fields of a struct changes the
+//! messages, so signatures made before the change no longer verify.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Index};
+
+#[proc_macro_derive(BbsMessages)]
+pub fn derive_bbs_messages(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => {
+            return syn::Error::new_spanned(&input, "BbsMessages can only be derived for structs")
+                .to_compile_error()
+                .into()
+        }
+    };
+    let accessors = match fields {
+        Fields::Named(named) => named
+            .named
+            .iter()
+            .map(|f| {
+                let ident = &f.ident;
+                quote!(&self.#ident)
+            })
+            .collect::<Vec<_>>(),
+        Fields::Unnamed(unnamed) => (0..unnamed.unnamed.len())
+            .map(|i| {
+                let index = Index::from(i);
+                quote!(&self.#index)
+            })
+            .collect(),
+        Fields::Unit => Vec::new(),
+    };
+    let count = accessors.len();
+
+    let expanded = quote! {
+        impl #impl_generics ::zmix::signatures::bbs::messages::ToMessages for #name #ty_generics #where_clause {
+            fn message_count() -> usize {
+                #count
+            }
+
+            fn to_messages(&self) -> Vec<::zmix::signatures::SignatureMessage> {
+                vec![#(::zmix::signatures::bbs::messages::ToMessage::to_message(#accessors)),*]
+            }
+        }
+    };
+    expanded.into()
+}
@@ -175,6 +175,21 @@ macro_rules! impl_PoK_VC {
                 FieldElement::from_msg_hash(&bytes)
             }
 
+            /// Same as `gen_challenge` with a verifier's `nonce` hashed in after `extra`, length
+            /// prefixed. A proof for one nonce fails under another, so a verifier picking a
+            /// fresh nonce per session can't be sent a replayed proof. Verify with
+            /// `verify_with_nonce`.
+            pub fn gen_challenge_with_nonce(&self, extra: Vec<u8>, nonce: &[u8]) -> FieldElement {
+                FieldElement::from_msg_hash(
+                    &$crate::commitments::pok_vc::challenge_bytes_with_nonce(
+                        self.gens.as_slice(),
+                        &self.commitment,
+                        extra,
+                        nonce,
+                    ),
+                )
+            }
+
             /// For each secret, generate a response as self.blinding[i] - challenge*secrets[i].
             pub fn gen_proof(
                 self,
@@ -200,6 +215,26 @@ macro_rules! impl_PoK_VC {
         }
 
         impl $Proof {
+            /// Recompute the challenge of `gen_challenge_with_nonce` from `extra` and the
+            /// verifier's own `nonce`, and `verify` with it
+            pub fn verify_with_nonce(
+                &self,
+                bases: &[$group_element],
+                commitment: &$group_element,
+                extra: Vec<u8>,
+                nonce: &[u8],
+            ) -> Result<bool, PoKVCError> {
+                let challenge = FieldElement::from_msg_hash(
+                    &$crate::commitments::pok_vc::challenge_bytes_with_nonce(
+                        bases,
+                        &self.commitment,
+                        extra,
+                        nonce,
+                    ),
+                );
+                self.verify(bases, commitment, &challenge)
+            }
+
             /// Verify that bases[0]^responses[0] * bases[0]^responses[0] * ... bases[i]^responses[i] * commitment^challenge == random_commitment
             pub fn verify(
                 &self,
@@ -228,6 +263,26 @@ macro_rules! impl_PoK_VC {
     };
 }
 
+// Bases, random commitment, `extra` and the nonce with its length as 4 bytes big endian.
+// Public only for `impl_PoK_VC` which is exported.
+#[doc(hidden)]
+pub fn challenge_bytes_with_nonce<G: GroupElement>(
+    bases: &[G],
+    random_commitment: &G,
+    mut extra: Vec<u8>,
+    nonce: &[u8],
+) -> Vec<u8> {
+    let mut bytes = vec![];
+    for b in bases {
+        bytes.append(&mut b.to_bytes());
+    }
+    bytes.append(&mut random_commitment.to_bytes());
+    bytes.append(&mut extra);
+    bytes.extend_from_slice(&(nonce.len() as u32).to_be_bytes());
+    bytes.extend_from_slice(nonce);
+    bytes
+}
+
 #[cfg(test)]
 macro_rules! test_PoK_VC {
     ( $n:ident, $ProverCommitting:ident, $ProverCommitted:ident, $Proof:ident, $group_element:ident, $group_element_vec:ident ) => {
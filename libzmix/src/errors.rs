@@ -17,12 +17,78 @@ pub enum BBSErrorKind {
     SignatureValueIncorrectSize,
     #[fail(display = "Malformed public key")]
     MalformedPublicKey,
+    #[fail(display = "Public key bases are not in canonical order")]
+    BaseOrderMismatch,
+    #[fail(display = "Presentation was created with a different message encoding profile")]
+    EncodingMismatch,
+    #[fail(display = "Signature point is the identity or not in the prime order subgroup")]
+    InvalidSignaturePoint,
+    #[fail(display = "Too few messages. Expected {}, found {}", expected, got)]
+    TooFewMessages { expected: usize, got: usize },
+    #[fail(display = "Too many messages. Expected {}, found {}", expected, got)]
+    TooManyMessages { expected: usize, got: usize },
+    #[fail(display = "Public key of {} bytes doesn't fit the fixed layout", _0)]
+    PublicKeyIncorrectSize(usize),
+    #[fail(display = "Unblinded signature doesn't verify over the expected messages")]
+    InvalidBlindSignature,
     #[fail(display = "Error from PoKVC module {:?}", msg)]
     PoKVCError { msg: String },
     #[fail(display = "{:?}", msg)]
     GeneralError { msg: String },
 }
 
+impl BBSErrorKind {
+    /// Stable numeric code for FFI consumers. Codes are never reused or renumbered, a new kind
+    /// gets the next free code. 0 is left for success.
+    ///
+    /// | Code | Kind |
+    /// |------|------|
+    /// | 1 | `KeyGenError` |
+    /// | 2 | `SigningErrorMessageCountMismatch` |
+    /// | 3 | `SignatureIncorrectSize` |
+    /// | 4 | `SignatureValueIncorrectSize` |
+    /// | 5 | `MalformedPublicKey` |
+    /// | 6 | `PoKVCError` |
+    /// | 7 | `GeneralError` |
+    /// | 8 | `BaseOrderMismatch` |
+    /// | 9 | `EncodingMismatch` |
+    /// | 10 | `InvalidSignaturePoint` |
+    /// | 11 | `TooFewMessages` |
+    /// | 12 | `TooManyMessages` |
+    /// | 13 | `PublicKeyIncorrectSize` |
+    /// | 14 | `InvalidBlindSignature` |
+    pub fn code(&self) -> u32 {
+        match self {
+            BBSErrorKind::KeyGenError => 1,
+            BBSErrorKind::SigningErrorMessageCountMismatch(..) => 2,
+            BBSErrorKind::SignatureIncorrectSize(..) => 3,
+            BBSErrorKind::SignatureValueIncorrectSize => 4,
+            BBSErrorKind::MalformedPublicKey => 5,
+            BBSErrorKind::PoKVCError { .. } => 6,
+            BBSErrorKind::GeneralError { .. } => 7,
+            BBSErrorKind::BaseOrderMismatch => 8,
+            BBSErrorKind::EncodingMismatch => 9,
+            BBSErrorKind::InvalidSignaturePoint => 10,
+            BBSErrorKind::TooFewMessages { .. } => 11,
+            BBSErrorKind::TooManyMessages { .. } => 12,
+            BBSErrorKind::PublicKeyIncorrectSize(..) => 13,
+            BBSErrorKind::InvalidBlindSignature => 14,
+        }
+    }
+
+    /// `TooFewMessages` or `TooManyMessages` for `got` messages where `expected` were needed,
+    /// `SigningErrorMessageCountMismatch` if the counts are equal but still don't fit
+    pub fn message_count(expected: usize, got: usize) -> Self {
+        if got < expected {
+            BBSErrorKind::TooFewMessages { expected, got }
+        } else if got > expected {
+            BBSErrorKind::TooManyMessages { expected, got }
+        } else {
+            BBSErrorKind::SigningErrorMessageCountMismatch(expected, got)
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct BBSError {
     inner: Context<BBSErrorKind>,
@@ -57,6 +123,11 @@ impl BBSError {
     pub fn kind(&self) -> BBSErrorKind {
         self.inner.get_context().clone()
     }
+
+    /// Stable numeric code of the error's kind, see `BBSErrorKind::code`
+    pub fn code(&self) -> u32 {
+        self.inner.get_context().code()
+    }
 }
 
 impl std::fmt::Display for BBSError {
@@ -192,3 +263,66 @@ macro_rules! impl_PoKVCError_conversion {
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bbs_error_codes() {
+        let kinds = vec![
+            BBSErrorKind::KeyGenError,
+            BBSErrorKind::SigningErrorMessageCountMismatch(2, 3),
+            BBSErrorKind::SignatureIncorrectSize(10),
+            BBSErrorKind::SignatureValueIncorrectSize,
+            BBSErrorKind::MalformedPublicKey,
+            BBSErrorKind::PoKVCError { msg: String::new() },
+            BBSErrorKind::GeneralError { msg: String::new() },
+            BBSErrorKind::BaseOrderMismatch,
+            BBSErrorKind::EncodingMismatch,
+            BBSErrorKind::InvalidSignaturePoint,
+            BBSErrorKind::TooFewMessages {
+                expected: 2,
+                got: 1,
+            },
+            BBSErrorKind::TooManyMessages {
+                expected: 2,
+                got: 3,
+            },
+            BBSErrorKind::PublicKeyIncorrectSize(0),
+            BBSErrorKind::InvalidBlindSignature,
+        ];
+        // Codes are part of the FFI, changing any of them is a breaking change
+        let codes: Vec<u32> = kinds.iter().map(|k| k.code()).collect();
+        assert_eq!(codes, (1..=14).collect::<Vec<u32>>());
+
+        let err = BBSError::from_kind(BBSErrorKind::SigningErrorMessageCountMismatch(1, 2));
+        assert_eq!(err.code(), 2);
+        let err = BBSError::from_msg(BBSErrorKind::SignatureIncorrectSize(3), "bad length");
+        assert_eq!(err.code(), 3);
+        assert_eq!(
+            BBSErrorKind::PublicKeyIncorrectSize(300).to_string(),
+            "Public key of 300 bytes doesn't fit the fixed layout"
+        );
+        // Values carried by the kind don't change the code
+        assert_eq!(
+            BBSErrorKind::SignatureIncorrectSize(4).code(),
+            BBSErrorKind::SignatureIncorrectSize(200).code()
+        );
+    }
+
+    #[test]
+    fn bbs_error_message_count() {
+        assert_eq!(BBSErrorKind::message_count(3, 2).code(), 11);
+        assert_eq!(BBSErrorKind::message_count(3, 4).code(), 12);
+        assert_eq!(BBSErrorKind::message_count(3, 3).code(), 2);
+        assert_eq!(
+            BBSErrorKind::message_count(3, 2).to_string(),
+            "Too few messages. Expected 3, found 2"
+        );
+        assert_eq!(
+            BBSErrorKind::message_count(3, 4).to_string(),
+            "Too many messages. Expected 3, found 4"
+        );
+    }
+}
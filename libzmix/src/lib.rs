@@ -1,6 +1,7 @@
 //#[macro_use]
 //extern crate lazy_static;
 extern crate amcl_wrapper;
+extern crate bbs_derive;
 extern crate failure;
 #[macro_use]
 extern crate serde;
@@ -9,6 +10,12 @@ extern crate serde_json;
 extern crate bulletproofs_amcl as bulletproofs;
 extern crate merlin;
 extern crate rand;
+extern crate subtle;
+extern crate unicode_normalization;
+extern crate zeroize;
+
+// Code from `#[derive(BbsMessages)]` names this crate `zmix`, in here as well
+extern crate self as zmix;
 
 #[macro_use]
 pub mod commitments;
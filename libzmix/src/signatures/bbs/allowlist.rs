@@ -0,0 +1,195 @@
+// Allowlists of signatures as a pairing based accumulator (Nguyen, CT-RSA 2005).
+//
+// The allowlist manager holds a secret `alpha`, publishes `g2^alpha` and accumulates the
+// elements `x_i`, each a hash of an allowed signature's `a`, into `V = g1^((x_1 + alpha) *
+// (x_2 + alpha) * ...)`. The witness for `x` is `W = V^(1 / (x + alpha))`, checked by
+// `e(W, g2^x * g2^alpha) == e(V, g2)`. Making a witness for an element that was never added
+// would break q-SDH, only the manager with `alpha` can make witnesses at all and it only does
+// for members. Verifiers need `Accumulator`, the value and the public key, and nothing else.
+//
+// Adding an element changes `V` and every witness with it. A holder updates its witness without
+// the secret with `AllowlistWitness::update`, or asks the manager for a new one. Removal isn't
+// supported: it needs `alpha` and invalidates witnesses the same way.
+
+use super::super::SignatureMessage;
+use super::keys::PublicKey;
+use super::signature::Signature;
+use crate::errors::prelude::*;
+
+use amcl_wrapper::extension_field_gt::GT;
+use amcl_wrapper::field_elem::FieldElement;
+use amcl_wrapper::group_elem::GroupElement;
+use amcl_wrapper::group_elem_g1::G1;
+use amcl_wrapper::group_elem_g2::G2;
+
+use std::collections::BTreeSet;
+
+pub mod prelude {
+    pub use super::{Accumulator, AllowlistManager, AllowlistWitness};
+}
+
+const ELEMENT_DST: &[u8] = b"BBS+ allowlist element";
+
+// The accumulated element standing for `a`
+fn element(a: &G1) -> FieldElement {
+    let mut bytes = ELEMENT_DST.to_vec();
+    bytes.append(&mut a.to_bytes());
+    FieldElement::from_msg_hash(&bytes)
+}
+
+/// What a verifier needs of an allowlist, see the top of `allowlist.rs`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Accumulator {
+    pub value: G1,
+    pub public_key: G2,
+}
+
+/// Membership witness of one signature
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AllowlistWitness {
+    pub w: G1,
+}
+
+/// The allowlist manager's secret and the elements it accumulated
+pub struct AllowlistManager {
+    secret: FieldElement,
+    members: BTreeSet<FieldElement>,
+    accumulator: Accumulator,
+}
+
+impl AllowlistManager {
+    /// Empty allowlist with a random secret
+    pub fn new() -> Self {
+        let secret = FieldElement::random();
+        AllowlistManager {
+            accumulator: Accumulator {
+                value: G1::generator(),
+                public_key: G2::generator() * &secret,
+            },
+            secret,
+            members: BTreeSet::new(),
+        }
+    }
+
+    pub fn accumulator(&self) -> &Accumulator {
+        &self.accumulator
+    }
+
+    /// Allow `signature`. Every witness made before has to be updated. Adding a signature twice
+    /// changes nothing.
+    pub fn add(&mut self, signature: &Signature) {
+        let x = element(&signature.a);
+        if self.members.insert(x.clone()) {
+            self.accumulator.value = &self.accumulator.value * &(x + &self.secret);
+        }
+    }
+
+    pub fn contains(&self, signature: &Signature) -> bool {
+        self.members.contains(&element(&signature.a))
+    }
+
+    /// Witness for the current accumulator, `None` if `signature` isn't allowed
+    pub fn witness(&self, signature: &Signature) -> Option<AllowlistWitness> {
+        let x = element(&signature.a);
+        if !self.members.contains(&x) {
+            return None;
+        }
+        Some(AllowlistWitness {
+            w: &self.accumulator.value * &(x + &self.secret).inverse(),
+        })
+    }
+}
+
+impl Default for AllowlistManager {
+    fn default() -> Self {
+        AllowlistManager::new()
+    }
+}
+
+impl AllowlistWitness {
+    /// Witness of `signature` after `added` was allowed, from the accumulator value before
+    /// the addition: `W' = V * W^(y - x)` for the added element `y`
+    pub fn update(&self, signature: &Signature, added: &Signature, old_value: &G1) -> Self {
+        let diff = element(&added.a) - element(&signature.a);
+        AllowlistWitness {
+            w: old_value + &self.w * &diff,
+        }
+    }
+}
+
+impl Accumulator {
+    /// Whether `witness` shows `signature` is allowed
+    pub fn verify_witness(&self, signature: &Signature, witness: &AllowlistWitness) -> bool {
+        let q = &(G2::generator() * &element(&signature.a)) + &self.public_key;
+        GT::ate_2_pairing(&witness.w, &q, &(-&self.value), &G2::generator()).is_one()
+    }
+}
+
+impl Signature {
+    /// `verify` and that `witness` shows the signature is in `allowlist`
+    pub fn verify_allowlisted(
+        &self,
+        messages: &[SignatureMessage],
+        verkey: &PublicKey,
+        allowlist: &Accumulator,
+        witness: &AllowlistWitness,
+    ) -> Result<bool, BBSError> {
+        if !allowlist.verify_witness(self, witness) {
+            return Ok(false);
+        }
+        self.verify(messages, verkey)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amcl_wrapper::field_elem::FieldElementVector;
+    use signatures::bbs::keys::generate;
+
+    #[test]
+    fn allowlisted_signature() {
+        let messages = FieldElementVector::random(2);
+        let (verkey, signkey) = generate(2).unwrap();
+        let allowed = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+        let other = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+
+        let mut manager = AllowlistManager::new();
+        manager.add(&allowed);
+        assert!(manager.contains(&allowed) && !manager.contains(&other));
+        let witness = manager.witness(&allowed).unwrap();
+        let accumulator = manager.accumulator().clone();
+        assert!(allowed
+            .verify_allowlisted(messages.as_slice(), &verkey, &accumulator, &witness)
+            .unwrap());
+
+        // Not allowed: no witness, and another's witness doesn't fit
+        assert!(manager.witness(&other).is_none());
+        assert!(!other
+            .verify_allowlisted(messages.as_slice(), &verkey, &accumulator, &witness)
+            .unwrap());
+        // Allowed but over other messages
+        assert!(!allowed
+            .verify_allowlisted(
+                FieldElementVector::random(2).as_slice(),
+                &verkey,
+                &accumulator,
+                &witness
+            )
+            .unwrap());
+
+        // Adding another signature invalidates the witness until it is updated
+        let old_value = accumulator.value.clone();
+        manager.add(&other);
+        let accumulator = manager.accumulator().clone();
+        assert!(!accumulator.verify_witness(&allowed, &witness));
+        let updated = witness.update(&allowed, &other, &old_value);
+        assert_eq!(updated, manager.witness(&allowed).unwrap());
+        assert!(accumulator.verify_witness(&allowed, &updated));
+        assert!(accumulator.verify_witness(&other, &manager.witness(&other).unwrap()));
+
+        // Adding again is a no-op
+        manager.add(&other);
+        assert_eq!(manager.accumulator(), &accumulator);
+    }
+}
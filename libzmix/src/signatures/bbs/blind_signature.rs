@@ -0,0 +1,394 @@
+// Requesting a blind signature over any set of hidden messages
+
+use super::super::{SignatureBlinding, SignatureMessage};
+use super::keys::PublicKey;
+use super::pok_sig::{ProofG1, ProverCommittingG1};
+use super::signature::Signature;
+use crate::errors::prelude::*;
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
+use amcl_wrapper::group_elem::{GroupElement, GroupElementVector};
+use amcl_wrapper::group_elem_g1::{G1Vector, G1};
+
+pub mod prelude {
+    pub use super::{merge_commitments, BlindSignatureContext, MultiBlindCommitment};
+}
+
+/// Commitment `h0^blinding * h_i^m_i...` to every hidden message `m_i` the holder wants signed
+/// without the signer learning them, and a proof of knowledge of the blinding and every hidden
+/// message. The blinding is shared by all hidden messages and kept by the holder to unblind the
+/// signature with `Signature::get_unblinded_signature`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MultiBlindCommitment {
+    pub commitment: G1,
+    pub hidden_indices: BTreeSet<usize>,
+    pub proof: ProofG1,
+}
+
+impl MultiBlindCommitment {
+    /// Commit to the messages in `hidden`, keyed by their index under `verkey`.
+    /// Returns the commitment and the blinding needed to unblind the signature.
+    pub fn new(
+        verkey: &PublicKey,
+        hidden: &BTreeMap<usize, SignatureMessage>,
+    ) -> Result<(Self, SignatureBlinding), BBSError> {
+        Self::new_with_blinding(verkey, hidden, Signature::generate_blinding())
+    }
+
+    /// Same as `new` but with a caller chosen blinding, e.g. one from `derive_blinding`.
+    /// The blinding must never be reused across commitments.
+    pub fn new_with_blinding(
+        verkey: &PublicKey,
+        hidden: &BTreeMap<usize, SignatureMessage>,
+        blinding: SignatureBlinding,
+    ) -> Result<(Self, SignatureBlinding), BBSError> {
+        Self::create(verkey, hidden, blinding, &[])
+    }
+
+    // The proof's challenge also covers `nonce`, empty for commitments without one
+    fn create(
+        verkey: &PublicKey,
+        hidden: &BTreeMap<usize, SignatureMessage>,
+        blinding: SignatureBlinding,
+        nonce: &[u8],
+    ) -> Result<(Self, SignatureBlinding), BBSError> {
+        Self::check_indices(verkey, hidden.keys())?;
+
+        let (bases, hidden_indices) = Self::bases(verkey, hidden.keys());
+        let mut secrets = FieldElementVector::with_capacity(bases.len());
+        secrets.push(blinding.clone());
+        for m in hidden.values() {
+            secrets.push(m.clone());
+        }
+        let commitment = bases.multi_scalar_mul_const_time(&secrets).unwrap();
+
+        // Each hidden message gets its own random blinding in the proof
+        let mut committing = ProverCommittingG1::new();
+        for b in bases.as_slice() {
+            committing.commit(b, None);
+        }
+        let committed = committing.finish();
+        let challenge = Self::challenge(&bases, &committed.commitment, &commitment, nonce);
+        let proof = committed.gen_proof(&challenge, secrets.as_slice())?;
+        Ok((
+            MultiBlindCommitment {
+                commitment,
+                hidden_indices,
+                proof,
+            },
+            blinding,
+        ))
+    }
+
+    /// Run by the signer before signing over `self.commitment`
+    pub fn verify(&self, verkey: &PublicKey) -> Result<bool, BBSError> {
+        self.verify_with_nonce(verkey, &[])
+    }
+
+    fn verify_with_nonce(&self, verkey: &PublicKey, nonce: &[u8]) -> Result<bool, BBSError> {
+        Self::check_indices(verkey, self.hidden_indices.iter())?;
+        let (bases, _) = Self::bases(verkey, self.hidden_indices.iter());
+        let challenge = Self::challenge(&bases, &self.proof.commitment, &self.commitment, nonce);
+        Ok(self
+            .proof
+            .verify(bases.as_slice(), &self.commitment, &challenge)?)
+    }
+
+    /// Signer side check of commitments from several holders before signing over their
+    /// merge, see `merge_commitments`. Every proof must hold and no index may be hidden by
+    /// more than one holder.
+    pub fn verify_merged(
+        parts: &[MultiBlindCommitment],
+        verkey: &PublicKey,
+    ) -> Result<bool, BBSError> {
+        let mut seen = BTreeSet::new();
+        for part in parts {
+            if !part.hidden_indices.iter().all(|i| seen.insert(*i)) {
+                return Ok(false);
+            }
+            if !part.verify(verkey)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    fn check_indices<'a, I>(verkey: &PublicKey, indices: I) -> Result<(), BBSError>
+    where
+        I: Iterator<Item = &'a usize>,
+    {
+        let mut count = 0;
+        for i in indices {
+            if *i >= verkey.message_count() {
+                return Err(BBSError::from_kind(BBSErrorKind::GeneralError {
+                    msg: format!("Index {} should be less than {}", i, verkey.message_count()),
+                }));
+            }
+            count += 1;
+        }
+        if count == 0 {
+            return Err(BBSError::from_kind(BBSErrorKind::GeneralError {
+                msg: "At least one message should be hidden".to_string(),
+            }));
+        }
+        Ok(())
+    }
+
+    // h0 followed by the base of every hidden message
+    fn bases<'a, I>(verkey: &PublicKey, indices: I) -> (G1Vector, BTreeSet<usize>)
+    where
+        I: Iterator<Item = &'a usize>,
+    {
+        let indices: BTreeSet<usize> = indices.cloned().collect();
+        let mut bases = G1Vector::with_capacity(indices.len() + 1);
+        bases.push(verkey.h0.clone());
+        for i in &indices {
+            bases.push(verkey.h[*i].clone());
+        }
+        (bases, indices)
+    }
+
+    fn challenge(
+        bases: &G1Vector,
+        proof_commitment: &G1,
+        commitment: &G1,
+        nonce: &[u8],
+    ) -> FieldElement {
+        let mut bytes = vec![];
+        for b in bases.as_slice() {
+            bytes.append(&mut b.to_bytes());
+        }
+        bytes.append(&mut proof_commitment.to_bytes());
+        bytes.append(&mut commitment.to_bytes());
+        bytes.extend_from_slice(nonce);
+        FieldElement::from_msg_hash(&bytes)
+    }
+}
+
+/// Requestor side of blind signing: the commitment to the hidden messages and its proof of
+/// knowledge bound to a `nonce` from the signer, so a proof can't be replayed in another
+/// signing session. Built like `MultiBlindCommitment` with one fresh blinding for all hidden
+/// messages, without the caller touching the key's bases.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlindSignatureContext {
+    pub commitment: G1,
+    pub hidden_indices: BTreeSet<usize>,
+    pub proof: ProofG1,
+}
+
+impl BlindSignatureContext {
+    /// Commit to `hidden`, keyed by index under `verkey`. Returns the context for the signer
+    /// and the blinding to unblind the signature with.
+    pub fn new(
+        verkey: &PublicKey,
+        hidden: &BTreeMap<usize, SignatureMessage>,
+        nonce: &[u8],
+    ) -> Result<(Self, SignatureBlinding), BBSError> {
+        let (c, blinding) =
+            MultiBlindCommitment::create(verkey, hidden, Signature::generate_blinding(), nonce)?;
+        Ok((
+            BlindSignatureContext {
+                commitment: c.commitment,
+                hidden_indices: c.hidden_indices,
+                proof: c.proof,
+            },
+            blinding,
+        ))
+    }
+
+    /// Run by the signer before `Signature::new_with_committed_messages_at` over `commitment`
+    /// and the messages at `revealed_indices`. Fails if any of those is also hidden, the
+    /// signed message there would be the sum of both values.
+    pub fn verify_proof(
+        &self,
+        revealed_indices: &BTreeSet<usize>,
+        verkey: &PublicKey,
+        nonce: &[u8],
+    ) -> Result<bool, BBSError> {
+        if let Some(i) = revealed_indices.intersection(&self.hidden_indices).next() {
+            return Err(BBSError::from_kind(BBSErrorKind::GeneralError {
+                msg: format!("Index {} is both hidden and revealed", i),
+            }));
+        }
+        MultiBlindCommitment {
+            commitment: self.commitment.clone(),
+            hidden_indices: self.hidden_indices.clone(),
+            proof: self.proof.clone(),
+        }
+        .verify_with_nonce(verkey, nonce)
+    }
+}
+
+/// Combine commitments of two holders into one the signer issues over. The result commits to
+/// the hidden messages of both under the sum of their blindings, so after signing each holder
+/// unblinds with its own blinding, one after the other, and both have to share the result.
+/// The hidden index sets must be disjoint: at an index hidden by both the bases add up and the
+/// signed message would be the sum of two values, which neither holder chose. Check with
+/// `MultiBlindCommitment::verify_merged` before signing.
+pub fn merge_commitments(c1: &G1, c2: &G1) -> G1 {
+    c1 + c2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use signatures::bbs::keys::generate;
+    use signatures::bbs::messages::derive_blinding;
+
+    #[test]
+    fn multi_blind_commitment() {
+        let message_count = 6;
+        let messages = FieldElementVector::random(message_count);
+        let (verkey, signkey) = generate(message_count).unwrap();
+
+        let mut hidden = BTreeMap::new();
+        let mut known = BTreeMap::new();
+        for i in 0..message_count {
+            if i == 1 || i == 3 || i == 4 {
+                hidden.insert(i, messages[i].clone());
+            } else {
+                known.insert(i, messages[i].clone());
+            }
+        }
+
+        let (blind_commitment, blinding) = MultiBlindCommitment::new(&verkey, &hidden).unwrap();
+        assert_eq!(blind_commitment.hidden_indices.len(), 3);
+        // Signer checks the proof before signing
+        assert!(blind_commitment.verify(&verkey).unwrap());
+
+        let blind_sig = Signature::new_with_committed_messages_at(
+            &blind_commitment.commitment,
+            &known,
+            &signkey,
+            &verkey,
+        )
+        .unwrap();
+        assert!(!blind_sig.verify(messages.as_slice(), &verkey).unwrap());
+        let sig = blind_sig.get_unblinded_signature(&blinding);
+        assert!(sig.verify(messages.as_slice(), &verkey).unwrap());
+
+        // Proof doesn't hold for other hidden indices
+        let mut tampered = blind_commitment.clone();
+        tampered.hidden_indices.remove(&4);
+        tampered.hidden_indices.insert(5);
+        assert!(!tampered.verify(&verkey).unwrap());
+
+        let mut out_of_range = hidden.clone();
+        out_of_range.insert(message_count, FieldElement::random());
+        assert!(MultiBlindCommitment::new(&verkey, &out_of_range).is_err());
+        assert!(MultiBlindCommitment::new(&verkey, &BTreeMap::new()).is_err());
+    }
+
+    #[test]
+    fn merged_blind_commitments() {
+        let message_count = 5;
+        let messages = FieldElementVector::random(message_count);
+        let (verkey, signkey) = generate(message_count).unwrap();
+
+        let mut hidden_1 = BTreeMap::new();
+        hidden_1.insert(0, messages[0].clone());
+        hidden_1.insert(3, messages[3].clone());
+        let mut hidden_2 = BTreeMap::new();
+        hidden_2.insert(1, messages[1].clone());
+        let mut known = BTreeMap::new();
+        known.insert(2, messages[2].clone());
+        known.insert(4, messages[4].clone());
+
+        let (part_1, blinding_1) = MultiBlindCommitment::new(&verkey, &hidden_1).unwrap();
+        let (part_2, blinding_2) = MultiBlindCommitment::new(&verkey, &hidden_2).unwrap();
+        let parts = vec![part_1.clone(), part_2.clone()];
+        assert!(MultiBlindCommitment::verify_merged(&parts, &verkey).unwrap());
+
+        let merged = merge_commitments(&part_1.commitment, &part_2.commitment);
+        let blind_sig =
+            Signature::new_with_committed_messages_at(&merged, &known, &signkey, &verkey).unwrap();
+        let sig = blind_sig
+            .get_unblinded_signature(&blinding_1)
+            .get_unblinded_signature(&blinding_2);
+        assert!(sig.verify(messages.as_slice(), &verkey).unwrap());
+        // Both parts are needed
+        let partly = blind_sig.get_unblinded_signature(&blinding_1);
+        assert!(!partly.verify(messages.as_slice(), &verkey).unwrap());
+
+        // Overlapping hidden indices are refused
+        let mut overlapping = hidden_2.clone();
+        overlapping.insert(3, messages[3].clone());
+        let (part_3, _) = MultiBlindCommitment::new(&verkey, &overlapping).unwrap();
+        assert!(!MultiBlindCommitment::verify_merged(&[part_1, part_3], &verkey).unwrap());
+    }
+
+    #[test]
+    fn derived_blinding_commitment() {
+        let seed = b"wallet seed with enough entropy!";
+        let blinding = derive_blinding(seed, b"credential 1");
+        assert_eq!(blinding, derive_blinding(seed, b"credential 1"));
+        assert_ne!(blinding, derive_blinding(seed, b"credential 2"));
+        assert_ne!(blinding, derive_blinding(b"another seed", b"credential 1"));
+
+        let message_count = 3;
+        let messages = FieldElementVector::random(message_count);
+        let (verkey, signkey) = generate(message_count).unwrap();
+        let mut hidden = BTreeMap::new();
+        hidden.insert(0, messages[0].clone());
+        let mut known = BTreeMap::new();
+        known.insert(1, messages[1].clone());
+        known.insert(2, messages[2].clone());
+
+        let (commitment, returned) =
+            MultiBlindCommitment::new_with_blinding(&verkey, &hidden, blinding.clone()).unwrap();
+        assert_eq!(returned, blinding);
+        assert!(commitment.verify(&verkey).unwrap());
+        let blind_sig = Signature::new_with_committed_messages_at(
+            &commitment.commitment,
+            &known,
+            &signkey,
+            &verkey,
+        )
+        .unwrap();
+        // Recovered from the seed alone
+        let recovered = derive_blinding(seed, b"credential 1");
+        let sig = blind_sig.get_unblinded_signature(&recovered);
+        assert!(sig.verify(messages.as_slice(), &verkey).unwrap());
+    }
+
+    #[test]
+    fn blind_signature_context() {
+        let message_count = 4;
+        let messages = FieldElementVector::random(message_count);
+        let (verkey, signkey) = generate(message_count).unwrap();
+        let nonce = b"signer nonce 1";
+
+        // Requestor
+        let mut hidden = BTreeMap::new();
+        hidden.insert(0, messages[0].clone());
+        hidden.insert(2, messages[2].clone());
+        let (context, blinding) = BlindSignatureContext::new(&verkey, &hidden, nonce).unwrap();
+
+        // Signer
+        let mut known = BTreeMap::new();
+        known.insert(1, messages[1].clone());
+        known.insert(3, messages[3].clone());
+        let revealed: BTreeSet<usize> = known.keys().cloned().collect();
+        assert!(context.verify_proof(&revealed, &verkey, nonce).unwrap());
+        let blind_sig = Signature::new_with_committed_messages_at(
+            &context.commitment,
+            &known,
+            &signkey,
+            &verkey,
+        )
+        .unwrap();
+
+        // Requestor unblinds
+        let sig = blind_sig.get_unblinded_signature(&blinding);
+        assert!(sig.verify(messages.as_slice(), &verkey).unwrap());
+
+        // Replayed in another session
+        assert!(!context
+            .verify_proof(&revealed, &verkey, b"signer nonce 2")
+            .unwrap());
+        let overlapping: BTreeSet<usize> = vec![1, 2].into_iter().collect();
+        assert!(context.verify_proof(&overlapping, &verkey, nonce).is_err());
+    }
+}
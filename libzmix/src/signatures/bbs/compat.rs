@@ -0,0 +1,167 @@
+// Upgrading stored signatures and public keys written by earlier versions of this crate to the
+// current encoding, so a deployment can rewrite its data in place.
+//
+// Formats, numbered from the first release of the BBS+ module:
+//
+// | Version | Signature                                   | Public key                          |
+// |---------|---------------------------------------------|-------------------------------------|
+// | 1       | `a` uncompressed, `e`, `s`, 193 bytes, `a`  | `w`, `h0`, count, every `h`, all    |
+// |         | not checked for identity or subgroup        | `G1` bases uncompressed             |
+// | 2       | `to_bytes`: same layout, `a` a non identity | `to_bytes`: same layout, `G1` bases |
+// |         | point of the prime order subgroup and `e`,  | compressed; `to_uncompressed_bytes` |
+// |         | `s` non zero                                | as version 1; `to_fixed_bytes`: `w`,|
+// |         | `to_bytes_compressed`: `a` compressed, 145  | `h0`, every `h` uncompressed, no    |
+// |         | bytes, same checks                          | count                               |
+//
+// Each version has its own parser: version 1 takes only its single layout, version 2 every
+// encoding of that version, for keys the count prefixed ones first and `to_fixed_bytes` if
+// those don't parse. An upgrade writes with `to_bytes` of `FORMAT_VERSION`, so it also
+// normalizes buffers already in the current version. Upgrading a version 1 key shrinks it, a
+// version 1 signature keeps its bytes unless the checks of version 2 refuse it, a signature
+// with `a` the identity or outside the subgroup never verified and can be dropped.
+
+use super::keys::PublicKey;
+use super::signature::{Signature, SIGNATURE_SIZE};
+use crate::errors::prelude::*;
+
+pub mod prelude {
+    pub use super::{upgrade_public_key_bytes, upgrade_signature_bytes, FORMAT_VERSION};
+}
+
+/// Version of the encoding written by `Signature::to_bytes` and `PublicKey::to_bytes`, see the
+/// top of `compat.rs`
+pub const FORMAT_VERSION: u32 = 2;
+
+fn unknown_version(from_version: u32) -> BBSError {
+    BBSError::from_kind(BBSErrorKind::GeneralError {
+        msg: format!(
+            "Unknown format version {}, known versions are 1 to {}",
+            from_version, FORMAT_VERSION
+        ),
+    })
+}
+
+fn malformed_key(from_version: u32) -> BBSError {
+    BBSError::from_kind(BBSErrorKind::GeneralError {
+        msg: format!("Public key of format {} doesn't parse", from_version),
+    })
+}
+
+/// Signature bytes written in format `from_version` re-encoded in `FORMAT_VERSION`. Fails for
+/// an unknown version, for bytes not in an encoding of that version and for version 1
+/// signatures the checks of version 2 refuse.
+pub fn upgrade_signature_bytes(old_bytes: &[u8], from_version: u32) -> Result<Vec<u8>, BBSError> {
+    let signature = match from_version {
+        1 => {
+            // Only the uncompressed layout, its values are then held to the version 2 checks
+            if old_bytes.len() != SIGNATURE_SIZE {
+                return Err(BBSError::from_kind(BBSErrorKind::SignatureIncorrectSize(
+                    old_bytes.len(),
+                )));
+            }
+            Signature::from_bytes(old_bytes)?
+        }
+        2 => Signature::from_bytes(old_bytes)?,
+        _ => return Err(unknown_version(from_version)),
+    };
+    Ok(signature.to_bytes())
+}
+
+/// Public key bytes written in format `from_version` re-encoded in `FORMAT_VERSION`. Fails for
+/// an unknown version and for bytes not in an encoding of that version.
+pub fn upgrade_public_key_bytes(old_bytes: &[u8], from_version: u32) -> Result<Vec<u8>, BBSError> {
+    let verkey = match from_version {
+        1 => {
+            // `w`, `h0` and the count, then uncompressed bases only, so the bytes have to be
+            // the uncompressed encoding of what they parse to
+            let verkey =
+                PublicKey::from_bytes(old_bytes).map_err(|_| malformed_key(from_version))?;
+            if verkey.to_uncompressed_bytes() != old_bytes {
+                return Err(malformed_key(from_version));
+            }
+            verkey
+        }
+        2 => match PublicKey::from_bytes(old_bytes) {
+            Ok(verkey) => verkey,
+            Err(_) => {
+                PublicKey::from_fixed_bytes(old_bytes).map_err(|_| malformed_key(from_version))?
+            }
+        },
+        _ => return Err(unknown_version(from_version)),
+    };
+    Ok(verkey.to_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amcl_wrapper::field_elem::FieldElementVector;
+    use amcl_wrapper::group_elem::GroupElement;
+    use amcl_wrapper::group_elem_g1::G1;
+    use signatures::bbs::keys::generate;
+
+    #[test]
+    fn upgrade_version_1() {
+        let messages = FieldElementVector::random(3);
+        let (verkey, signkey) = generate(3).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+
+        // A version 1 signature buffer is `a` uncompressed, `e` and `s`
+        let mut v1_sig = sig.a.to_bytes();
+        v1_sig.append(&mut sig.e.to_bytes());
+        v1_sig.append(&mut sig.s.to_bytes());
+        assert_eq!(v1_sig.len(), SIGNATURE_SIZE);
+        let upgraded = upgrade_signature_bytes(&v1_sig, 1).unwrap();
+        let parsed = Signature::from_bytes(&upgraded).unwrap();
+        assert!(parsed.verify(messages.as_slice(), &verkey).unwrap());
+
+        // A version 1 key has uncompressed bases and shrinks
+        let v1_key = verkey.to_uncompressed_bytes();
+        let upgraded = upgrade_public_key_bytes(&v1_key, 1).unwrap();
+        assert!(upgraded.len() < v1_key.len());
+        assert_eq!(upgraded, verkey.to_bytes());
+        let parsed = PublicKey::from_bytes(&upgraded).unwrap();
+        assert!(sig.verify(messages.as_slice(), &parsed).unwrap());
+
+        // Current buffers come back as they were
+        assert_eq!(
+            upgrade_signature_bytes(&sig.to_bytes(), FORMAT_VERSION).unwrap(),
+            sig.to_bytes()
+        );
+        assert_eq!(
+            upgrade_public_key_bytes(&verkey.to_bytes(), FORMAT_VERSION).unwrap(),
+            verkey.to_bytes()
+        );
+
+        // Version 1 stored signatures with `a` the identity, version 2 doesn't
+        let mut identity = G1::identity().to_bytes();
+        identity.append(&mut sig.e.to_bytes());
+        identity.append(&mut sig.s.to_bytes());
+        assert!(upgrade_signature_bytes(&identity, 1).is_err());
+
+        // Encodings of version 2 only, compressed or without a count, aren't version 1
+        let compressed = sig.to_bytes_compressed();
+        assert!(upgrade_signature_bytes(&compressed, 1).is_err());
+        assert_eq!(
+            upgrade_signature_bytes(&compressed, 2).unwrap(),
+            sig.to_bytes()
+        );
+        let fixed = verkey.to_fixed_bytes();
+        assert!(upgrade_public_key_bytes(&fixed, 1).is_err());
+        assert!(upgrade_public_key_bytes(&verkey.to_bytes(), 1).is_err());
+        assert_eq!(
+            upgrade_public_key_bytes(&fixed, 2).unwrap(),
+            verkey.to_bytes()
+        );
+        assert_eq!(
+            upgrade_public_key_bytes(&v1_key, 2).unwrap(),
+            verkey.to_bytes()
+        );
+
+        for version in &[0, FORMAT_VERSION + 1] {
+            assert!(upgrade_signature_bytes(&v1_sig, *version).is_err());
+            assert!(upgrade_public_key_bytes(&v1_key, *version).is_err());
+        }
+        assert!(upgrade_public_key_bytes(&v1_key[..v1_key.len() - 1], 1).is_err());
+    }
+}
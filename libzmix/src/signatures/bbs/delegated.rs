@@ -0,0 +1,178 @@
+// Delegated verification: a first verifier, who saw every message, hands a relying party the
+// values of some messages and only `C_i = h_i^m_i` for the others, each with a proof of
+// knowledge of `m_i`. `C_i` is exactly the term `m_i` contributes to `b`, so the relying party
+// can check the signature without learning `m_i`.
+//
+// Trust model: the signature check shows the `C_i` are the terms of the signed messages, so the
+// relying party can't be given a commitment to a message that wasn't signed. What it has to
+// take from the first verifier is any judgement about the committed values themselves, e.g.
+// that they met a policy. `C_i` is deterministic, so it hides `m_i` only when `m_i` has high
+// entropy: a low entropy message can be found by trying every candidate. The same message
+// always gives the same `C_i`, making presentations linkable, and the proofs carry no nonce so
+// anyone holding them can pass them on.
+
+use super::super::SignatureMessage;
+use super::keys::PublicKey;
+use super::signature::Signature;
+use crate::errors::prelude::*;
+
+use amcl_wrapper::constants::{FieldElement_SIZE, GroupG1_SIZE};
+use amcl_wrapper::extension_field_gt::GT;
+use amcl_wrapper::field_elem::FieldElement;
+use amcl_wrapper::group_elem::GroupElement;
+use amcl_wrapper::group_elem_g1::G1;
+use amcl_wrapper::group_elem_g2::G2;
+
+pub mod prelude {
+    pub use super::{commit_for_delegation, ProofBytes};
+}
+
+/// Serialized proof of knowledge of `m_i` in `C_i = h_i^m_i`: the commitment of the Schnorr
+/// proof followed by its response
+pub type ProofBytes = Vec<u8>;
+
+const PROOF_SIZE: usize = GroupG1_SIZE + FieldElement_SIZE;
+
+fn challenge(index: usize, base: &G1, commitment: &G1, t: &G1) -> FieldElement {
+    let mut bytes = (index as u32).to_be_bytes().to_vec();
+    bytes.append(&mut base.to_bytes());
+    bytes.append(&mut commitment.to_bytes());
+    bytes.append(&mut t.to_bytes());
+    FieldElement::from_msg_hash(&bytes)
+}
+
+/// Run by the first verifier for each message the relying party should only get committed.
+/// Returns `C_i` and the proof to pass to `Signature::verify_delegated`.
+pub fn commit_for_delegation(
+    index: usize,
+    message: &SignatureMessage,
+    verkey: &PublicKey,
+) -> Result<(G1, ProofBytes), BBSError> {
+    let base = verkey.h.get(index).ok_or_else(|| {
+        BBSError::from_kind(BBSErrorKind::GeneralError {
+            msg: format!(
+                "Index {} should be less than {}",
+                index,
+                verkey.message_count()
+            ),
+        })
+    })?;
+    let commitment = base * message;
+    let blinding = FieldElement::random();
+    let t = base * &blinding;
+    let c = challenge(index, base, &commitment, &t);
+    let response = &blinding - &(&c * message);
+    let mut proof = t.to_bytes();
+    proof.append(&mut response.to_bytes());
+    Ok((commitment, proof))
+}
+
+fn verify_proof(index: usize, base: &G1, commitment: &G1, proof: &[u8]) -> Result<bool, BBSError> {
+    if proof.len() != PROOF_SIZE {
+        return Err(BBSError::from_kind(BBSErrorKind::GeneralError {
+            msg: format!(
+                "Proof for index {} should be {} bytes, found {}",
+                index,
+                PROOF_SIZE,
+                proof.len()
+            ),
+        }));
+    }
+    let malformed = |_| {
+        BBSError::from_kind(BBSErrorKind::GeneralError {
+            msg: format!("Malformed proof for index {}", index),
+        })
+    };
+    let t = G1::from_bytes(&proof[..GroupG1_SIZE]).map_err(malformed)?;
+    let response = FieldElement::from_bytes(&proof[GroupG1_SIZE..]).map_err(malformed)?;
+    let c = challenge(index, base, commitment, &t);
+    Ok(base.binary_scalar_mul(commitment, &response, &c) == t)
+}
+
+impl Signature {
+    /// Verify with the values of the `known` messages and only the commitments of the
+    /// `committed` ones, see `commit_for_delegation` and the trust model at the top of
+    /// `delegated.rs`. Both are given as `(index, ..)` and together must cover every index of
+    /// `verkey` exactly once. False if a proof or the signature doesn't hold.
+    pub fn verify_delegated(
+        &self,
+        known: &[(usize, FieldElement)],
+        committed: &[(usize, G1, ProofBytes)],
+        verkey: &PublicKey,
+    ) -> Result<bool, BBSError> {
+        let message_count = verkey.message_count();
+        if known.len() + committed.len() != message_count {
+            return Err(BBSError::from_kind(BBSErrorKind::message_count(
+                message_count,
+                known.len() + committed.len(),
+            )));
+        }
+        let mut seen = vec![false; message_count];
+        let indices = known
+            .iter()
+            .map(|(i, _)| *i)
+            .chain(committed.iter().map(|(i, _, _)| *i));
+        for i in indices {
+            if i >= message_count || seen[i] {
+                return Err(BBSError::from_kind(BBSErrorKind::GeneralError {
+                    msg: format!("Index {} is out of range or given twice", i),
+                }));
+            }
+            seen[i] = true;
+        }
+
+        let mut b = G1::generator() + &verkey.h0 * &self.s;
+        for (i, m) in known {
+            b += &verkey.h[*i] * m;
+        }
+        for (i, commitment, proof) in committed {
+            if !verify_proof(*i, &verkey.h[*i], commitment, proof)? {
+                return Ok(false);
+            }
+            b += commitment;
+        }
+        let a = (&G2::generator() * &self.e) + &verkey.w;
+        Ok(GT::ate_2_pairing(&self.a, &a, &(-&b), &G2::generator()).is_one())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use signatures::bbs::keys::generate;
+
+    #[test]
+    fn delegated_verification() {
+        let messages = vec![FieldElement::random(), FieldElement::random()];
+        let (verkey, signkey) = generate(2).unwrap();
+        let sig = Signature::new(&messages, &signkey, &verkey).unwrap();
+
+        let (commitment, proof) = commit_for_delegation(1, &messages[1], &verkey).unwrap();
+        let known = vec![(0, messages[0].clone())];
+        let committed = vec![(1, commitment.clone(), proof.clone())];
+        assert!(sig.verify_delegated(&known, &committed, &verkey).unwrap());
+
+        // Commitment to another message
+        let (other, other_proof) =
+            commit_for_delegation(1, &FieldElement::random(), &verkey).unwrap();
+        assert!(!sig
+            .verify_delegated(&known, &[(1, other, other_proof)], &verkey)
+            .unwrap());
+        // Proof doesn't fit the commitment
+        let (_, wrong_proof) = commit_for_delegation(1, &FieldElement::random(), &verkey).unwrap();
+        assert!(!sig
+            .verify_delegated(&known, &[(1, commitment.clone(), wrong_proof)], &verkey)
+            .unwrap());
+        // Wrong known value
+        assert!(!sig
+            .verify_delegated(&[(0, FieldElement::random())], &committed, &verkey)
+            .unwrap());
+
+        assert!(sig.verify_delegated(&known, &[], &verkey).is_err());
+        let twice = vec![(0, commitment.clone(), proof.clone())];
+        assert!(sig.verify_delegated(&known, &twice, &verkey).is_err());
+        let truncated = vec![(1, commitment, proof[1..].to_vec())];
+        assert!(sig.verify_delegated(&known, &truncated, &verkey).is_err());
+        assert!(commit_for_delegation(2, &messages[0], &verkey).is_err());
+    }
+}
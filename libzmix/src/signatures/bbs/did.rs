@@ -0,0 +1,151 @@
+// Public keys as DID Document verification methods
+//
+// The key material is `PublicKey::to_bytes` as a base58btc multibase string (prefix `z`).
+// That is the whole key, `w` and every message base, since the bases here are random per key
+// and can't be derived by the verifier. The standard `Bls12381G2Key2020` type carries only `w`,
+// a compressed `G2` point behind the 0xeb01 multicodec, which amcl can't write, so methods here
+// use a type of this crate's own and no other implementation mistakes them for theirs.
+
+use super::keys::PublicKey;
+use crate::errors::prelude::*;
+
+pub mod prelude {
+    pub use super::VERIFICATION_METHOD_TYPE;
+}
+
+/// `type` of the verification method, specific to this crate, see the top of `did.rs`
+pub const VERIFICATION_METHOD_TYPE: &str = "ZmixBbsPlusPublicKey";
+
+const BASE58_ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+const MULTIBASE_BASE58BTC: char = 'z';
+
+fn base58_encode(data: &[u8]) -> String {
+    let zeros = data.iter().take_while(|b| **b == 0).count();
+    // Base 58 digits of the number `data` encodes, least significant first
+    let mut digits: Vec<u8> = Vec::with_capacity(data.len() * 138 / 100 + 1);
+    for byte in &data[zeros..] {
+        let mut carry = *byte as u32;
+        for d in digits.iter_mut() {
+            carry += (*d as u32) << 8;
+            *d = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    let mut out = "1".repeat(zeros);
+    out.extend(
+        digits
+            .iter()
+            .rev()
+            .map(|d| BASE58_ALPHABET[*d as usize] as char),
+    );
+    out
+}
+
+fn base58_decode(s: &str) -> Option<Vec<u8>> {
+    let zeros = s.bytes().take_while(|c| *c == b'1').count();
+    // Bytes of the number, least significant first
+    let mut bytes: Vec<u8> = Vec::with_capacity(s.len());
+    for c in s.bytes().skip(zeros) {
+        let mut carry = BASE58_ALPHABET.iter().position(|a| *a == c)? as u32;
+        for b in bytes.iter_mut() {
+            carry += (*b as u32) * 58;
+            *b = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+    let mut out = vec![0u8; zeros];
+    out.extend(bytes.iter().rev());
+    Some(out)
+}
+
+fn malformed(msg: &str) -> BBSError {
+    BBSError::from_kind(BBSErrorKind::GeneralError {
+        msg: format!("Invalid verification method: {}", msg),
+    })
+}
+
+impl PublicKey {
+    /// Verification method `id` for a DID Document, the controller is `id` without its
+    /// fragment. See the top of `did.rs` for what the key material holds.
+    pub fn to_verification_method(&self, id: &str) -> serde_json::Value {
+        let controller = id.split('#').next().unwrap_or(id);
+        let mut material = MULTIBASE_BASE58BTC.to_string();
+        material.push_str(&base58_encode(&self.to_bytes()));
+        serde_json::json!({
+            "id": id,
+            "type": VERIFICATION_METHOD_TYPE,
+            "controller": controller,
+            "publicKeyMultibase": material,
+        })
+    }
+
+    /// Key of a verification method made by `to_verification_method`
+    pub fn from_verification_method(method: &serde_json::Value) -> Result<Self, BBSError> {
+        if method.get("type").and_then(|t| t.as_str()) != Some(VERIFICATION_METHOD_TYPE) {
+            return Err(malformed("unsupported type"));
+        }
+        let material = method
+            .get("publicKeyMultibase")
+            .and_then(|m| m.as_str())
+            .ok_or_else(|| malformed("missing publicKeyMultibase"))?;
+        if !material.starts_with(MULTIBASE_BASE58BTC) {
+            return Err(malformed("key material is not base58btc"));
+        }
+        let bytes = base58_decode(&material[1..])
+            .ok_or_else(|| malformed("key material is not base58btc"))?;
+        PublicKey::from_bytes(&bytes)
+            .map_err(|_| BBSError::from_kind(BBSErrorKind::MalformedPublicKey))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use signatures::bbs::keys::generate;
+
+    #[test]
+    fn base58_known_values() {
+        assert_eq!(base58_encode(b""), "");
+        assert_eq!(base58_encode(b"hello world"), "StV1DL6CwTryKyV");
+        assert_eq!(base58_encode(&[0, 0, 1]), "112");
+        assert_eq!(base58_decode("StV1DL6CwTryKyV").unwrap(), b"hello world");
+        assert_eq!(base58_decode("112").unwrap(), vec![0, 0, 1]);
+        assert!(base58_decode("0OIl").is_none());
+    }
+
+    #[test]
+    fn verification_method_round_trip() {
+        let (verkey, _) = generate(3).unwrap();
+        let method = verkey.to_verification_method("did:example:issuer#key-1");
+        assert_eq!(method["type"], VERIFICATION_METHOD_TYPE);
+        assert_eq!(method["controller"], "did:example:issuer");
+        let text = serde_json::to_string(&method).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(
+            PublicKey::from_verification_method(&parsed).unwrap(),
+            verkey
+        );
+
+        let mut wrong_type = method.clone();
+        wrong_type["type"] = serde_json::json!("Ed25519VerificationKey2018");
+        assert!(PublicKey::from_verification_method(&wrong_type).is_err());
+        // The standard type holds only `w`, a method claiming it isn't read as a whole key
+        wrong_type["type"] = serde_json::json!("Bls12381G2Key2020");
+        assert!(PublicKey::from_verification_method(&wrong_type).is_err());
+        let mut truncated = method.clone();
+        let material = method["publicKeyMultibase"].as_str().unwrap();
+        truncated["publicKeyMultibase"] = serde_json::json!(&material[..material.len() - 10]);
+        assert!(PublicKey::from_verification_method(&truncated).is_err());
+        let mut not_base58 = method.clone();
+        not_base58["publicKeyMultibase"] = serde_json::json!("f00");
+        assert!(PublicKey::from_verification_method(&not_base58).is_err());
+    }
+}
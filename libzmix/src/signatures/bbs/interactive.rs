@@ -0,0 +1,153 @@
+// Three move interactive version of the proof of knowledge of a signature.
+//
+// The non-interactive flow in `pok_sig` derives the challenge by hashing the prover's
+// commitments (Fiat-Shamir), so the prover computes the whole proof alone and anyone can check
+// it afterwards. Here the verifier picks a random challenge only after receiving the
+// commitments: commitment -> challenge -> response. The transcript convinces only the verifier
+// that chose the challenge, so it cannot be shown to a third party, and no hash function is
+// involved in soundness. The response has the same form as `PoKOfSignatureProof`.
+
+use super::super::SignatureMessage;
+use super::keys::PublicKey;
+use super::pok_sig::{PoKOfSignature, PoKOfSignatureProof};
+use super::signature::Signature;
+use crate::errors::prelude::*;
+
+use std::collections::{HashMap, HashSet};
+
+use amcl_wrapper::field_elem::FieldElement;
+use amcl_wrapper::group_elem_g1::G1;
+
+pub mod prelude {
+    pub use super::{InteractiveProver, InteractiveVerifier, PoKOfSignatureCommitment};
+}
+
+/// First move, sent by the prover
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PoKOfSignatureCommitment {
+    pub a_prime: G1,
+    pub a_bar: G1,
+    pub d: G1,
+    pub commitment_1: G1, // commitment of the proof of a_bar / d == a_prime^{-e} * h_0^r2
+    pub commitment_2: G1, // commitment of the proof over d, h_0 and the hidden messages
+}
+
+impl PoKOfSignatureCommitment {
+    fn from_proof(proof: &PoKOfSignatureProof) -> Self {
+        PoKOfSignatureCommitment {
+            a_prime: proof.a_prime.clone(),
+            a_bar: proof.a_bar.clone(),
+            d: proof.d.clone(),
+            commitment_1: proof.proof_vc_1.commitment.clone(),
+            commitment_2: proof.proof_vc_2.commitment.clone(),
+        }
+    }
+}
+
+pub struct InteractiveProver {
+    pok: PoKOfSignature,
+}
+
+impl InteractiveProver {
+    /// Start the protocol, returns the prover and the commitment to send to the verifier.
+    pub fn new(
+        signature: &Signature,
+        verkey: &PublicKey,
+        messages: &[SignatureMessage],
+        revealed_msg_indices: HashSet<usize>,
+    ) -> Result<(Self, PoKOfSignatureCommitment), BBSError> {
+        let pok = PoKOfSignature::init(signature, verkey, messages, None, revealed_msg_indices)?;
+        let commitment = PoKOfSignatureCommitment {
+            a_prime: pok.a_prime.clone(),
+            a_bar: pok.a_bar.clone(),
+            d: pok.d.clone(),
+            commitment_1: pok.pok_vc_1.commitment.clone(),
+            commitment_2: pok.pok_vc_2.commitment.clone(),
+        };
+        Ok((InteractiveProver { pok }, commitment))
+    }
+
+    /// Last move, the response to the verifier's challenge. Consumes the prover since
+    /// answering two challenges for the same commitment reveals the secrets.
+    pub fn respond(self, challenge: &FieldElement) -> Result<PoKOfSignatureProof, BBSError> {
+        self.pok.gen_proof(challenge)
+    }
+}
+
+pub struct InteractiveVerifier {
+    commitment: PoKOfSignatureCommitment,
+    challenge: FieldElement,
+}
+
+impl InteractiveVerifier {
+    /// Receive the prover's commitment and pick a random challenge
+    pub fn new(commitment: PoKOfSignatureCommitment) -> Self {
+        InteractiveVerifier {
+            commitment,
+            challenge: FieldElement::random(),
+        }
+    }
+
+    /// Challenge to send to the prover
+    pub fn challenge(&self) -> &FieldElement {
+        &self.challenge
+    }
+
+    /// Check the response. It must be for the commitment received in the first move.
+    pub fn verify(
+        &self,
+        verkey: &PublicKey,
+        revealed_msgs: HashMap<usize, SignatureMessage>,
+        response: &PoKOfSignatureProof,
+    ) -> Result<bool, BBSError> {
+        if PoKOfSignatureCommitment::from_proof(response) != self.commitment {
+            return Ok(false);
+        }
+        response.verify(verkey, revealed_msgs, &self.challenge)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amcl_wrapper::field_elem::FieldElementVector;
+    use signatures::bbs::keys::generate;
+
+    #[test]
+    fn interactive_pok_signature() {
+        let message_count = 5;
+        let messages = FieldElementVector::random(message_count);
+        let (verkey, signkey) = generate(message_count).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+        let mut revealed = HashSet::new();
+        revealed.insert(1);
+        let mut revealed_msgs = HashMap::new();
+        revealed_msgs.insert(1, messages[1].clone());
+
+        let (prover, commitment) =
+            InteractiveProver::new(&sig, &verkey, messages.as_slice(), revealed.clone()).unwrap();
+        let verifier = InteractiveVerifier::new(commitment);
+        let response = prover.respond(verifier.challenge()).unwrap();
+        assert!(verifier
+            .verify(&verkey, revealed_msgs.clone(), &response)
+            .unwrap());
+
+        // Response to a challenge the verifier didn't pick
+        let (prover, commitment) =
+            InteractiveProver::new(&sig, &verkey, messages.as_slice(), revealed.clone()).unwrap();
+        let verifier = InteractiveVerifier::new(commitment);
+        let response = prover.respond(&FieldElement::random()).unwrap();
+        assert!(!verifier
+            .verify(&verkey, revealed_msgs.clone(), &response)
+            .unwrap());
+
+        // Response from another run of the protocol
+        let (_, commitment) =
+            InteractiveProver::new(&sig, &verkey, messages.as_slice(), revealed.clone()).unwrap();
+        let verifier = InteractiveVerifier::new(commitment);
+        let (prover, _) =
+            InteractiveProver::new(&sig, &verkey, messages.as_slice(), revealed).unwrap();
+        let response = prover.respond(verifier.challenge()).unwrap();
+        assert!(!verifier.verify(&verkey, revealed_msgs, &response).unwrap());
+    }
+}
@@ -0,0 +1,175 @@
+// Issuance records for issuers that have to keep an audit trail of what they signed
+//
+// A record holds the signature, its `b = g1 * h0^s * h_i^m_i...`, when it was issued and a
+// hash of the messages. The messages themselves aren't stored, an auditor given them later can
+// check the record was made over exactly those. `b` and the hash are deterministic in the
+// messages, so a record is only as confidential as the messages: a low entropy message can be
+// found by trying every candidate against it.
+
+use super::super::SignatureMessage;
+use super::keys::PublicKey;
+use super::signature::{compute_b_const_time, Signature, SIGNATURE_SIZE};
+use crate::errors::prelude::*;
+
+use amcl_wrapper::constants::{FieldElement_SIZE, GroupG1_SIZE};
+use amcl_wrapper::group_elem::GroupElement;
+use amcl_wrapper::group_elem_g1::G1;
+use amcl_wrapper::utils::hash_msg;
+
+pub mod prelude {
+    pub use super::{IssuanceRecord, ISSUANCE_RECORD_SIZE};
+}
+
+const MESSAGE_HASH_DST: &[u8] = b"BBS+ issuance record";
+
+/// Size of the byte representation returned by `IssuanceRecord::to_bytes`
+pub const ISSUANCE_RECORD_SIZE: usize = SIGNATURE_SIZE + GroupG1_SIZE + 8 + FieldElement_SIZE;
+
+/// What an issuer stores about one signature, see the top of `issuance.rs`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IssuanceRecord {
+    pub signature: Signature,
+    pub b: G1,
+    /// Seconds since the Unix epoch, as given by the issuer
+    pub timestamp: u64,
+    pub message_hash: Vec<u8>,
+}
+
+impl IssuanceRecord {
+    /// Record of `signature` over `messages` issued at `timestamp`. Fails if `signature`
+    /// doesn't verify so no record is ever made of a bad signature.
+    pub fn new(
+        signature: &Signature,
+        messages: &[SignatureMessage],
+        verkey: &PublicKey,
+        timestamp: u64,
+    ) -> Result<Self, BBSError> {
+        if !signature.verify(messages, verkey)? {
+            return Err(BBSError::from_kind(BBSErrorKind::GeneralError {
+                msg: "Signature doesn't verify over the messages".to_string(),
+            }));
+        }
+        Ok(IssuanceRecord {
+            signature: signature.clone(),
+            b: compute_b_const_time(&G1::new(), verkey, messages, &signature.s, 0),
+            timestamp,
+            message_hash: Self::hash_messages(messages),
+        })
+    }
+
+    /// SHAKE256 over the DST, the number of messages as 4 bytes big endian and every message
+    pub fn hash_messages(messages: &[SignatureMessage]) -> Vec<u8> {
+        let mut bytes = MESSAGE_HASH_DST.to_vec();
+        bytes.extend_from_slice(&(messages.len() as u32).to_be_bytes());
+        for m in messages {
+            bytes.append(&mut m.to_bytes());
+        }
+        hash_msg(&bytes).to_vec()
+    }
+
+    /// Whether the record was made over `messages`
+    pub fn matches_messages(&self, messages: &[SignatureMessage]) -> bool {
+        self.message_hash == Self::hash_messages(messages)
+    }
+
+    /// Full audit check: the record was made over `messages`, `b` is theirs and the signature
+    /// verifies under `verkey`
+    pub fn verify(
+        &self,
+        messages: &[SignatureMessage],
+        verkey: &PublicKey,
+    ) -> Result<bool, BBSError> {
+        if !self.matches_messages(messages) || !self.signature.verify(messages, verkey)? {
+            return Ok(false);
+        }
+        let b = compute_b_const_time(&G1::new(), verkey, messages, &self.signature.s, 0);
+        Ok(b == self.b)
+    }
+
+    /// The signature, `b`, the timestamp as 8 bytes big endian and the message hash
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(ISSUANCE_RECORD_SIZE);
+        out.append(&mut self.signature.to_bytes());
+        out.append(&mut self.b.to_bytes());
+        out.extend_from_slice(&self.timestamp.to_be_bytes());
+        out.extend_from_slice(&self.message_hash);
+        out
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, BBSError> {
+        if data.len() != ISSUANCE_RECORD_SIZE {
+            return Err(BBSError::from_kind(BBSErrorKind::GeneralError {
+                msg: format!(
+                    "Issuance record should be {} bytes, found {}",
+                    ISSUANCE_RECORD_SIZE,
+                    data.len()
+                ),
+            }));
+        }
+        let (signature, mut index) = Signature::from_bytes_prefix(data)?;
+        let b = G1::from_bytes(&data[index..index + GroupG1_SIZE]).map_err(|_| {
+            BBSError::from_kind(BBSErrorKind::GeneralError {
+                msg: "Malformed b in issuance record".to_string(),
+            })
+        })?;
+        index += GroupG1_SIZE;
+        let mut timestamp = [0u8; 8];
+        timestamp.copy_from_slice(&data[index..index + 8]);
+        index += 8;
+        Ok(IssuanceRecord {
+            signature,
+            b,
+            timestamp: u64::from_be_bytes(timestamp),
+            message_hash: data[index..].to_vec(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
+    use signatures::bbs::keys::generate;
+
+    #[test]
+    fn issuance_record_round_trip() {
+        let messages = FieldElementVector::random(3);
+        let (verkey, signkey) = generate(3).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+
+        let record =
+            IssuanceRecord::new(&sig, messages.as_slice(), &verkey, 1_600_000_000).unwrap();
+        let bytes = record.to_bytes();
+        assert_eq!(bytes.len(), ISSUANCE_RECORD_SIZE);
+        let parsed = IssuanceRecord::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed, record);
+        assert_eq!(parsed.timestamp, 1_600_000_000);
+        assert!(IssuanceRecord::from_bytes(&bytes[1..]).is_err());
+
+        let other = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+        assert!(IssuanceRecord::new(&other, &messages.as_slice()[..2], &verkey, 0).is_err());
+    }
+
+    #[test]
+    fn issuance_record_message_hash() {
+        let messages = FieldElementVector::random(3);
+        let (verkey, signkey) = generate(3).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+        let record = IssuanceRecord::new(&sig, messages.as_slice(), &verkey, 10).unwrap();
+
+        assert!(record.matches_messages(messages.as_slice()));
+        assert!(record.verify(messages.as_slice(), &verkey).unwrap());
+        let mut changed = messages.clone();
+        changed[1] = FieldElement::random();
+        assert!(!record.matches_messages(changed.as_slice()));
+        assert!(!record.verify(changed.as_slice(), &verkey).unwrap());
+
+        // A hash recorded for other messages is caught
+        let mut tampered = record.clone();
+        tampered.message_hash = IssuanceRecord::hash_messages(changed.as_slice());
+        assert!(!tampered.verify(messages.as_slice(), &verkey).unwrap());
+        let mut tampered = record;
+        tampered.b = G1::random();
+        assert!(!tampered.verify(messages.as_slice(), &verkey).unwrap());
+    }
+}
@@ -1,64 +1,342 @@
 use amcl_wrapper::{
-    constants::GroupG1_SIZE, errors::SerzDeserzError, field_elem::FieldElement,
-    group_elem::GroupElement, group_elem_g1::G1, group_elem_g2::G2, types_g2::GroupG2_SIZE,
+    constants::{GroupG1_SIZE, MODBYTES},
+    errors::SerzDeserzError,
+    field_elem::FieldElement,
+    group_elem::GroupElement,
+    group_elem_g1::G1,
+    group_elem_g2::G2,
+    types::GroupG1,
+    types_g2::GroupG2_SIZE,
 };
 
+use super::signature::ct_is_zero;
 use crate::errors::prelude::*;
 
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use zeroize::Zeroizing;
+
 pub mod prelude {
-    pub use super::{generate, PublicKey, SecretKey};
+    pub use super::{
+        generate, generate_deterministic, generate_from_seed, generate_with_params, Params,
+        PreparedSecretKey, PublicKey, SecretKey, ToPublicKey, MIN_SEED_SIZE,
+    };
 }
 
 // https://eprint.iacr.org/2016/663.pdf Section 4.3
+// `FieldElement` zeroes its limbs when dropped, so does every secret key and every copy or
+// intermediate value of one, like `x + e` when signing. Byte buffers holding key material are
+// `Zeroizing`. Bytes from `to_bytes` are the caller's to clear.
 pub type SecretKey = FieldElement;
 
+/// Secret key of a long lived signer together with a table of random `e` values and their
+/// `1/(x + e)`. Signing needs one field inversion per signature, the table is filled with a
+/// single batch inversion (3 multiplications per entry plus one inversion) whenever it runs out,
+/// see `Signature::new_prepared`. Every entry is used for one signature only.
+/// The inversion is a small part of signing next to computing `b` so the saving is modest,
+/// see `benches/bbs.rs`.
+pub struct PreparedSecretKey {
+    key: SecretKey,
+    table_size: usize,
+    table: Vec<(FieldElement, FieldElement)>,
+}
+
+impl PreparedSecretKey {
+    /// Inverses are computed `table_size` at a time
+    pub fn new(signkey: &SecretKey, table_size: usize) -> Self {
+        PreparedSecretKey {
+            key: signkey.clone(),
+            table_size: table_size.max(1),
+            table: Vec::new(),
+        }
+    }
+
+    /// Number of precomputed entries left
+    pub fn remaining(&self) -> usize {
+        self.table.len()
+    }
+
+    /// Fill the table with `e` values chosen by the caller, used by tests to compare with
+    /// known results
+    #[cfg(test)]
+    pub(crate) fn with_nonces(signkey: &SecretKey, es: &[FieldElement]) -> Self {
+        let mut prepared = PreparedSecretKey::new(signkey, es.len());
+        prepared.fill(es.to_vec());
+        prepared
+    }
+
+    /// Next `(e, 1/(x + e))`
+    pub(crate) fn next_inverse(&mut self) -> (FieldElement, FieldElement) {
+        if self.table.is_empty() {
+            let es = (0..self.table_size)
+                .map(|_| FieldElement::random())
+                .collect();
+            self.fill(es);
+        }
+        self.table.pop().unwrap()
+    }
+
+    // An `e` with `x + e` zero would have no inverse and, inverted in a batch, zero every
+    // other inverse with it. It is drawn again, checked in constant time as in `random_e`.
+    fn fill(&mut self, mut es: Vec<FieldElement>) {
+        for e in es.iter_mut() {
+            while bool::from(ct_is_zero(&(&self.key + &*e))) {
+                *e = FieldElement::random();
+            }
+        }
+        let sums: Vec<FieldElement> = es.iter().map(|e| &self.key + e).collect();
+        let (inverses, _) = FieldElement::batch_invert(sums.as_slice());
+        // Popped from the back, keep the first `e` last
+        self.table = es.into_iter().zip(inverses).rev().collect();
+    }
+}
+
 /// `PublicKey` consists of a blinding generator `h0`, a commitment to the secret key `w`
 /// and a generator for each message in `h`
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PublicKey {
     pub h0: G1,     //blinding factor base
     pub h: Vec<G1>, //base for each message to be signed
     pub w: G2,      //commitment to private key
 }
 
+/// Size of a compressed `G1` element, a prefix byte and the x coordinate
+pub const G1_COMPRESSED_SIZE: usize = MODBYTES + 1;
+
+pub(crate) fn write_g1(p: &G1, compress: bool, out: &mut Vec<u8>) {
+    if compress {
+        let mut bytes = [0u8; G1_COMPRESSED_SIZE];
+        p.to_ecp().tobytes(&mut bytes, true);
+        out.extend_from_slice(&bytes);
+    } else {
+        out.append(&mut p.to_bytes());
+    }
+}
+
+// Reads a compressed or uncompressed `G1` element at `index` and moves past it
+pub(crate) fn read_g1(data: &[u8], index: &mut usize) -> Result<G1, SerzDeserzError> {
+    let size = match data.get(*index) {
+        Some(0x02) | Some(0x03) => G1_COMPRESSED_SIZE,
+        _ => GroupG1_SIZE,
+    };
+    if data.len() < *index + size {
+        return Err(SerzDeserzError::G1BytesIncorrectSize(
+            data.len() - *index,
+            size,
+        ));
+    }
+    let bytes = &data[*index..(*index + size)];
+    let p = if size == G1_COMPRESSED_SIZE {
+        let p = GroupG1::frombytes(bytes);
+        // amcl returns the point at infinity for an x not on the curve
+        if p.is_infinity() {
+            return Err(SerzDeserzError::CannotParseFP);
+        }
+        G1::from(p)
+    } else {
+        G1::from_bytes(bytes)?
+    };
+    *index += size;
+    Ok(p)
+}
+
+// Equality and hashing go through the canonical bytes so that keys equal as points always hash
+// the same, which lets registries keep keys in a `HashSet` or as `HashMap` keys.
+impl PartialEq for PublicKey {
+    fn eq(&self, other: &PublicKey) -> bool {
+        self.to_bytes() == other.to_bytes()
+    }
+}
+
+impl Eq for PublicKey {}
+
+impl Hash for PublicKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.to_bytes().hash(state);
+    }
+}
+
 impl PublicKey {
+    /// Random but structurally valid key for `message_count` messages. Nobody knows the secret
+    /// key for `w` so no signature can be produced under it, useful for negative tests.
+    pub fn random(message_count: usize) -> Self {
+        PublicKey {
+            w: G2::random(),
+            h0: G1::random(),
+            h: (0..message_count).map(|_| G1::random()).collect(),
+        }
+    }
+
     pub fn message_count(&self) -> usize {
         self.h.len()
     }
 
+    /// Move the key behind an `Arc` so clones handed to other threads share the bases instead
+    /// of copying every `h`. Everything taking a `&PublicKey` accepts `&shared` as is.
+    pub fn shared(self) -> Arc<Self> {
+        Arc::new(self)
+    }
+
+    /// Same as `to_compressed_bytes`
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut out = Vec::with_capacity(GroupG1_SIZE * (self.h.len() + 1) + 4 + GroupG2_SIZE);
+        self.to_compressed_bytes()
+    }
+
+    /// `w`, `h0`, the number of messages as 4 bytes big endian and every `h`, with the `G1`
+    /// bases compressed to their x coordinate and the sign of y, `G1_COMPRESSED_SIZE` (49)
+    /// bytes instead of `GroupG1_SIZE` (97). amcl has no compressed form for `G2` so `w`
+    /// always takes `GroupG2_SIZE` (192) bytes. A key for 10 messages takes 735 bytes instead
+    /// of 1263.
+    pub fn to_compressed_bytes(&self) -> Vec<u8> {
+        self.write_bytes(true)
+    }
+
+    /// Same layout as `to_compressed_bytes` with uncompressed `G1` bases
+    pub fn to_uncompressed_bytes(&self) -> Vec<u8> {
+        self.write_bytes(false)
+    }
+
+    fn write_bytes(&self, compress: bool) -> Vec<u8> {
+        let size = if compress {
+            G1_COMPRESSED_SIZE
+        } else {
+            GroupG1_SIZE
+        };
+        let mut out = Vec::with_capacity(size * (self.h.len() + 1) + 4 + GroupG2_SIZE);
         out.extend_from_slice(self.w.to_bytes().as_slice());
-        out.extend_from_slice(self.h0.to_bytes().as_slice());
+        write_g1(&self.h0, compress, &mut out);
         out.extend_from_slice(&(self.h.len() as u32).to_be_bytes());
         for p in &self.h {
-            out.extend_from_slice(p.to_bytes().as_slice());
+            write_g1(p, compress, &mut out);
         }
         out
     }
 
+    /// Reads keys written by `to_compressed_bytes` or `to_uncompressed_bytes`, each `G1` base
+    /// says by its first byte whether it is compressed.
     pub fn from_bytes(data: &[u8]) -> Result<Self, SerzDeserzError> {
+        if data.len() < GroupG2_SIZE {
+            return Err(SerzDeserzError::G2BytesIncorrectSize(
+                data.len(),
+                GroupG2_SIZE,
+            ));
+        }
         let mut index = 0;
         let w = G2::from_bytes(&data[0..GroupG2_SIZE])?;
         index += GroupG2_SIZE;
-        let h0 = G1::from_bytes(&data[index..(index + GroupG1_SIZE)])?;
-        index += GroupG1_SIZE;
+        let h0 = read_g1(data, &mut index)?;
+        if data.len() < index + 4 {
+            return Err(SerzDeserzError::G1BytesIncorrectSize(data.len() - index, 4));
+        }
         let h_size = u32::from_be_bytes([
             data[index],
             data[index + 1],
             data[index + 2],
             data[index + 3],
         ]) as usize;
-        let mut h = Vec::with_capacity(h_size);
         index += 4;
+        let mut h = Vec::with_capacity(h_size.min(data.len() / G1_COMPRESSED_SIZE));
         for _ in 0..h_size {
-            let p = G1::from_bytes(&data[index..(index + GroupG1_SIZE)])?;
-            h.push(p);
-            index += GroupG1_SIZE;
+            h.push(read_g1(data, &mut index)?);
         }
         Ok(PublicKey { w, h0, h })
     }
 
+    /// `w`, `h0` and every `h` uncompressed, with no count: the size alone,
+    /// `GroupG2_SIZE + GroupG1_SIZE * (message_count + 1)`, gives the number of messages.
+    /// Every key for the same number of messages has the same size and one encoding.
+    /// A separate pair of functions rather than the layout of `to_bytes`, which keeps the
+    /// count and compressed bases: `Hash`, `Eq`, DID verification methods and stored keys go
+    /// through it. Serde output is unchanged as well.
+    pub fn to_fixed_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(GroupG2_SIZE + GroupG1_SIZE * (self.h.len() + 1));
+        out.append(&mut self.w.to_bytes());
+        out.append(&mut self.h0.to_bytes());
+        for p in &self.h {
+            out.append(&mut p.to_bytes());
+        }
+        out
+    }
+
+    /// Reads keys written by `to_fixed_bytes`. Fails with `PublicKeyIncorrectSize` if the size
+    /// is not that of a key for some number of messages and with `MalformedPublicKey` if a
+    /// point isn't on the curve or is the identity.
+    // `usize::is_multiple_of` needs Rust 1.87
+    #[allow(clippy::manual_is_multiple_of)]
+    pub fn from_fixed_bytes(data: &[u8]) -> Result<Self, BBSError> {
+        if data.len() < GroupG2_SIZE + GroupG1_SIZE
+            || (data.len() - GroupG2_SIZE) % GroupG1_SIZE != 0
+        {
+            return Err(BBSError::from_kind(BBSErrorKind::PublicKeyIncorrectSize(
+                data.len(),
+            )));
+        }
+        let malformed = |_| BBSError::from_kind(BBSErrorKind::MalformedPublicKey);
+        let w = G2::from_bytes(&data[..GroupG2_SIZE]).map_err(malformed)?;
+        let mut points = data[GroupG2_SIZE..]
+            .chunks(GroupG1_SIZE)
+            .map(|c| G1::from_bytes(c).map_err(malformed));
+        let h0 = points.next().unwrap()?;
+        let h = points.collect::<Result<Vec<G1>, BBSError>>()?;
+        // amcl reads bytes off the curve as the identity
+        let key = PublicKey { w, h0, h };
+        key.validate()?;
+        Ok(key)
+    }
+
+    /// Copy of this key with the message bases permuted, base `i` of the result is
+    /// `self.h[mapping[i]]`. `mapping` must be a permutation of `0..message_count`.
+    pub fn reordered(&self, mapping: &[usize]) -> Result<Self, BBSError> {
+        let mut seen = vec![false; self.h.len()];
+        if mapping.len() != self.h.len() {
+            return Err(BBSError::from_kind(BBSErrorKind::message_count(
+                self.h.len(),
+                mapping.len(),
+            )));
+        }
+        for i in mapping {
+            if *i >= self.h.len() || seen[*i] {
+                return Err(BBSError::from_kind(BBSErrorKind::GeneralError {
+                    msg: format!("Mapping is not a permutation, index {}", i),
+                }));
+            }
+            seen[*i] = true;
+        }
+        Ok(PublicKey {
+            w: self.w.clone(),
+            h0: self.h0.clone(),
+            h: mapping.iter().map(|i| self.h[*i].clone()).collect(),
+        })
+    }
+
+    /// Find for each base of `canonical` the position of the same base in this key.
+    /// Returns `None` when the keys don't consist of the same elements.
+    pub fn match_bases(&self, canonical: &PublicKey) -> Option<Vec<usize>> {
+        if self.w != canonical.w || self.h0 != canonical.h0 || self.h.len() != canonical.h.len() {
+            return None;
+        }
+        let positions: HashMap<&G1, usize> =
+            self.h.iter().enumerate().map(|(i, p)| (p, i)).collect();
+        canonical
+            .h
+            .iter()
+            .map(|p| positions.get(p).cloned())
+            .collect()
+    }
+
+    /// Strict check that this key is `canonical` with the bases in the same order.
+    /// Keys are often moved through formats like unsorted maps which can shuffle the bases, a
+    /// shuffled key makes every signature fail to verify with no indication why.
+    /// Fails with `BaseOrderMismatch` if only the order differs.
+    pub fn check_order(&self, canonical: &PublicKey) -> Result<(), BBSError> {
+        match self.match_bases(canonical) {
+            None => Err(BBSError::from_kind(BBSErrorKind::MalformedPublicKey)),
+            Some(ref mapping) if mapping.iter().enumerate().all(|(i, j)| i == *j) => Ok(()),
+            Some(_) => Err(BBSError::from_kind(BBSErrorKind::BaseOrderMismatch)),
+        }
+    }
+
     // Make sure no generator is identity
     pub fn validate(&self) -> Result<(), BBSError> {
         if self.h0.is_identity() || self.w.is_identity() || self.h.iter().any(|v| v.is_identity()) {
@@ -67,18 +345,65 @@ impl PublicKey {
             Ok(())
         }
     }
+
+    /// Whether `sk` is the secret key of this key, `w == g2^sk`. Checks against amcl's `g2`,
+    /// a key made by `generate_with_params` with another `g2` never matches.
+    pub fn matches_secret(&self, sk: &SecretKey) -> bool {
+        self.w == G2::generator() * sk
+    }
+}
+
+/// Generators `g1` and `g2` of a deployment, the default is amcl's generators which
+/// `generate`, `Signature::new` and `Signature::verify` use. Keys, signatures and verification
+/// must all use the same params: a key made with other params doesn't verify with the default.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Params {
+    pub g1: G1,
+    pub g2: G2,
+}
+
+impl Params {
+    /// Fails if either generator is the identity or outside the prime order subgroup
+    pub fn new(g1: G1, g2: G2) -> Result<Self, BBSError> {
+        if g1.is_identity()
+            || !g1.has_correct_order()
+            || g2.is_identity()
+            || !g2.has_correct_order()
+        {
+            return Err(BBSError::from_kind(BBSErrorKind::GeneralError {
+                msg: "Generators should be non identity points of the prime order subgroups"
+                    .to_string(),
+            }));
+        }
+        Ok(Params { g1, g2 })
+    }
+}
+
+impl Default for Params {
+    fn default() -> Self {
+        Params {
+            g1: G1::generator(),
+            g2: G2::generator(),
+        }
+    }
 }
 
 /// Create a new BBS+ keypair
 pub fn generate(message_count: usize) -> Result<(PublicKey, SecretKey), BBSError> {
+    generate_with_params(message_count, &Params::default())
+}
+
+/// Same as `generate` with `w = params.g2^x`
+pub fn generate_with_params(
+    message_count: usize,
+    params: &Params,
+) -> Result<(PublicKey, SecretKey), BBSError> {
     if message_count == 0 {
         return Err(BBSError::from_kind(BBSErrorKind::KeyGenError));
     }
     let secret = FieldElement::random();
 
-    // Super paranoid could allow a context to generate the generator from a well known value
-    // Not doing this for now since any generator in a prime field should be okay.
-    let w = &G2::generator() * &secret;
+    let w = &params.g2 * &secret;
     let mut h = Vec::new();
     for _ in 0..message_count {
         h.push(G1::random());
@@ -93,9 +418,133 @@ pub fn generate(message_count: usize) -> Result<(PublicKey, SecretKey), BBSError
     ))
 }
 
+/// Derives the `PublicKey` of a `SecretKey`. A trait since `SecretKey` is a `FieldElement`.
+pub trait ToPublicKey {
+    /// `w = g2^x` and every base hashed from `w`, the base index and `message_count`, so a
+    /// signer can keep only the secret key. Only keys made this way, e.g. by
+    /// `generate_deterministic`, can be derived again: `generate` picks random bases.
+    fn to_public_key(&self, message_count: usize) -> Result<PublicKey, BBSError>;
+}
+
+impl ToPublicKey for SecretKey {
+    fn to_public_key(&self, message_count: usize) -> Result<PublicKey, BBSError> {
+        if message_count == 0 {
+            return Err(BBSError::from_kind(BBSErrorKind::KeyGenError));
+        }
+        let w = &G2::generator() * self;
+        let w_bytes = w.to_bytes();
+        // Base 0 is h0, base i + 1 is h[i]
+        let base = |index: usize| {
+            let mut bytes = w_bytes.clone();
+            bytes.extend_from_slice(&(index as u32).to_be_bytes());
+            bytes.extend_from_slice(&(message_count as u32).to_be_bytes());
+            G1::from_msg_hash(&bytes)
+        };
+        Ok(PublicKey {
+            h0: base(0),
+            h: (1..=message_count).map(base).collect(),
+            w,
+        })
+    }
+}
+
+/// Create a new BBS+ keypair whose public key can be derived again from the secret key with
+/// `ToPublicKey::to_public_key`
+pub fn generate_deterministic(message_count: usize) -> Result<(PublicKey, SecretKey), BBSError> {
+    let secret = FieldElement::random();
+    Ok((secret.to_public_key(message_count)?, secret))
+}
+
+const SEED_KEY_DST: &[u8] = b"BBS+ key from seed";
+
+/// Shortest seed `generate_from_seed` accepts
+pub const MIN_SEED_SIZE: usize = 32;
+
+/// Create the BBS+ keypair of `seed`, the same seed and `message_count` always give the same
+/// keys so they can be recovered from a backed up seed. The secret key is
+/// `FieldElement::from_msg_hash(DST || counter || seed)` with a 4 byte big endian counter
+/// starting at 0 and only increased in the negligible case the hash is zero. The public key is
+/// `ToPublicKey::to_public_key` of it, every base is hashed to the curve on its own so no
+/// discrete log relation between them is known. The seed must hold at least
+/// `MIN_SEED_SIZE` bytes and as much entropy as the key should have.
+pub fn generate_from_seed(
+    message_count: usize,
+    seed: &[u8],
+) -> Result<(PublicKey, SecretKey), BBSError> {
+    if seed.len() < MIN_SEED_SIZE {
+        return Err(BBSError::from_msg(
+            BBSErrorKind::KeyGenError,
+            format!("Seed should have at least {} bytes", MIN_SEED_SIZE),
+        ));
+    }
+    let mut counter = 0u32;
+    let secret = loop {
+        let mut bytes = Zeroizing::new(Vec::with_capacity(SEED_KEY_DST.len() + 4 + seed.len()));
+        bytes.extend_from_slice(SEED_KEY_DST);
+        bytes.extend_from_slice(&counter.to_be_bytes());
+        bytes.extend_from_slice(seed);
+        let secret = FieldElement::from_msg_hash(&bytes);
+        if !secret.is_zero() {
+            break secret;
+        }
+        counter += 1;
+    };
+    Ok((secret.to_public_key(message_count)?, secret))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use amcl_wrapper::field_elem::FieldElementVector;
+    use signatures::bbs::signature::Signature;
+    use std::collections::HashSet;
+    use std::thread;
+
+    #[test]
+    fn public_key_compressed_bytes() {
+        let (public_key, _) = generate(10).unwrap();
+        let compressed = public_key.to_compressed_bytes();
+        let uncompressed = public_key.to_uncompressed_bytes();
+        assert_eq!(compressed.len(), 735);
+        assert_eq!(uncompressed.len(), 1263);
+        assert_eq!(public_key.to_bytes(), compressed);
+
+        let from_compressed = PublicKey::from_bytes(compressed.as_slice()).unwrap();
+        let from_uncompressed = PublicKey::from_bytes(uncompressed.as_slice()).unwrap();
+        assert_eq!(from_compressed, public_key);
+        assert_eq!(from_compressed, from_uncompressed);
+        assert_eq!(from_uncompressed.to_uncompressed_bytes(), uncompressed);
+
+        assert!(PublicKey::from_bytes(&compressed[..compressed.len() - 1]).is_err());
+        assert!(PublicKey::from_bytes(&compressed[..100]).is_err());
+    }
+
+    #[test]
+    fn public_key_registry() {
+        let (pk_1, _) = generate(3).unwrap();
+        let (pk_2, _) = generate(3).unwrap();
+        let copy = PublicKey::from_bytes(pk_1.to_bytes().as_slice()).unwrap();
+        assert_eq!(pk_1, copy);
+        assert_ne!(pk_1, pk_2);
+
+        let mut registry = HashSet::new();
+        assert!(registry.insert(pk_1.clone()));
+        assert!(!registry.insert(copy));
+        assert!(registry.contains(&pk_1));
+        assert!(!registry.contains(&pk_2));
+
+        // Any base differing makes a different key
+        let mut other = pk_1.clone();
+        other.w = G2::random();
+        assert!(!registry.contains(&other));
+        let mut other = pk_1.clone();
+        other.h[2] = G1::random();
+        assert!(!registry.contains(&other));
+        let mut other = pk_1.clone();
+        other.h.pop();
+        assert!(!registry.contains(&other));
+        assert_eq!(registry.len(), 1);
+    }
 
     #[test]
     fn key_generate() {
@@ -104,16 +553,146 @@ mod tests {
         //Check to make sure key has correct size
         let (public_key, _) = generate(1).unwrap();
         let bytes = public_key.to_bytes();
-        assert_eq!(bytes.len(), GroupG1_SIZE * 2 + 4 + GroupG2_SIZE);
+        assert_eq!(bytes.len(), G1_COMPRESSED_SIZE * 2 + 4 + GroupG2_SIZE);
 
         let (public_key, _) = generate(5).unwrap();
         assert_eq!(public_key.message_count(), 5);
         //Check key doesn't contain any invalid points
         assert!(public_key.validate().is_ok());
         let bytes = public_key.to_bytes();
-        assert_eq!(bytes.len(), GroupG1_SIZE * 6 + 4 + GroupG2_SIZE);
+        assert_eq!(bytes.len(), G1_COMPRESSED_SIZE * 6 + 4 + GroupG2_SIZE);
         //Check serialization is working
         let public_key_2 = PublicKey::from_bytes(bytes.as_slice()).unwrap();
         assert_eq!(public_key_2, public_key);
     }
+
+    #[test]
+    fn random_public_key() {
+        let public_key = PublicKey::random(5);
+        assert_eq!(public_key.message_count(), 5);
+        assert!(public_key.validate().is_ok());
+        assert_ne!(public_key, PublicKey::random(5));
+    }
+
+    #[test]
+    fn shared_public_key() {
+        let messages = FieldElementVector::random(4);
+        let (verkey, signkey) = generate(4).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+        let shared = verkey.clone().shared();
+        let clone = shared.clone();
+        assert_eq!(shared.h.as_ptr(), clone.h.as_ptr());
+        assert_eq!(*clone, verkey);
+
+        let handles: Vec<_> = (0..2)
+            .map(|_| {
+                let key = shared.clone();
+                let sig = sig.clone();
+                let messages = messages.clone();
+                thread::spawn(move || sig.verify(messages.as_slice(), &key).unwrap())
+            })
+            .collect();
+        for h in handles {
+            assert!(h.join().unwrap());
+        }
+    }
+
+    #[test]
+    fn derived_public_key() {
+        let (verkey, signkey) = generate_deterministic(5).unwrap();
+        assert_eq!(signkey.to_public_key(5).unwrap(), verkey);
+        verkey.validate().unwrap();
+        assert!(signkey.to_public_key(0).is_err());
+        // Unrelated bases for another message count
+        let longer = signkey.to_public_key(6).unwrap();
+        assert_eq!(longer.w, verkey.w);
+        assert_ne!(longer.h0, verkey.h0);
+
+        let messages = FieldElementVector::random(5);
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+        let derived = signkey.to_public_key(5).unwrap();
+        assert!(sig.verify(messages.as_slice(), &derived).unwrap());
+    }
+
+    #[test]
+    fn keys_from_seed() {
+        let seed = [3u8; MIN_SEED_SIZE];
+        let (verkey, signkey) = generate_from_seed(4, &seed).unwrap();
+        let (again_verkey, again_signkey) = generate_from_seed(4, &seed).unwrap();
+        assert_eq!(verkey.to_bytes(), again_verkey.to_bytes());
+        assert_eq!(signkey.to_bytes(), again_signkey.to_bytes());
+        assert_eq!(signkey.to_public_key(4).unwrap(), verkey);
+
+        let mut bases = HashSet::new();
+        bases.insert(verkey.h0.to_bytes());
+        for h in &verkey.h {
+            bases.insert(h.to_bytes());
+        }
+        assert_eq!(bases.len(), 5);
+
+        let mut other_seed = seed;
+        other_seed[0] ^= 1;
+        let (other, _) = generate_from_seed(4, &other_seed).unwrap();
+        assert_ne!(other.w, verkey.w);
+        assert_ne!(other.h0, verkey.h0);
+
+        let messages = FieldElementVector::random(4);
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+        assert!(sig.verify(messages.as_slice(), &again_verkey).unwrap());
+
+        assert!(generate_from_seed(4, &seed[1..]).is_err());
+        assert!(generate_from_seed(0, &seed).is_err());
+    }
+
+    #[test]
+    fn secret_key_zeroized_on_drop() {
+        // Zeroing happens in `Drop` of `FieldElement`
+        assert!(std::mem::needs_drop::<SecretKey>());
+        assert!(std::mem::needs_drop::<PreparedSecretKey>());
+
+        let (verkey, signkey) = generate(3).unwrap();
+        let messages = FieldElementVector::random(3);
+        {
+            let copy = signkey.clone();
+            let sig = Signature::new(messages.as_slice(), &copy, &verkey).unwrap();
+            assert!(sig.verify(messages.as_slice(), &verkey).unwrap());
+        }
+        let sig = Signature::new_deterministic(messages.as_slice(), &signkey, &verkey).unwrap();
+        assert!(sig.verify(messages.as_slice(), &verkey).unwrap());
+        let (verkey, signkey) = generate_from_seed(3, &[9u8; MIN_SEED_SIZE]).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+        assert!(sig.verify(messages.as_slice(), &verkey).unwrap());
+    }
+
+    #[test]
+    fn public_key_matches_secret() {
+        let (verkey, signkey) = generate(3).unwrap();
+        assert!(verkey.matches_secret(&signkey));
+        let (other_verkey, other_signkey) = generate(3).unwrap();
+        assert!(!verkey.matches_secret(&other_signkey));
+        assert!(!other_verkey.matches_secret(&signkey));
+    }
+
+    #[test]
+    fn public_key_fixed_bytes() {
+        let (verkey, _) = generate(5).unwrap();
+        let bytes = verkey.to_fixed_bytes();
+        assert_eq!(bytes.len(), GroupG2_SIZE + GroupG1_SIZE * 6);
+        let parsed = PublicKey::from_fixed_bytes(&bytes).unwrap();
+        assert_eq!(parsed, verkey);
+        assert_eq!(parsed.message_count(), 5);
+
+        for bad in &[&bytes[..bytes.len() - 1], &bytes[..GroupG2_SIZE], &[][..]] {
+            match PublicKey::from_fixed_bytes(bad).unwrap_err().kind() {
+                BBSErrorKind::PublicKeyIncorrectSize(size) => assert_eq!(size, bad.len()),
+                kind => panic!("unexpected {:?}", kind),
+            }
+        }
+        let mut longer = bytes.clone();
+        longer.push(0);
+        assert!(PublicKey::from_fixed_bytes(&longer).is_err());
+        let mut corrupted = bytes;
+        corrupted[GroupG2_SIZE + 1] ^= 1;
+        assert!(PublicKey::from_fixed_bytes(&corrupted).is_err());
+    }
 }
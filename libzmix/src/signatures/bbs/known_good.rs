@@ -0,0 +1,216 @@
+use super::super::SignatureMessage;
+use super::keys::PublicKey;
+use super::signature::Signature;
+use crate::errors::prelude::*;
+
+use amcl_wrapper::field_elem::FieldElement;
+
+pub mod prelude {
+    pub use super::KnownGoodFilter;
+}
+
+/// Bloom filter over (signature, messages, public key) triples that were verified, so
+/// `Signature::verify_cached` can skip the pairing when the same triple shows up again.
+///
+/// A Bloom filter has false positives: a triple that was never added is reported present with
+/// about the probability given to `new`, and `verify_cached` then accepts it without checking,
+/// forged or not. The positions are derived with a random key chosen per filter so matches
+/// cannot be searched for offline, but the rate still grows past the target once more items
+/// than planned are added. Only add triples that passed a full `verify`, and only use the
+/// filter where occasionally accepting an unverified signature is acceptable.
+#[derive(Debug, Clone)]
+pub struct KnownGoodFilter {
+    key: FieldElement,
+    bits: Vec<u64>,
+    bit_count: usize,
+    hash_count: usize,
+    len: usize,
+}
+
+impl KnownGoodFilter {
+    /// Filter sized for `expected_items` entries at `false_positive_rate`, which must be
+    /// strictly between 0 and 1. Fails for any other rate and when the filter wouldn't fit in
+    /// memory.
+    pub fn new(expected_items: usize, false_positive_rate: f64) -> Result<Self, BBSError> {
+        // Also false for NaN
+        if !(false_positive_rate > 0.0 && false_positive_rate < 1.0) {
+            return Err(BBSError::from_kind(BBSErrorKind::GeneralError {
+                msg: format!(
+                    "False positive rate should be between 0 and 1, found {}",
+                    false_positive_rate
+                ),
+            }));
+        }
+        let ln2 = std::f64::consts::LN_2;
+        let n = expected_items.max(1) as f64;
+        // Whole words of 64 bits, at least one
+        let words = (-(n * false_positive_rate.ln()) / (ln2 * ln2) / 64.0)
+            .ceil()
+            .max(1.0);
+        if words > (usize::MAX / 64) as f64 {
+            return Err(BBSError::from_kind(BBSErrorKind::GeneralError {
+                msg: format!(
+                    "A filter for {} items at rate {} is too large",
+                    expected_items, false_positive_rate
+                ),
+            }));
+        }
+        let words = words as usize;
+        let bit_count = words * 64;
+        let hash_count = ((bit_count as f64 / n) * ln2).round().max(1.0) as usize;
+        Ok(KnownGoodFilter {
+            key: FieldElement::random(),
+            bits: vec![0; words],
+            bit_count,
+            hash_count,
+            len: 0,
+        })
+    }
+
+    /// Record a triple that passed `Signature::verify`
+    pub fn insert(
+        &mut self,
+        signature: &Signature,
+        messages: &[SignatureMessage],
+        verkey: &PublicKey,
+    ) {
+        for i in self.positions(signature, messages, verkey) {
+            self.bits[i / 64] |= 1 << (i % 64);
+        }
+        self.len += 1;
+    }
+
+    /// True if the triple was added, or for a false positive
+    pub fn contains(
+        &self,
+        signature: &Signature,
+        messages: &[SignatureMessage],
+        verkey: &PublicKey,
+    ) -> bool {
+        self.positions(signature, messages, verkey)
+            .iter()
+            .all(|i| self.bits[i / 64] & (1 << (i % 64)) != 0)
+    }
+
+    /// Number of inserts
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Double hashing, position i is h1 + i * h2 with h1 and h2 from a keyed hash of the triple
+    fn positions(
+        &self,
+        signature: &Signature,
+        messages: &[SignatureMessage],
+        verkey: &PublicKey,
+    ) -> Vec<usize> {
+        let mut bytes = self.key.to_bytes();
+        bytes.append(&mut signature.to_bytes());
+        bytes.extend_from_slice(&(messages.len() as u32).to_be_bytes());
+        for m in messages {
+            bytes.append(&mut m.to_bytes());
+        }
+        bytes.append(&mut verkey.to_bytes());
+        let digest = FieldElement::from_msg_hash(&bytes).to_bytes();
+        // The top bytes of a reduced field element are mostly zero, use the low 16
+        let low = &digest[digest.len() - 16..];
+        let mut h1 = [0u8; 8];
+        let mut h2 = [0u8; 8];
+        h1.copy_from_slice(&low[..8]);
+        h2.copy_from_slice(&low[8..]);
+        let h1 = u64::from_be_bytes(h1);
+        let h2 = u64::from_be_bytes(h2) | 1;
+        (0..self.hash_count as u64)
+            .map(|i| (h1.wrapping_add(i.wrapping_mul(h2)) % self.bit_count as u64) as usize)
+            .collect()
+    }
+}
+
+impl Signature {
+    /// `verify` that first looks for the triple in `filter` and returns true right away when
+    /// present. Otherwise verifies and adds the triple to `filter` if valid.
+    /// See `KnownGoodFilter` for the false positive risk.
+    pub fn verify_cached(
+        &self,
+        messages: &[SignatureMessage],
+        verkey: &PublicKey,
+        filter: &mut KnownGoodFilter,
+    ) -> Result<bool, BBSError> {
+        if filter.contains(self, messages, verkey) {
+            return Ok(true);
+        }
+        let valid = self.verify(messages, verkey)?;
+        if valid {
+            filter.insert(self, messages, verkey);
+        }
+        Ok(valid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amcl_wrapper::field_elem::FieldElementVector;
+    use signatures::bbs::keys::generate;
+    use signatures::bbs::metrics;
+
+    #[test]
+    fn known_good_filter() {
+        let message_count = 3;
+        let messages = FieldElementVector::random(message_count);
+        let (verkey, signkey) = generate(message_count).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+        let mut filter = KnownGoodFilter::new(100, 0.001).unwrap();
+        assert!(filter.is_empty());
+
+        // Miss, verified normally and remembered
+        metrics::reset();
+        assert!(sig
+            .verify_cached(messages.as_slice(), &verkey, &mut filter)
+            .unwrap());
+        assert_eq!(metrics::get().pairings, 2);
+        assert_eq!(filter.len(), 1);
+
+        // Hit, no pairing
+        metrics::reset();
+        assert!(sig
+            .verify_cached(messages.as_slice(), &verkey, &mut filter)
+            .unwrap());
+        assert_eq!(metrics::get().pairings, 0);
+
+        // Same signature with other messages isn't a hit and fails
+        let other = FieldElementVector::random(message_count);
+        assert!(!filter.contains(&sig, other.as_slice(), &verkey));
+        assert!(!sig
+            .verify_cached(other.as_slice(), &verkey, &mut filter)
+            .unwrap());
+        assert_eq!(filter.len(), 1);
+
+        // Invalid signatures are never added
+        let bad = Signature::new(other.as_slice(), &signkey, &verkey).unwrap();
+        assert!(!bad
+            .verify_cached(messages.as_slice(), &verkey, &mut filter)
+            .unwrap());
+        assert!(!filter.contains(&bad, messages.as_slice(), &verkey));
+    }
+
+    #[test]
+    fn known_good_filter_size() {
+        for rate in &[0.0, 1.0, -0.5, 2.0, f64::NAN, f64::INFINITY] {
+            assert!(KnownGoodFilter::new(100, *rate).is_err());
+        }
+        assert!(KnownGoodFilter::new(usize::MAX, 1e-300).is_err());
+
+        // Tiny and almost certain rates still give a usable filter
+        let filter = KnownGoodFilter::new(0, 0.999).unwrap();
+        assert_eq!(filter.bit_count, 64);
+        assert!(filter.hash_count >= 1);
+        let filter = KnownGoodFilter::new(10, f64::MIN_POSITIVE).unwrap();
+        assert_eq!(filter.bit_count % 64, 0);
+        assert_eq!(filter.bits.len() * 64, filter.bit_count);
+    }
+}
@@ -0,0 +1,150 @@
+// Memoizing signature verification results for a session that keeps seeing the same
+// credentials, so repeats skip the pairing.
+//
+// The memo holds a SHAKE256 digest of each signature, message set and public key with the
+// result. The digest hides nothing an attacker can guess: anyone with the memo and the
+// signature and key can hash candidate messages until one matches, which is cheap for low
+// entropy messages like dates or yes/no attributes. Treat the memo as sensitive as the
+// messages it was filled from.
+
+use super::super::SignatureMessage;
+use super::keys::PublicKey;
+use super::signature::Signature;
+use crate::errors::prelude::*;
+
+use amcl_wrapper::utils::hash_msg;
+
+use std::collections::{HashMap, VecDeque};
+
+pub mod prelude {
+    pub use super::MemoVerifier;
+}
+
+const MEMO_DST: &[u8] = b"BBS+ memo verifier";
+
+/// Remembers the result of `Signature::verify` for (signature, messages, public key) triples
+/// verified before so a session that sees the same triple again skips the pairing. Unlike
+/// `KnownGoodFilter` the answer is exact: entries are keyed by a SHAKE256 digest of the whole
+/// triple, and invalid triples are remembered as invalid. Only the digests and the results are
+/// stored, no messages or keys, but the digests can be matched against guessed messages, see
+/// the top of `memo.rs`. Once `capacity` entries are held the oldest one is dropped for each
+/// new one.
+#[derive(Debug, Clone)]
+pub struct MemoVerifier {
+    capacity: usize,
+    results: HashMap<Vec<u8>, bool>,
+    order: VecDeque<Vec<u8>>,
+    hits: usize,
+    misses: usize,
+}
+
+impl MemoVerifier {
+    pub fn new(capacity: usize) -> Self {
+        MemoVerifier {
+            capacity: capacity.max(1),
+            results: HashMap::new(),
+            order: VecDeque::new(),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Same result as `signature.verify(messages, verkey)`, from memory if the triple was seen
+    pub fn verify(
+        &mut self,
+        signature: &Signature,
+        messages: &[SignatureMessage],
+        verkey: &PublicKey,
+    ) -> Result<bool, BBSError> {
+        let key = Self::digest(signature, messages, verkey);
+        if let Some(result) = self.results.get(&key) {
+            self.hits += 1;
+            return Ok(*result);
+        }
+        // Errors, like a wrong number of messages, aren't remembered
+        let result = signature.verify(messages, verkey)?;
+        self.misses += 1;
+        if self.order.len() == self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.results.remove(&oldest);
+            }
+        }
+        self.order.push_back(key.clone());
+        self.results.insert(key, result);
+        Ok(result)
+    }
+
+    /// Number of calls answered from memory
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// Number of calls that ran `verify`
+    pub fn misses(&self) -> usize {
+        self.misses
+    }
+
+    /// Number of remembered triples
+    pub fn len(&self) -> usize {
+        self.results.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.results.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.results.clear();
+        self.order.clear();
+    }
+
+    fn digest(signature: &Signature, messages: &[SignatureMessage], verkey: &PublicKey) -> Vec<u8> {
+        let mut bytes = MEMO_DST.to_vec();
+        bytes.append(&mut signature.to_bytes());
+        bytes.extend_from_slice(&(messages.len() as u32).to_be_bytes());
+        for m in messages {
+            bytes.append(&mut m.to_bytes());
+        }
+        bytes.append(&mut verkey.to_bytes());
+        hash_msg(&bytes).to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
+    use signatures::bbs::keys::generate;
+
+    #[test]
+    fn memo_verifier_matches_verify() {
+        let messages = FieldElementVector::random(3);
+        let (verkey, signkey) = generate(3).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+        let mut changed = messages.clone();
+        changed[0] = FieldElement::random();
+
+        let mut memo = MemoVerifier::new(2);
+        for m in &[messages.clone(), changed.clone()] {
+            let expected = sig.verify(m.as_slice(), &verkey).unwrap();
+            assert_eq!(memo.verify(&sig, m.as_slice(), &verkey).unwrap(), expected);
+            assert_eq!(memo.verify(&sig, m.as_slice(), &verkey).unwrap(), expected);
+        }
+        assert_eq!((memo.hits(), memo.misses()), (2, 2));
+        assert_eq!(memo.len(), 2);
+
+        // The oldest entry makes room for a new one
+        let other = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+        assert!(memo.verify(&other, messages.as_slice(), &verkey).unwrap());
+        assert_eq!(memo.len(), 2);
+        assert!(memo.verify(&sig, messages.as_slice(), &verkey).unwrap());
+        assert_eq!(memo.misses(), 4);
+        assert!(!memo.verify(&sig, changed.as_slice(), &verkey).unwrap());
+
+        assert!(memo
+            .verify(&sig, &messages.as_slice()[1..], &verkey)
+            .is_err());
+        memo.clear();
+        assert!(memo.is_empty());
+    }
+}
@@ -0,0 +1,376 @@
+use super::super::SignatureMessage;
+use super::keys::{PublicKey, SecretKey};
+use super::signature::Signature;
+use crate::errors::prelude::*;
+
+use amcl_wrapper::field_elem::FieldElement;
+
+pub mod prelude {
+    pub use super::{
+        prove_leaf, prove_set_member, LeafProof, MerklePath, MerkleTree, SetMemberProof,
+    };
+}
+
+// Domain separation between leaf, interior node and padding hashes so a node can't be passed off
+// as a leaf, and no value's leaf hash is the padding
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+const PADDING_PREFIX: u8 = 2;
+
+fn hash_leaf(leaf: &FieldElement) -> FieldElement {
+    let mut bytes = vec![LEAF_PREFIX];
+    bytes.append(&mut leaf.to_bytes());
+    FieldElement::from_msg_hash(&bytes)
+}
+
+fn padding() -> FieldElement {
+    FieldElement::from_msg_hash(&[PADDING_PREFIX])
+}
+
+fn hash_node(left: &FieldElement, right: &FieldElement) -> FieldElement {
+    let mut bytes = vec![NODE_PREFIX];
+    bytes.append(&mut left.to_bytes());
+    bytes.append(&mut right.to_bytes());
+    FieldElement::from_msg_hash(&bytes)
+}
+
+/// Binary Merkle tree over a list of leaf messages. The number of leaves is padded to the next
+/// power of 2 with a padding node that isn't the hash of any leaf, so no value, zero included,
+/// can be shown at a padding position.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MerkleTree {
+    levels: Vec<Vec<FieldElement>>, // levels[0] are the hashed leaves, the last level is the root
+    leaf_count: usize,
+}
+
+impl MerkleTree {
+    pub fn new(leaves: &[SignatureMessage]) -> Result<Self, BBSError> {
+        if leaves.is_empty() {
+            return Err(BBSError::from_kind(BBSErrorKind::GeneralError {
+                msg: "Merkle tree needs at least one leaf".to_string(),
+            }));
+        }
+        let mut level: Vec<FieldElement> = leaves.iter().map(hash_leaf).collect();
+        level.resize(leaves.len().next_power_of_two(), padding());
+
+        let mut levels = vec![level];
+        while levels[levels.len() - 1].len() > 1 {
+            let next = levels[levels.len() - 1]
+                .chunks(2)
+                .map(|pair| hash_node(&pair[0], &pair[1]))
+                .collect();
+            levels.push(next);
+        }
+        Ok(MerkleTree {
+            levels,
+            leaf_count: leaves.len(),
+        })
+    }
+
+    pub fn root(&self) -> FieldElement {
+        self.levels[self.levels.len() - 1][0].clone()
+    }
+
+    pub fn leaf_count(&self) -> usize {
+        self.leaf_count
+    }
+
+    /// Authentication path for the leaf at `index`
+    pub fn path(&self, index: usize) -> Result<MerklePath, BBSError> {
+        if index >= self.leaf_count {
+            return Err(BBSError::from_kind(BBSErrorKind::GeneralError {
+                msg: format!("Index {} should be less than {}", index, self.leaf_count),
+            }));
+        }
+        let mut siblings = Vec::with_capacity(self.levels.len() - 1);
+        let mut i = index;
+        for level in &self.levels[..self.levels.len() - 1] {
+            siblings.push(level[i ^ 1].clone());
+            i >>= 1;
+        }
+        Ok(MerklePath { index, siblings })
+    }
+}
+
+/// Siblings from a leaf up to the root. `index` decides on which side each sibling goes.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MerklePath {
+    pub index: usize,
+    pub siblings: Vec<FieldElement>,
+}
+
+impl MerklePath {
+    /// Root of the tree that `leaf` is part of if this path is correct. `None` if `index` doesn't
+    /// fit in a tree of the path's depth, its bits past the depth would otherwise be ignored and
+    /// one leaf would have many indices.
+    pub fn compute_root(&self, leaf: &FieldElement) -> Option<FieldElement> {
+        let depth = self.siblings.len();
+        if depth < 8 * std::mem::size_of::<usize>() && self.index >> depth != 0 {
+            return None;
+        }
+        let mut node = hash_leaf(leaf);
+        let mut i = self.index;
+        for s in &self.siblings {
+            node = if i & 1 == 0 {
+                hash_node(&node, s)
+            } else {
+                hash_node(s, &node)
+            };
+            i >>= 1;
+        }
+        Some(node)
+    }
+}
+
+/// Disclosure of a single leaf of a signed Merkle tree.
+/// The signature over the root is revealed so presentations using it are linkable.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeafProof {
+    pub leaf: SignatureMessage,
+    pub path: MerklePath,
+}
+
+pub fn prove_leaf(leaf: &SignatureMessage, path: &MerklePath) -> LeafProof {
+    LeafProof {
+        leaf: leaf.clone(),
+        path: path.clone(),
+    }
+}
+
+impl LeafProof {
+    /// Check that `signature` is over the root this leaf and path lead to
+    pub fn verify(&self, signature: &Signature, verkey: &PublicKey) -> Result<bool, BBSError> {
+        match self.path.compute_root(&self.leaf) {
+            Some(root) => signature.verify(&[root], verkey),
+            None => Ok(false),
+        }
+    }
+}
+
+/// Disclosure that `value` is in a set whose Merkle root is the message at `root_index` of a
+/// signed credential, e.g. a postcode among the allowed ones, for sets of any size under one
+/// message. Checked with `Signature::verify_set_member` which needs the signature and all
+/// messages, so like `LeafProof` it is linkable. The verifier names the slot of the set itself,
+/// `root_index` only has to agree with it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetMemberProof {
+    pub value: SignatureMessage,
+    pub path: MerklePath,
+    pub root_index: usize,
+}
+
+pub fn prove_set_member(
+    value: &SignatureMessage,
+    path: &MerklePath,
+    root_index: usize,
+) -> SetMemberProof {
+    SetMemberProof {
+        value: value.clone(),
+        path: path.clone(),
+        root_index,
+    }
+}
+
+impl Signature {
+    /// Sign the Merkle root of `leaf_messages` as the only message of `verkey`.
+    /// Any number of leaves can be signed with a key for a single message, individual leaves are
+    /// later disclosed with `prove_leaf`.
+    pub fn new_merkle(
+        leaf_messages: &[SignatureMessage],
+        signkey: &SecretKey,
+        verkey: &PublicKey,
+    ) -> Result<(Self, MerkleTree), BBSError> {
+        if verkey.message_count() != 1 {
+            return Err(BBSError::from_kind(BBSErrorKind::GeneralError {
+                msg: format!(
+                    "Key must have exactly 1 message base, has {}",
+                    verkey.message_count()
+                ),
+            }));
+        }
+        let tree = MerkleTree::new(leaf_messages)?;
+        let signature = Signature::new(&[tree.root()], signkey, verkey)?;
+        Ok((signature, tree))
+    }
+
+    /// Check that `proof.value` and its path lead to the root at `root_index` of `messages`,
+    /// the slot the verifier expects the set in, and that this signature is over `messages`.
+    /// False if the proof names another slot, any signed message could be the root of a set
+    /// the holder picked.
+    pub fn verify_set_member(
+        &self,
+        proof: &SetMemberProof,
+        root_index: usize,
+        messages: &[SignatureMessage],
+        verkey: &PublicKey,
+    ) -> Result<bool, BBSError> {
+        let root = messages.get(root_index).ok_or_else(|| {
+            BBSError::from_kind(BBSErrorKind::GeneralError {
+                msg: format!(
+                    "Root index {} should be less than {}",
+                    root_index,
+                    messages.len()
+                ),
+            })
+        })?;
+        if proof.root_index != root_index {
+            return Ok(false);
+        }
+        if proof.path.compute_root(&proof.value).as_ref() != Some(root) {
+            return Ok(false);
+        }
+        self.verify(messages, verkey)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amcl_wrapper::field_elem::FieldElementVector;
+    use signatures::bbs::keys::generate;
+    use signatures::bbs::messages::encode_u64;
+
+    #[test]
+    fn merkle_leaf_disclosure() {
+        let leaves = FieldElementVector::random(8);
+        let (verkey, signkey) = generate(1).unwrap();
+
+        let (sig, tree) = Signature::new_merkle(leaves.as_slice(), &signkey, &verkey).unwrap();
+        assert!(sig.verify(&[tree.root()], &verkey).unwrap());
+
+        let path = tree.path(5).unwrap();
+        assert_eq!(path.siblings.len(), 3);
+        let proof = prove_leaf(&leaves[5], &path);
+        assert!(proof.verify(&sig, &verkey).unwrap());
+
+        // Leaf not at that position
+        let proof = prove_leaf(&leaves[4], &path);
+        assert!(!proof.verify(&sig, &verkey).unwrap());
+        // Leaf not in tree
+        let proof = prove_leaf(&FieldElement::random(), &path);
+        assert!(!proof.verify(&sig, &verkey).unwrap());
+
+        assert!(tree.path(8).is_err());
+    }
+
+    #[test]
+    fn merkle_uneven_leaves() {
+        let leaves = FieldElementVector::random(5);
+        let (verkey, signkey) = generate(1).unwrap();
+        let (sig, tree) = Signature::new_merkle(leaves.as_slice(), &signkey, &verkey).unwrap();
+        for i in 0..5 {
+            let proof = prove_leaf(&leaves[i], &tree.path(i).unwrap());
+            assert!(proof.verify(&sig, &verkey).unwrap());
+        }
+
+        // Nothing, zero included, is a leaf at a padding position, even with the right siblings
+        for i in 5..8 {
+            let padded = MerklePath {
+                index: i,
+                siblings: (0..3)
+                    .map(|level| tree.levels[level][(i >> level) ^ 1].clone())
+                    .collect(),
+            };
+            let proof = prove_leaf(&FieldElement::zero(), &padded);
+            assert!(!proof.verify(&sig, &verkey).unwrap());
+        }
+
+        // An index past the depth of the path doesn't alias a leaf
+        let mut aliased = tree.path(2).unwrap();
+        aliased.index += 8;
+        assert_eq!(aliased.compute_root(&leaves[2]), None);
+        assert!(!prove_leaf(&leaves[2], &aliased)
+            .verify(&sig, &verkey)
+            .unwrap());
+
+        // Root is signed as a single message
+        let (verkey, signkey) = generate(2).unwrap();
+        let err = Signature::new_merkle(leaves.as_slice(), &signkey, &verkey).unwrap_err();
+        assert_eq!(
+            err.kind().to_string(),
+            "\"Key must have exactly 1 message base, has 2\""
+        );
+    }
+
+    #[test]
+    fn merkle_set_member() {
+        let set = FieldElementVector::random(16);
+        let tree = MerkleTree::new(set.as_slice()).unwrap();
+        let messages = vec![FieldElement::random(), tree.root(), FieldElement::random()];
+        let (verkey, signkey) = generate(3).unwrap();
+        let sig = Signature::new(&messages, &signkey, &verkey).unwrap();
+
+        let path = tree.path(11).unwrap();
+        assert_eq!(path.siblings.len(), 4);
+        let proof = prove_set_member(&set[11], &path, 1);
+        assert!(sig
+            .verify_set_member(&proof, 1, &messages, &verkey)
+            .unwrap());
+
+        // Not a member, or the root not where the proof says
+        let proof = prove_set_member(&FieldElement::random(), &path, 1);
+        assert!(!sig
+            .verify_set_member(&proof, 1, &messages, &verkey)
+            .unwrap());
+        let proof = prove_set_member(&set[11], &path, 0);
+        assert!(!sig
+            .verify_set_member(&proof, 0, &messages, &verkey)
+            .unwrap());
+        assert!(!sig
+            .verify_set_member(&proof, 1, &messages, &verkey)
+            .unwrap());
+        let proof = prove_set_member(&set[11], &path, 1);
+        assert!(sig
+            .verify_set_member(&proof, 3, &messages, &verkey)
+            .is_err());
+
+        // The root has to be signed
+        let mut other = messages.clone();
+        other[0] = FieldElement::random();
+        assert!(!sig.verify_set_member(&proof, 1, &other, &verkey).unwrap());
+
+        // A set of the holder's choosing whose root sits in another slot
+        let own_set = FieldElementVector::random(4);
+        let own_tree = MerkleTree::new(own_set.as_slice()).unwrap();
+        let messages = vec![own_tree.root(), tree.root(), FieldElement::random()];
+        let sig = Signature::new(&messages, &signkey, &verkey).unwrap();
+        let proof = prove_set_member(&own_set[2], &own_tree.path(2).unwrap(), 0);
+        assert!(sig
+            .verify_set_member(&proof, 0, &messages, &verkey)
+            .unwrap());
+        assert!(!sig
+            .verify_set_member(&proof, 1, &messages, &verkey)
+            .unwrap());
+    }
+
+    #[test]
+    fn merkle_set_member_uneven() {
+        let set: Vec<_> = (1..=5).map(encode_u64).collect();
+        let tree = MerkleTree::new(&set).unwrap();
+        let messages = vec![tree.root()];
+        let (verkey, signkey) = generate(1).unwrap();
+        let sig = Signature::new(&messages, &signkey, &verkey).unwrap();
+        for (i, value) in set.iter().enumerate() {
+            let proof = prove_set_member(value, &tree.path(i).unwrap(), 0);
+            assert!(sig
+                .verify_set_member(&proof, 0, &messages, &verkey)
+                .unwrap());
+        }
+
+        // 0 isn't in the set at any of the padding positions
+        for zero in &[encode_u64(0), FieldElement::zero()] {
+            for i in 5..8 {
+                let path = MerklePath {
+                    index: i,
+                    siblings: (0..3)
+                        .map(|level| tree.levels[level][(i >> level) ^ 1].clone())
+                        .collect(),
+                };
+                let proof = prove_set_member(zero, &path, 0);
+                assert!(!sig
+                    .verify_set_member(&proof, 0, &messages, &verkey)
+                    .unwrap());
+            }
+        }
+    }
+}
@@ -0,0 +1,1044 @@
+use super::super::{SignatureBlinding, SignatureMessage};
+use super::keys::{PublicKey, SecretKey};
+use super::signature::Signature;
+use crate::errors::prelude::*;
+
+use amcl_wrapper::constants::MODBYTES;
+use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
+use unicode_normalization::UnicodeNormalization;
+
+use std::convert::TryFrom;
+
+pub use bbs_derive::BbsMessages;
+
+pub mod prelude {
+    pub use super::{
+        derive_blinding, encode_json, encode_message_salted, encode_split, encode_string,
+        encode_u64, messages_from_bytes, messages_from_bytes_with_order, parse_decimal, parse_hex,
+        BbsMessages, ByteOrder, EncodingProfile, MessageSalt, PreparedMessages, ReductionMode,
+        Schema, SchemaField, ToMessage, ToMessages,
+    };
+}
+
+const AGGREGATE_DST: &[u8] = b"BBS+ aggregate messages";
+
+/// A message set laid out as the scalars of the `b` computation so it can be verified against
+/// many signatures or keys without rebuilding the vector each time.
+/// Only the blinding factor slot changes between signatures.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(try_from = "PreparedMessagesData")]
+pub struct PreparedMessages {
+    // 1 for g1, the blinding factor of the signature and then every message
+    pub(crate) scalars: FieldElementVector,
+}
+
+// What deserializing reads before `PreparedMessages` checks the layout
+#[derive(Deserialize)]
+struct PreparedMessagesData {
+    scalars: FieldElementVector,
+}
+
+impl TryFrom<PreparedMessagesData> for PreparedMessages {
+    type Error = String;
+
+    fn try_from(data: PreparedMessagesData) -> Result<Self, String> {
+        if data.scalars.len() < 2 || data.scalars[0] != FieldElement::one() {
+            return Err("Prepared messages should start with 1 and the blinding slot".to_string());
+        }
+        Ok(PreparedMessages {
+            scalars: data.scalars,
+        })
+    }
+}
+
+impl PreparedMessages {
+    pub fn new(messages: &[SignatureMessage]) -> Self {
+        let mut scalars = FieldElementVector::with_capacity(messages.len() + 2);
+        scalars.push(FieldElement::one());
+        scalars.push(FieldElement::zero());
+        for m in messages {
+            scalars.push(m.clone());
+        }
+        PreparedMessages { scalars }
+    }
+
+    /// Number of messages
+    pub fn len(&self) -> usize {
+        self.scalars.len().saturating_sub(2)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Encode a string attribute as a message. The string is brought to Unicode Normalization
+/// Form C before hashing so that e.g. a precomposed "é" and "e" followed by a combining acute
+/// accent, which look the same to users, encode to the same message.
+pub fn encode_string(s: &str) -> SignatureMessage {
+    let normalized: String = s.nfc().collect();
+    FieldElement::from_msg_hash(normalized.as_bytes())
+}
+
+/// Encode a string attribute as two messages, the first `at` bytes and the rest, each with
+/// `encode_string`. Signing both parts as consecutive messages is what allows proving that the
+/// value starts or ends with a public string while hiding the other part, see `PrefixProof`.
+/// The split point has to be fixed by the schema, like a length or the position of a delimiter,
+/// since any split made at signing time is the only one a proof can be made for.
+/// `at` must be on a character boundary.
+pub fn encode_split(
+    value: &str,
+    at: usize,
+) -> Result<(SignatureMessage, SignatureMessage), BBSError> {
+    if !value.is_char_boundary(at) {
+        return Err(BBSError::from_kind(BBSErrorKind::GeneralError {
+            msg: format!("Cannot split value at {}", at),
+        }));
+    }
+    let (head, tail) = value.split_at(at);
+    Ok((encode_string(head), encode_string(tail)))
+}
+
+/// How raw message bytes are turned into a `SignatureMessage`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReductionMode {
+    /// Hash to a field element with `FieldElement::from_msg_hash`, used by everything else in
+    /// this module
+    Hash,
+    /// Older signers read the bytes as a big endian integer reduced modulo the group order.
+    /// Values of up to `MODBYTES` (48) bytes only and distinct values can collide, so only for
+    /// checking existing credentials.
+    Legacy,
+}
+
+impl ReductionMode {
+    pub fn reduce(self, raw: &[u8]) -> Result<SignatureMessage, BBSError> {
+        match self {
+            ReductionMode::Hash => Ok(FieldElement::from_msg_hash(raw)),
+            ReductionMode::Legacy => {
+                if raw.len() > MODBYTES {
+                    return Err(BBSError::from_kind(BBSErrorKind::GeneralError {
+                        msg: format!(
+                            "Legacy messages are at most {} bytes, found {}",
+                            MODBYTES,
+                            raw.len()
+                        ),
+                    }));
+                }
+                let mut bytes = [0u8; MODBYTES];
+                bytes[MODBYTES - raw.len()..].copy_from_slice(raw);
+                Ok(FieldElement::from(&bytes))
+            }
+        }
+    }
+}
+
+/// Byte order a signer serialized integer message values in before hashing or reducing them.
+/// Helpers taking one expect the values in network order, big endian, and reverse them for a
+/// `LittleEndian` signer, so a verifier matches either signer from the same values. Byte order
+/// only means something for a fixed width integer, so `LittleEndian` takes values of 1, 2, 4,
+/// 8 or 16 bytes, or a `MODBYTES` field element, and refuses strings of any other length
+/// rather than reversing them. Raw bytes are used as given everywhere else, which is
+/// `BigEndian`, the default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ByteOrder {
+    #[default]
+    BigEndian,
+    LittleEndian,
+}
+
+impl ByteOrder {
+    /// `value`, in network order, as the signer serialized it. Fails for a `LittleEndian`
+    /// value that isn't an integer width, see `ByteOrder`.
+    pub fn apply(self, value: &[u8]) -> Result<Vec<u8>, BBSError> {
+        match self {
+            ByteOrder::BigEndian => Ok(value.to_vec()),
+            ByteOrder::LittleEndian => match value.len() {
+                1 | 2 | 4 | 8 | 16 | MODBYTES => Ok(value.iter().rev().cloned().collect()),
+                len => Err(BBSError::from_kind(BBSErrorKind::GeneralError {
+                    msg: format!(
+                        "Little endian values are integers of 1, 2, 4, 8, 16 or {} bytes, found {}",
+                        MODBYTES, len
+                    ),
+                })),
+            },
+        }
+    }
+}
+
+/// Random value mixed into a message encoding, see `encode_message_salted`
+pub type MessageSalt = FieldElement;
+
+/// Encode `value` together with `salt` as `H(salt || value)`. Without a salt a low entropy value
+/// like a yes/no flag can be found from its encoding by trying every candidate, which matters
+/// wherever encodings are exposed without the value, e.g. the sibling hashes of a Merkle path.
+/// The signer picks a fresh salt per message and hands the salts to the holder, who reveals the
+/// salt together with the value.
+pub fn encode_message_salted(value: &[u8], salt: &MessageSalt) -> SignatureMessage {
+    let mut bytes = salt.to_bytes();
+    bytes.extend_from_slice(value);
+    FieldElement::from_msg_hash(&bytes)
+}
+
+/// One message per input, in order, for application data of any length. Message `i` is
+/// `FieldElement::from_msg_hash(len(dst) || dst || inputs[i])` with `len(dst)` as 4 bytes big
+/// endian: SHAKE256 with a 48 byte output reduced modulo the group order. So unlike
+/// `FieldElement::from_bytes` every byte of an input counts, and data hashed under one `dst`
+/// gives different messages under another. Implementations that want the same messages must use
+/// this procedure and the same `dst`.
+pub fn messages_from_bytes(inputs: &[&[u8]], dst: &[u8]) -> Vec<SignatureMessage> {
+    let prefix = dst_prefix(dst);
+    inputs
+        .iter()
+        .map(|input| message_from_bytes(&prefix, input))
+        .collect()
+}
+
+/// Same as `messages_from_bytes` for a signer that serialized the inputs in `order`, see
+/// `ByteOrder`. `BigEndian` hashes the inputs as given, `LittleEndian` fails unless every
+/// input is an integer width.
+pub fn messages_from_bytes_with_order(
+    inputs: &[&[u8]],
+    dst: &[u8],
+    order: ByteOrder,
+) -> Result<Vec<SignatureMessage>, BBSError> {
+    let prefix = dst_prefix(dst);
+    inputs
+        .iter()
+        .map(|input| Ok(message_from_bytes(&prefix, &order.apply(input)?)))
+        .collect()
+}
+
+fn dst_prefix(dst: &[u8]) -> Vec<u8> {
+    let mut prefix = (dst.len() as u32).to_be_bytes().to_vec();
+    prefix.extend_from_slice(dst);
+    prefix
+}
+
+fn message_from_bytes(prefix: &[u8], input: &[u8]) -> SignatureMessage {
+    let mut bytes = prefix.to_vec();
+    bytes.extend_from_slice(input);
+    FieldElement::from_msg_hash(&bytes)
+}
+
+const BLINDING_DST: &[u8] = b"BBS+ blinding";
+
+/// Blinding factor derived from a holder secret `seed` and a `context` naming what it blinds,
+/// e.g. a credential id, so a wallet restored from the seed can recompute it. Distinct contexts
+/// give unrelated blindings. The seed must be secret and high entropy: anyone who knows it can
+/// unblind, and a guessable seed lets the signer find the hidden messages. Use with
+/// `MultiBlindCommitment::new_with_blinding`, `MultiBlindCommitment::new` picks a random one.
+pub fn derive_blinding(seed: &[u8], context: &[u8]) -> SignatureBlinding {
+    let mut bytes = BLINDING_DST.to_vec();
+    bytes.extend_from_slice(&(seed.len() as u32).to_be_bytes());
+    bytes.extend_from_slice(seed);
+    bytes.extend_from_slice(context);
+    FieldElement::from_msg_hash(&bytes)
+}
+
+/// Encode an integer, e.g. a counter or an attribute for a `Predicate::Range`, as the field
+/// element of the same value. Unlike `FieldElement::from(u64)`, which goes through `isize`
+/// and amcl's first limb, this is correct for the whole `u64` range.
+pub fn encode_u64(value: u64) -> SignatureMessage {
+    let mut bytes = [0u8; MODBYTES];
+    bytes[MODBYTES - 8..].copy_from_slice(&value.to_be_bytes());
+    FieldElement::from(&bytes)
+}
+
+/// Message for the non-negative decimal integer `value`, e.g. from an issuer configuration.
+/// Only ASCII digits are accepted and the value must be less than the group order, nothing
+/// is reduced.
+pub fn parse_decimal(value: &str) -> Result<SignatureMessage, BBSError> {
+    parse_radix(value, 10)
+}
+
+/// Same as `parse_decimal` for a hex string with an optional `0x` prefix, either case
+pub fn parse_hex(value: &str) -> Result<SignatureMessage, BBSError> {
+    let digits = if value.starts_with("0x") || value.starts_with("0X") {
+        &value[2..]
+    } else {
+        value
+    };
+    parse_radix(digits, 16)
+}
+
+fn parse_radix(value: &str, radix: u32) -> Result<SignatureMessage, BBSError> {
+    let invalid = |msg: &str| {
+        BBSError::from_kind(BBSErrorKind::GeneralError {
+            msg: format!("Invalid number {:?}: {}", value, msg),
+        })
+    };
+    if value.is_empty() {
+        return Err(invalid("no digits"));
+    }
+    // Big endian bytes of the value, always MODBYTES long
+    let mut bytes = [0u8; MODBYTES];
+    for c in value.chars() {
+        let mut carry = c.to_digit(radix).ok_or_else(|| invalid("not a digit"))?;
+        for b in bytes.iter_mut().rev() {
+            carry += (*b as u32) * radix;
+            *b = carry as u8;
+            carry >>= 8;
+        }
+        if carry != 0 {
+            return Err(invalid("not less than the group order"));
+        }
+    }
+    let element = FieldElement::from(&bytes);
+    // `From` reduces, so any change means the value was too large
+    if element.to_bytes().as_slice() != &bytes[..] {
+        return Err(invalid("not less than the group order"));
+    }
+    Ok(element)
+}
+
+/// Hash of all of `messages`, in order, to sign as the one message of a key for a single
+/// message with `Signature::new_aggregate`. The signature and key are as small as for one
+/// attribute whatever the count, but no attribute can be disclosed or proven on its own any
+/// more: a verifier needs every attribute to check the hash, and `PoKOfSignature` can only
+/// hide or reveal the digest as a whole.
+pub fn aggregate_messages(messages: &[SignatureMessage]) -> SignatureMessage {
+    let mut bytes = AGGREGATE_DST.to_vec();
+    bytes.extend_from_slice(&(messages.len() as u32).to_be_bytes());
+    for m in messages {
+        bytes.append(&mut m.to_bytes());
+    }
+    FieldElement::from_msg_hash(&bytes)
+}
+
+/// A value that is one message: a `SignatureMessage` as is, integers with `encode_u64`, `bool`
+/// as 0 or 1 and strings with `encode_string`
+pub trait ToMessage {
+    fn to_message(&self) -> SignatureMessage;
+}
+
+impl ToMessage for SignatureMessage {
+    fn to_message(&self) -> SignatureMessage {
+        self.clone()
+    }
+}
+
+impl ToMessage for u64 {
+    fn to_message(&self) -> SignatureMessage {
+        encode_u64(*self)
+    }
+}
+
+impl ToMessage for u32 {
+    fn to_message(&self) -> SignatureMessage {
+        encode_u64(u64::from(*self))
+    }
+}
+
+impl ToMessage for bool {
+    fn to_message(&self) -> SignatureMessage {
+        encode_u64(u64::from(*self))
+    }
+}
+
+impl ToMessage for str {
+    fn to_message(&self) -> SignatureMessage {
+        encode_string(self)
+    }
+}
+
+impl ToMessage for String {
+    fn to_message(&self) -> SignatureMessage {
+        encode_string(self)
+    }
+}
+
+impl<T: ToMessage + ?Sized> ToMessage for &T {
+    fn to_message(&self) -> SignatureMessage {
+        (**self).to_message()
+    }
+}
+
+/// A set of attributes signed as one message per attribute. `#[derive(BbsMessages)]` implements
+/// it for a struct whose fields are all `ToMessage`, one message per field in declaration order:
+/// `Signature::new(person.to_messages().as_slice(), &signkey, &verkey)`.
+pub trait ToMessages {
+    /// Number of messages, the `message_count` of the key to sign them with
+    fn message_count() -> usize;
+
+    fn to_messages(&self) -> Vec<SignatureMessage>;
+}
+
+/// The integer a message made with `encode_u64` holds, `None` for any larger value
+pub(crate) fn to_u64(message: &SignatureMessage) -> Option<u64> {
+    let bytes = message.to_bytes();
+    let (high, low) = bytes.split_at(bytes.len() - 8);
+    if high.iter().any(|b| *b != 0) {
+        return None;
+    }
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(low);
+    Some(u64::from_be_bytes(buf))
+}
+
+impl Signature {
+    /// Sign `values` each encoded with a fresh salt. Returns the signature and the salts in
+    /// message order.
+    pub fn new_salted(
+        values: &[&[u8]],
+        signkey: &SecretKey,
+        verkey: &PublicKey,
+    ) -> Result<(Self, Vec<MessageSalt>), BBSError> {
+        let salts: Vec<MessageSalt> = values.iter().map(|_| MessageSalt::random()).collect();
+        let messages = encode_salted_values(values, &salts)?;
+        let signature = Signature::new(messages.as_slice(), signkey, verkey)?;
+        Ok((signature, salts))
+    }
+
+    /// Sign `messages` followed by `counter` as a reserved last message, so `verkey` is for
+    /// one message more than given. The signer raises the counter with each issuance and
+    /// verifiers reject everything below a cutoff with `verify_counter`, revoking all
+    /// credentials issued before it at once.
+    pub fn new_with_counter(
+        messages: &[SignatureMessage],
+        counter: u64,
+        signkey: &SecretKey,
+        verkey: &PublicKey,
+    ) -> Result<Self, BBSError> {
+        let mut messages = messages.to_vec();
+        messages.push(encode_u64(counter));
+        Signature::new(messages.as_slice(), signkey, verkey)
+    }
+
+    /// Verify a signature made with `new_with_counter`. `messages` includes the counter as its
+    /// last message, as presented by the holder. False if the counter is below `min_counter`.
+    pub fn verify_counter(
+        &self,
+        messages: &[SignatureMessage],
+        verkey: &PublicKey,
+        min_counter: u64,
+    ) -> Result<bool, BBSError> {
+        match messages.last().and_then(to_u64) {
+            Some(counter) if counter >= min_counter => self.verify(messages, verkey),
+            _ => Ok(false),
+        }
+    }
+
+    /// Verify over raw messages reduced with `reduction`, e.g. to check credentials issued
+    /// before the move to hashed messages.
+    pub fn verify_with_reduction(
+        &self,
+        raw_messages: &[&[u8]],
+        reduction: ReductionMode,
+        verkey: &PublicKey,
+    ) -> Result<bool, BBSError> {
+        self.verify_with_byte_order(raw_messages, reduction, ByteOrder::BigEndian, verkey)
+    }
+
+    /// Same as `verify_with_reduction` for a signer that serialized the raw messages in
+    /// `order` before reducing them, see `ByteOrder`. Fails for `LittleEndian` unless every
+    /// raw message is an integer width.
+    pub fn verify_with_byte_order(
+        &self,
+        raw_messages: &[&[u8]],
+        reduction: ReductionMode,
+        order: ByteOrder,
+        verkey: &PublicKey,
+    ) -> Result<bool, BBSError> {
+        let messages = raw_messages
+            .iter()
+            .map(|m| reduction.reduce(&order.apply(m)?))
+            .collect::<Result<Vec<SignatureMessage>, BBSError>>()?;
+        self.verify(messages.as_slice(), verkey)
+    }
+
+    /// Verify a signature whose message at `schema_index` is reserved for the hash of the
+    /// schema the other messages follow, e.g. `EncodingProfile::fingerprint`. Fails unless that
+    /// message is `expected_schema_hash`, so a signature can't be read under another layout.
+    pub fn verify_schema(
+        &self,
+        messages: &[SignatureMessage],
+        verkey: &PublicKey,
+        expected_schema_hash: &FieldElement,
+        schema_index: usize,
+    ) -> Result<bool, BBSError> {
+        match messages.get(schema_index) {
+            None => Err(BBSError::from_kind(BBSErrorKind::GeneralError {
+                msg: format!(
+                    "Schema index {} should be less than {}",
+                    schema_index,
+                    messages.len()
+                ),
+            })),
+            Some(m) if m != expected_schema_hash => Ok(false),
+            Some(_) => self.verify(messages, verkey),
+        }
+    }
+
+    /// Sign `aggregate_messages(messages)` under `verkey` for one message, see
+    /// `aggregate_messages` for what this gives up
+    pub fn new_aggregate(
+        messages: &[SignatureMessage],
+        signkey: &SecretKey,
+        verkey: &PublicKey,
+    ) -> Result<Self, BBSError> {
+        Signature::new(&[aggregate_messages(messages)], signkey, verkey)
+    }
+
+    /// Verify a signature made with `new_aggregate` over all of `messages`
+    pub fn verify_aggregate(
+        &self,
+        messages: &[SignatureMessage],
+        verkey: &PublicKey,
+    ) -> Result<bool, BBSError> {
+        self.verify(&[aggregate_messages(messages)], verkey)
+    }
+
+    /// Verify a signature made with `new_salted`
+    pub fn verify_salted(
+        &self,
+        values: &[&[u8]],
+        salts: &[MessageSalt],
+        verkey: &PublicKey,
+    ) -> Result<bool, BBSError> {
+        let messages = encode_salted_values(values, salts)?;
+        self.verify(messages.as_slice(), verkey)
+    }
+}
+
+fn encode_salted_values(
+    values: &[&[u8]],
+    salts: &[MessageSalt],
+) -> Result<Vec<SignatureMessage>, BBSError> {
+    if values.len() != salts.len() {
+        return Err(BBSError::from_kind(BBSErrorKind::GeneralError {
+            msg: format!("Expected {} salts, found {}", values.len(), salts.len()),
+        }));
+    }
+    Ok(values
+        .iter()
+        .zip(salts)
+        .map(|(v, salt)| encode_message_salted(v, salt))
+        .collect())
+}
+
+/// Field of a JSON credential, see `encode_json`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SchemaField {
+    pub name: String,
+    pub required: bool,
+}
+
+/// Which top level fields of a JSON object are signed and in which order
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Schema {
+    pub fields: Vec<SchemaField>,
+}
+
+impl Schema {
+    /// Schema with the given `(name, required)` fields in signing order
+    pub fn new(fields: &[(&str, bool)]) -> Self {
+        Schema {
+            fields: fields
+                .iter()
+                .map(|(name, required)| SchemaField {
+                    name: name.to_string(),
+                    required: *required,
+                })
+                .collect(),
+        }
+    }
+}
+
+// Type tags so e.g. the string "1" and the number 1 encode differently
+const JSON_ABSENT: u8 = 0;
+const JSON_STRING: u8 = 1;
+const JSON_OTHER: u8 = 2;
+
+/// Encode the fields of `value` listed in `schema` as one message each, in schema order.
+/// Strings are NFC normalized like `encode_string`, any other value is encoded as its compact
+/// JSON text where object keys are sorted, so the result doesn't depend on how the JSON was
+/// formatted. Strings and object keys nested in arrays and objects are NFC normalized too, two
+/// keys of one object that normalize the same are an error. An optional field that is missing encodes to a fixed message different from
+/// every value, including `null`. A missing required field is an error, fields not in the
+/// schema are ignored.
+pub fn encode_json(
+    value: &serde_json::Value,
+    schema: &Schema,
+) -> Result<Vec<SignatureMessage>, BBSError> {
+    let object = value.as_object().ok_or_else(|| {
+        BBSError::from_kind(BBSErrorKind::GeneralError {
+            msg: "Expected a JSON object".to_string(),
+        })
+    })?;
+    let mut messages = Vec::with_capacity(schema.fields.len());
+    for field in &schema.fields {
+        let bytes = match object.get(&field.name) {
+            Some(serde_json::Value::String(s)) => {
+                let mut bytes = vec![JSON_STRING];
+                bytes.extend(s.nfc().collect::<String>().as_bytes());
+                bytes
+            }
+            Some(v) => {
+                let mut bytes = vec![JSON_OTHER];
+                bytes.append(&mut serde_json::to_vec(&json_nfc(v)?).unwrap());
+                bytes
+            }
+            None if field.required => {
+                return Err(BBSError::from_kind(BBSErrorKind::GeneralError {
+                    msg: format!("Missing required field {}", field.name),
+                }))
+            }
+            None => vec![JSON_ABSENT],
+        };
+        messages.push(FieldElement::from_msg_hash(&bytes));
+    }
+    Ok(messages)
+}
+
+// `value` with every string and object key NFC normalized
+fn json_nfc(value: &serde_json::Value) -> Result<serde_json::Value, BBSError> {
+    Ok(match value {
+        serde_json::Value::String(s) => serde_json::Value::String(s.nfc().collect()),
+        serde_json::Value::Array(items) => serde_json::Value::Array(
+            items
+                .iter()
+                .map(json_nfc)
+                .collect::<Result<Vec<_>, BBSError>>()?,
+        ),
+        serde_json::Value::Object(object) => {
+            let mut normalized = serde_json::Map::new();
+            for (key, v) in object {
+                let key: String = key.nfc().collect();
+                if normalized.contains_key(&key) {
+                    return Err(BBSError::from_kind(BBSErrorKind::GeneralError {
+                        msg: format!("Key {} appears twice after normalization", key),
+                    }));
+                }
+                normalized.insert(key, json_nfc(v)?);
+            }
+            serde_json::Value::Object(normalized)
+        }
+        v => v.clone(),
+    })
+}
+
+/// `EncodingProfile` freezes how application attributes are turned into `SignatureMessage`s.
+/// Signer and verifier must agree on the profile, otherwise the same attributes encode to
+/// different messages and verification fails without saying why. Presentations carry the
+/// profile's fingerprint so a mismatch can be reported as such.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EncodingProfile {
+    pub id: String,
+    pub version: u32,
+    pub attributes: Vec<String>, // attribute label for each message in signing order
+}
+
+impl EncodingProfile {
+    pub fn new(id: &str, version: u32, attributes: &[&str]) -> Self {
+        EncodingProfile {
+            id: id.to_string(),
+            version,
+            attributes: attributes.iter().map(|a| a.to_string()).collect(),
+        }
+    }
+
+    /// Canonical byte representation. Every variable length value is length prefixed so
+    /// different profiles cannot produce the same bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&(self.id.len() as u32).to_be_bytes());
+        out.extend_from_slice(self.id.as_bytes());
+        out.extend_from_slice(&self.version.to_be_bytes());
+        out.extend_from_slice(&(self.attributes.len() as u32).to_be_bytes());
+        for a in &self.attributes {
+            out.extend_from_slice(&(a.len() as u32).to_be_bytes());
+            out.extend_from_slice(a.as_bytes());
+        }
+        out
+    }
+
+    /// Hash of the canonical bytes, this is what gets embedded in presentations.
+    pub fn fingerprint(&self) -> FieldElement {
+        FieldElement::from_msg_hash(&self.to_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use signatures::bbs::keys::generate;
+
+    #[test]
+    fn encode_string_normalization() {
+        let composed = "Jos\u{e9}";
+        let decomposed = "Jose\u{301}";
+        assert_ne!(composed.as_bytes(), decomposed.as_bytes());
+        assert_eq!(encode_string(composed), encode_string(decomposed));
+        assert_ne!(encode_string(composed), encode_string("Jose"));
+    }
+
+    #[test]
+    fn legacy_reduction() {
+        let raw: [&[u8]; 2] = [b"1978-04-01", &[0xff; 48]];
+        let (verkey, signkey) = generate(2).unwrap();
+        // Signed under the old reduction
+        let legacy = vec![
+            ReductionMode::Legacy.reduce(raw[0]).unwrap(),
+            ReductionMode::Legacy.reduce(raw[1]).unwrap(),
+        ];
+        let sig = Signature::new(legacy.as_slice(), &signkey, &verkey).unwrap();
+        assert!(sig
+            .verify_with_reduction(&raw, ReductionMode::Legacy, &verkey)
+            .unwrap());
+        assert!(!sig
+            .verify_with_reduction(&raw, ReductionMode::Hash, &verkey)
+            .unwrap());
+
+        let hashed: Vec<SignatureMessage> =
+            raw.iter().map(|m| FieldElement::from_msg_hash(m)).collect();
+        let sig = Signature::new(hashed.as_slice(), &signkey, &verkey).unwrap();
+        assert!(sig
+            .verify_with_reduction(&raw, ReductionMode::Hash, &verkey)
+            .unwrap());
+
+        // The integer value of the bytes
+        assert_eq!(
+            ReductionMode::Legacy.reduce(&[1, 0]).unwrap(),
+            FieldElement::from(256u64)
+        );
+        assert!(ReductionMode::Legacy.reduce(&[1; 49]).is_err());
+    }
+
+    #[test]
+    fn salted_messages() {
+        let salt_1 = MessageSalt::random();
+        let salt_2 = MessageSalt::random();
+        assert_ne!(
+            encode_message_salted(b"yes", &salt_1),
+            encode_message_salted(b"yes", &salt_2)
+        );
+
+        let (verkey, signkey) = generate(2).unwrap();
+        let values: [&[u8]; 2] = [b"yes", b"yes"];
+        let (sig, salts) = Signature::new_salted(&values, &signkey, &verkey).unwrap();
+        assert_ne!(salts[0], salts[1]);
+        assert!(sig.verify_salted(&values, &salts, &verkey).unwrap());
+        let (sig_2, salts_2) = Signature::new_salted(&values, &signkey, &verkey).unwrap();
+        assert!(sig_2.verify_salted(&values, &salts_2, &verkey).unwrap());
+
+        assert!(!sig
+            .verify_salted(&[b"yes", b"no"], &salts, &verkey)
+            .unwrap());
+        assert!(!sig.verify_salted(&values, &salts_2, &verkey).unwrap());
+        assert!(sig.verify_salted(&values, &salts[..1], &verkey).is_err());
+    }
+
+    #[test]
+    fn json_credential() {
+        let schema = Schema::new(&[("name", true), ("age", true), ("nickname", false)]);
+        let credential = serde_json::json!({
+            "age": 30,
+            "name": "Alice",
+            "extra": "ignored",
+        });
+        let messages = encode_json(&credential, &schema).unwrap();
+        assert_eq!(messages.len(), 3);
+
+        let (verkey, signkey) = generate(3).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+        // Same object written differently
+        let reparsed: serde_json::Value =
+            serde_json::from_str(r#"{ "name": "Alice",   "age": 30 }"#).unwrap();
+        let messages_2 = encode_json(&reparsed, &schema).unwrap();
+        assert!(sig.verify(messages_2.as_slice(), &verkey).unwrap());
+
+        // Types are kept apart, and absent differs from null
+        let as_string = serde_json::json!({ "name": "Alice", "age": "30" });
+        assert_ne!(encode_json(&as_string, &schema).unwrap()[1], messages[1]);
+        let null = serde_json::json!({ "name": "Alice", "age": 30, "nickname": null });
+        assert_ne!(encode_json(&null, &schema).unwrap()[2], messages[2]);
+
+        // NFC and NFD spellings of a nested value, or of a nested key, encode the same
+        let nfc = serde_json::json!({
+            "name": "Alice",
+            "age": 30,
+            "nickname": ["Ren\u{e9}e", { "caf\u{e9}": "na\u{ef}ve" }],
+        });
+        let nfd = serde_json::json!({
+            "name": "Alice",
+            "age": 30,
+            "nickname": ["Rene\u{301}e", { "cafe\u{301}": "nai\u{308}ve" }],
+        });
+        assert_eq!(
+            encode_json(&nfc, &schema).unwrap(),
+            encode_json(&nfd, &schema).unwrap()
+        );
+        let clash = serde_json::json!({
+            "name": "Alice",
+            "age": { "caf\u{e9}": 1, "cafe\u{301}": 2 },
+        });
+        assert!(encode_json(&clash, &schema).is_err());
+
+        let missing = serde_json::json!({ "name": "Alice" });
+        assert!(encode_json(&missing, &schema).is_err());
+        assert!(encode_json(&serde_json::json!([1, 2]), &schema).is_err());
+    }
+
+    #[test]
+    fn encoding_profile_fingerprint() {
+        let profile = EncodingProfile::new("driver-license", 1, &["name", "birthdate"]);
+        let same = EncodingProfile::new("driver-license", 1, &["name", "birthdate"]);
+        assert_eq!(profile.fingerprint(), same.fingerprint());
+
+        let bumped = EncodingProfile::new("driver-license", 2, &["name", "birthdate"]);
+        assert_ne!(profile.fingerprint(), bumped.fingerprint());
+        let reordered = EncodingProfile::new("driver-license", 1, &["birthdate", "name"]);
+        assert_ne!(profile.fingerprint(), reordered.fingerprint());
+        // Length prefixes keep label boundaries apart
+        let merged = EncodingProfile::new("driver-license", 1, &["namebirth", "date"]);
+        assert_ne!(profile.fingerprint(), merged.fingerprint());
+    }
+
+    #[test]
+    fn schema_hash_message() {
+        let profile = EncodingProfile::new("driver-license", 1, &["schema", "name", "birthdate"]);
+        let other = EncodingProfile::new("driver-license", 2, &["schema", "name", "birthdate"]);
+        let messages = vec![
+            profile.fingerprint(),
+            encode_string("Alice"),
+            encode_string("1978-04-01"),
+        ];
+        let (verkey, signkey) = generate(3).unwrap();
+        let sig = Signature::new(&messages, &signkey, &verkey).unwrap();
+
+        assert!(sig
+            .verify_schema(&messages, &verkey, &profile.fingerprint(), 0)
+            .unwrap());
+        assert!(!sig
+            .verify_schema(&messages, &verkey, &other.fingerprint(), 0)
+            .unwrap());
+        // The reserved message is at another index
+        assert!(!sig
+            .verify_schema(&messages, &verkey, &profile.fingerprint(), 1)
+            .unwrap());
+        assert!(sig
+            .verify_schema(&messages, &verkey, &profile.fingerprint(), 3)
+            .is_err());
+    }
+
+    #[test]
+    fn counter_message() {
+        let messages = vec![encode_string("Alice"), encode_string("1978-04-01")];
+        let (verkey, signkey) = generate(3).unwrap();
+        let sig = Signature::new_with_counter(&messages, 7, &signkey, &verkey).unwrap();
+        let mut presented = messages.clone();
+        presented.push(encode_u64(7));
+
+        assert!(sig.verify(&presented, &verkey).unwrap());
+        assert!(sig.verify_counter(&presented, &verkey, 0).unwrap());
+        assert!(sig.verify_counter(&presented, &verkey, 7).unwrap());
+        // Stale
+        assert!(!sig.verify_counter(&presented, &verkey, 8).unwrap());
+        // Claiming a higher counter breaks the signature
+        let mut raised = messages.clone();
+        raised.push(encode_u64(9));
+        assert!(!sig.verify_counter(&raised, &verkey, 8).unwrap());
+        // Last message isn't a counter
+        let mut reordered = presented.clone();
+        reordered.swap(0, 2);
+        assert!(!sig.verify_counter(&reordered, &verkey, 0).unwrap());
+        assert_eq!(to_u64(&encode_u64(u64::MAX)), Some(u64::MAX));
+        assert_eq!(encode_u64(5), FieldElement::from(5u64));
+
+        assert!(Signature::new_with_counter(&presented, 7, &signkey, &verkey).is_err());
+    }
+
+    #[test]
+    fn parse_number_strings() {
+        assert_eq!(parse_decimal("0").unwrap(), FieldElement::zero());
+        assert_eq!(parse_decimal("1978").unwrap(), encode_u64(1978));
+        assert_eq!(parse_decimal("0018").unwrap(), encode_u64(18));
+        assert_eq!(
+            parse_decimal("18446744073709551615").unwrap(),
+            encode_u64(u64::MAX)
+        );
+        assert_eq!(parse_hex("0x7ba").unwrap(), encode_u64(1978));
+        assert_eq!(parse_hex("7BA").unwrap(), encode_u64(1978));
+
+        // Group order - 1 is the largest value, the order itself overflows
+        let order = "52435875175126190479447740508185965837690552500527637822603658699938581184513";
+        let max = "52435875175126190479447740508185965837690552500527637822603658699938581184512";
+        assert_eq!(parse_decimal(max).unwrap(), FieldElement::one().negation());
+        assert!(parse_decimal(order).is_err());
+        assert!(parse_hex(&"f".repeat(100)).is_err());
+        let max_hex = "0x73eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff00000000";
+        assert_eq!(parse_hex(max_hex).unwrap(), FieldElement::one().negation());
+        assert!(
+            parse_hex("0x73eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff00000001")
+                .is_err()
+        );
+
+        for malformed in &["", "-1", "1.5", "12a", " 1", "0x"] {
+            assert!(parse_decimal(malformed).is_err());
+        }
+        for malformed in &["", "0x", "0xg", "0x-1", "0x 1"] {
+            assert!(parse_hex(malformed).is_err());
+        }
+    }
+
+    #[test]
+    fn hash_bytes_to_messages() {
+        let dst = b"example credential v1";
+        let mut long_1 = vec![7u8; MODBYTES];
+        let mut long_2 = long_1.clone();
+        long_1.extend_from_slice(b"first");
+        long_2.extend_from_slice(b"second");
+        let inputs: Vec<&[u8]> = vec![&long_1, &long_2, b"", b"42"];
+        let messages = messages_from_bytes(&inputs, dst);
+        assert_eq!(messages.len(), inputs.len());
+        // Inputs sharing their first MODBYTES bytes don't collide
+        assert_ne!(messages[0], messages[1]);
+        assert_eq!(messages, messages_from_bytes(&inputs, dst));
+        assert_ne!(messages, messages_from_bytes(&inputs, b"another dst"));
+        assert!(messages_from_bytes(&[], dst).is_empty());
+
+        let (verkey, signkey) = generate(inputs.len()).unwrap();
+        let sig = Signature::new(&messages, &signkey, &verkey).unwrap();
+        assert!(sig.verify(&messages, &verkey).unwrap());
+    }
+
+    #[test]
+    fn byte_order_of_signer() {
+        let values: Vec<&[u8]> = vec![&[0, 0, 1, 2], &[7, 8], &[9]];
+        let dst = b"byte order test";
+        let (verkey, signkey) = generate(values.len()).unwrap();
+        assert_eq!(ByteOrder::default(), ByteOrder::BigEndian);
+        assert_eq!(
+            ByteOrder::LittleEndian.apply(&[1, 2, 3, 4]).unwrap(),
+            vec![4, 3, 2, 1]
+        );
+        // Only integer widths have a byte order, any string passes unchanged as big endian
+        for len in &[0, 3, 5, 32, MODBYTES + 1] {
+            let value = vec![1u8; *len];
+            assert!(ByteOrder::LittleEndian.apply(&value).is_err());
+            assert_eq!(ByteOrder::BigEndian.apply(&value).unwrap(), value);
+        }
+        assert!(ByteOrder::LittleEndian.apply(&[1u8; MODBYTES]).is_ok());
+        let text: Vec<&[u8]> = vec![b"not an integer"];
+        assert!(messages_from_bytes_with_order(&text, dst, ByteOrder::LittleEndian).is_err());
+        assert_eq!(
+            messages_from_bytes_with_order(&text, dst, ByteOrder::BigEndian).unwrap(),
+            messages_from_bytes(&text, dst)
+        );
+
+        for order in &[ByteOrder::BigEndian, ByteOrder::LittleEndian] {
+            // The signer serialized the values in its own order
+            let serialized: Vec<Vec<u8>> = values.iter().map(|v| order.apply(v).unwrap()).collect();
+            let signed: Vec<&[u8]> = serialized.iter().map(|v| v.as_slice()).collect();
+            let messages = messages_from_bytes(&signed, dst);
+            let sig = Signature::new(&messages, &signkey, &verkey).unwrap();
+            let matched = messages_from_bytes_with_order(&values, dst, *order).unwrap();
+            assert_eq!(matched, messages);
+            assert!(sig.verify(&matched, &verkey).unwrap());
+
+            let legacy: Vec<SignatureMessage> = signed
+                .iter()
+                .map(|v| ReductionMode::Legacy.reduce(v).unwrap())
+                .collect();
+            let legacy_sig = Signature::new(&legacy, &signkey, &verkey).unwrap();
+            assert!(legacy_sig
+                .verify_with_byte_order(&values, ReductionMode::Legacy, *order, &verkey)
+                .unwrap());
+        }
+
+        // Mismatched order doesn't verify
+        let messages =
+            messages_from_bytes_with_order(&values, dst, ByteOrder::LittleEndian).unwrap();
+        assert_ne!(messages, messages_from_bytes(&values, dst));
+        let raw: Vec<SignatureMessage> = values
+            .iter()
+            .map(|v| {
+                ReductionMode::Hash
+                    .reduce(&ByteOrder::LittleEndian.apply(v).unwrap())
+                    .unwrap()
+            })
+            .collect();
+        let sig = Signature::new(&raw, &signkey, &verkey).unwrap();
+        assert!(sig
+            .verify_with_byte_order(
+                &values,
+                ReductionMode::Hash,
+                ByteOrder::LittleEndian,
+                &verkey
+            )
+            .unwrap());
+        assert!(!sig
+            .verify_with_reduction(&values, ReductionMode::Hash, &verkey)
+            .unwrap());
+    }
+
+    #[derive(BbsMessages)]
+    struct Person {
+        name: String,
+        age: u64,
+        over_18: bool,
+        id: SignatureMessage,
+    }
+
+    #[derive(BbsMessages)]
+    struct Pair<'a>(&'a str, u32);
+
+    #[test]
+    fn derived_messages() {
+        let person = Person {
+            name: "Alice".to_string(),
+            age: 30,
+            over_18: true,
+            id: FieldElement::random(),
+        };
+        let messages = person.to_messages();
+        assert_eq!(Person::message_count(), 4);
+        assert_eq!(
+            messages,
+            vec![
+                encode_string("Alice"),
+                encode_u64(30),
+                FieldElement::one(),
+                person.id.clone()
+            ]
+        );
+
+        let (verkey, signkey) = generate(Person::message_count()).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+        assert!(sig
+            .verify(person.to_messages().as_slice(), &verkey)
+            .unwrap());
+        let older = Person { age: 31, ..person };
+        assert!(!sig.verify(older.to_messages().as_slice(), &verkey).unwrap());
+
+        let pair = Pair("x", 7);
+        assert_eq!(Pair::message_count(), 2);
+        assert_eq!(pair.to_messages(), vec![encode_string("x"), encode_u64(7)]);
+    }
+
+    #[test]
+    fn aggregate_signature() {
+        let messages = FieldElementVector::random(6);
+        let (verkey, signkey) = generate(1).unwrap();
+        let sig = Signature::new_aggregate(messages.as_slice(), &signkey, &verkey).unwrap();
+        assert!(sig.verify_aggregate(messages.as_slice(), &verkey).unwrap());
+        assert!(sig
+            .verify(&[aggregate_messages(messages.as_slice())], &verkey)
+            .unwrap());
+
+        let mut changed = messages.clone();
+        changed[4] = FieldElement::random();
+        assert!(!sig.verify_aggregate(changed.as_slice(), &verkey).unwrap());
+        assert!(!sig
+            .verify_aggregate(&messages.as_slice()[..5], &verkey)
+            .unwrap());
+        let mut swapped = messages.as_slice().to_vec();
+        swapped.swap(0, 1);
+        assert!(!sig.verify_aggregate(&swapped, &verkey).unwrap());
+
+        // The key is for the one digest
+        let (verkey_6, signkey_6) = generate(6).unwrap();
+        assert!(Signature::new_aggregate(messages.as_slice(), &signkey_6, &verkey_6).is_err());
+    }
+}
@@ -0,0 +1,89 @@
+// Operation counters for profiling, compiled only with the `metrics` feature (and in tests) so
+// builds without it pay nothing.
+
+use std::cell::Cell;
+
+/// Group operations counted on the current thread since the last `reset`.
+/// A multi-scalar multiplication over `n` points counts as `n` scalar multiplications and
+/// `n - 1` additions, a multi-pairing over `n` pairs as `n` pairings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OpCounts {
+    pub scalar_muls: usize,
+    pub additions: usize,
+    pub pairings: usize,
+}
+
+thread_local! {
+    static COUNTS: Cell<OpCounts> = const {
+        Cell::new(OpCounts {
+            scalar_muls: 0,
+            additions: 0,
+            pairings: 0,
+        })
+    };
+}
+
+pub fn reset() {
+    COUNTS.with(|c| c.set(OpCounts::default()));
+}
+
+pub fn get() -> OpCounts {
+    COUNTS.with(|c| c.get())
+}
+
+pub(crate) fn record(scalar_muls: usize, additions: usize, pairings: usize) {
+    COUNTS.with(|c| {
+        let mut counts = c.get();
+        counts.scalar_muls += scalar_muls;
+        counts.additions += additions;
+        counts.pairings += pairings;
+        c.set(counts);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amcl_wrapper::field_elem::FieldElementVector;
+    use signatures::bbs::keys::{generate, Params};
+    use signatures::bbs::messages::PreparedMessages;
+    use signatures::bbs::signature::{cost_model, Signature};
+
+    #[test]
+    fn verification_op_counts() {
+        let message_count = 5;
+        let messages = FieldElementVector::random(message_count);
+        let (verkey, signkey) = generate(message_count).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+
+        reset();
+        assert!(sig.verify(messages.as_slice(), &verkey).unwrap());
+        // g1, h0 and one base per message for `b` plus `g2 * e + w`, then a 2-pairing, as
+        // `cost_model` says
+        let model = cost_model(message_count);
+        let expected = OpCounts {
+            scalar_muls: model.scalar_muls,
+            additions: model.additions,
+            pairings: model.pairings,
+        };
+        assert_eq!(get(), expected);
+
+        // Same work without the starting point of `compute_b`
+        let prepared = PreparedMessages::new(messages.as_slice());
+        reset();
+        assert!(sig.verify_prepared_messages(&prepared, &verkey).unwrap());
+        assert_eq!(get().scalar_muls, expected.scalar_muls);
+        assert_eq!(get().additions, expected.additions - 1);
+        assert_eq!(get().pairings, 2);
+
+        // Signing under custom params costs what signing under the default ones does
+        reset();
+        Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+        let signing = get();
+        assert_ne!(signing, OpCounts::default());
+        reset();
+        Signature::new_with_params(messages.as_slice(), &signkey, &verkey, &Params::default())
+            .unwrap();
+        assert_eq!(get(), signing);
+    }
+}
@@ -1,11 +1,78 @@
+pub mod allowlist;
+pub mod blind_signature;
+pub mod compat;
+pub mod delegated;
+pub mod did;
+pub mod interactive;
+pub mod issuance;
 pub mod keys;
+pub mod known_good;
+pub mod memo;
+pub mod merkle;
+pub mod messages;
+#[cfg(any(test, feature = "metrics"))]
+pub mod metrics;
+pub mod pairing;
 pub mod pok_sig;
+pub mod predicate;
+pub mod prefix_proof;
+pub mod presentation;
+pub mod revocation;
+pub mod same_key;
 pub mod signature;
+pub mod subcredential;
+pub mod transcript;
+pub mod vector_commitment;
 
 pub mod prelude {
-    pub use super::keys::{generate, PublicKey, SecretKey};
+    pub use super::allowlist::{Accumulator, AllowlistManager, AllowlistWitness};
+    pub use super::blind_signature::{
+        merge_commitments, BlindSignatureContext, MultiBlindCommitment,
+    };
+    pub use super::compat::{upgrade_public_key_bytes, upgrade_signature_bytes, FORMAT_VERSION};
+    pub use super::delegated::{commit_for_delegation, ProofBytes};
+    pub use super::did::VERIFICATION_METHOD_TYPE;
+    pub use super::interactive::{
+        InteractiveProver, InteractiveVerifier, PoKOfSignatureCommitment,
+    };
+    pub use super::issuance::{IssuanceRecord, ISSUANCE_RECORD_SIZE};
+    pub use super::keys::{
+        generate, generate_deterministic, generate_from_seed, generate_with_params, Params,
+        PreparedSecretKey, PublicKey, SecretKey, ToPublicKey, MIN_SEED_SIZE,
+    };
+    pub use super::known_good::KnownGoodFilter;
+    pub use super::memo::MemoVerifier;
+    pub use super::merkle::{
+        prove_leaf, prove_set_member, LeafProof, MerklePath, MerkleTree, SetMemberProof,
+    };
+    pub use super::messages::{
+        aggregate_messages, derive_blinding, encode_json, encode_message_salted, encode_split,
+        encode_string, encode_u64, messages_from_bytes, messages_from_bytes_with_order,
+        parse_decimal, parse_hex, BbsMessages, ByteOrder, EncodingProfile, MessageSalt,
+        PreparedMessages, ReductionMode, Schema, SchemaField, ToMessage, ToMessages,
+    };
+    pub use super::pairing::PairingAccumulator;
     pub use super::pok_sig::{
         PoKOfSignature, PoKOfSignatureProof, ProofG1, ProverCommittedG1, ProverCommittingG1,
     };
-    pub use super::signature::Signature;
+    pub use super::predicate::{PoKOfPredicates, Predicate, PredicateProof};
+    pub use super::prefix_proof::PrefixProof;
+    pub use super::presentation::{
+        combine_nonces, sign_and_present, verify_presentation, verify_presentation_multi,
+        CompactProof, Presentation, PresentationBatch, PresentationProof, ProofNonce,
+    };
+    pub use super::revocation::{
+        prove_surrendered, RegistryDelta, RevocationRegistry, SurrenderProof,
+    };
+    pub use super::same_key::{prove_same_key, SameKeyProof};
+    pub use super::signature::{
+        cost_model, BlindingScheme, MessageGroup, OptLevel, PreparedVerifier, Signature,
+        ThinSignature, VerifyComponents, VerifyCost, VerifyFailure, VerifyScratch, LAZY_CHUNK_SIZE,
+        SIGNATURE_COMPRESSED_SIZE, SIGNATURE_SIZE,
+    };
+    pub use super::subcredential::{
+        commit_subcredential, subcredential_message, SubCredentialOpening,
+    };
+    pub use super::transcript::Transcript;
+    pub use super::vector_commitment::{prove_position, PositionProof, VectorOpening};
 }
@@ -0,0 +1,64 @@
+use amcl_wrapper::extension_field_gt::GT;
+use amcl_wrapper::field_elem::FieldElement;
+use amcl_wrapper::group_elem::GroupElement;
+use amcl_wrapper::group_elem_g1::G1;
+use amcl_wrapper::group_elem_g2::G2;
+
+pub mod prelude {
+    pub use super::PairingAccumulator;
+}
+
+/// Collects pairing equations of the form `e(p_1, q_1) * e(p_2, q_2) * ... == 1` from several
+/// statements and checks all of them with a single multi-pairing.
+/// Each equation is scaled by a fresh random exponent when added so that a failing equation
+/// cannot be cancelled out by another one.
+/// Terms paired with the `G2` generator are summed in `G1` first so they cost one pairing total.
+#[derive(Debug, Clone)]
+pub struct PairingAccumulator {
+    pairs: Vec<(G1, G2)>,
+    g2_term: G1,
+}
+
+impl PairingAccumulator {
+    pub fn new() -> Self {
+        PairingAccumulator {
+            pairs: Vec::new(),
+            g2_term: G1::identity(),
+        }
+    }
+
+    /// Add the equation `e(p, q) * e(g2_term, G2::generator()) == 1`
+    pub fn add(&mut self, p: &G1, q: &G2, g2_term: &G1) {
+        let r = FieldElement::random();
+        self.pairs.push((p * &r, q.clone()));
+        self.g2_term += g2_term * &r;
+    }
+
+    /// Number of equations added
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    /// True only if every added equation holds. An empty accumulator checks trivially.
+    pub fn check(&self) -> bool {
+        if self.pairs.is_empty() {
+            return true;
+        }
+        #[cfg(any(test, feature = "metrics"))]
+        super::metrics::record(0, 0, self.pairs.len() + 1);
+        let g2 = G2::generator();
+        let mut elems: Vec<(&G1, &G2)> = self.pairs.iter().map(|(p, q)| (p, q)).collect();
+        elems.push((&self.g2_term, &g2));
+        GT::ate_multi_pairing(elems).is_one()
+    }
+}
+
+impl Default for PairingAccumulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,9 +1,11 @@
 use super::keys::PublicKey;
+use super::pairing::PairingAccumulator;
 use super::signature::{compute_b_const_time, Signature};
 use crate::commitments::pok_vc::{PoKVCError, PoKVCErrorKind};
 use crate::errors::prelude::*;
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use subtle::{Choice, ConstantTimeEq};
 
 use amcl_wrapper::extension_field_gt::GT;
 use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
@@ -48,9 +50,10 @@ impl PoKOfSignature {
         revealed_msg_indices: HashSet<usize>,
     ) -> Result<Self, BBSError> {
         if messages.len() != vk.message_count() {
-            return Err(BBSError::from_kind(
-                BBSErrorKind::SigningErrorMessageCountMismatch(vk.message_count(), messages.len()),
-            ));
+            return Err(BBSError::from_kind(BBSErrorKind::message_count(
+                vk.message_count(),
+                messages.len(),
+            )));
         }
         for idx in &revealed_msg_indices {
             if *idx >= messages.len() {
@@ -137,6 +140,23 @@ impl PoKOfSignature {
         })
     }
 
+    /// Same as `init` for the common case of revealing the messages at `revealed_indices` and
+    /// hiding the others with random blindings. Verify with `PoKOfSignatureProof::verify_revealed`.
+    pub fn init_revealing(
+        signature: &Signature,
+        vk: &PublicKey,
+        messages: &[FieldElement],
+        revealed_indices: &BTreeSet<usize>,
+    ) -> Result<Self, BBSError> {
+        Self::init(
+            signature,
+            vk,
+            messages,
+            None,
+            revealed_indices.iter().cloned().collect(),
+        )
+    }
+
     /// Return byte representation of public elements so they can be used for challenge computation.
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut bytes = vec![];
@@ -169,6 +189,35 @@ impl PoKOfSignature {
             proof_vc_2,
         })
     }
+
+    /// Same as `gen_proof` for a `challenge` that comes from an outer protocol this proof is
+    /// part of, hashed over `to_bytes` and whatever else that protocol commits to. `hidden_msgs`
+    /// are the witnesses the outer protocol expects, in index order, and must be the hidden
+    /// messages given to `init`. Otherwise the outer protocol would be combining responses for
+    /// other values and its checks across proofs fail, so this errors instead.
+    pub fn gen_proof_with_challenge(
+        self,
+        challenge: &FieldElement,
+        hidden_msgs: &[FieldElement],
+    ) -> Result<PoKOfSignatureProof, BBSError> {
+        // The first 2 secrets are r3 and s_prime, the hidden messages follow
+        let committed = &self.secrets_2.as_slice()[2..];
+        let same = committed.len() == hidden_msgs.len()
+            && bool::from(
+                committed
+                    .iter()
+                    .zip(hidden_msgs)
+                    .fold(Choice::from(1), |acc, (c, m)| {
+                        acc & c.to_bytes().ct_eq(&m.to_bytes())
+                    }),
+            );
+        if !same {
+            return Err(BBSError::from_kind(BBSErrorKind::GeneralError {
+                msg: "Hidden messages differ from the ones the proof commits to".to_string(),
+            }));
+        }
+        self.gen_proof(challenge)
+    }
 }
 
 impl PoKOfSignatureProof {
@@ -216,20 +265,34 @@ impl PoKOfSignatureProof {
         Ok(self.proof_vc_2.responses[2 + msg_idx].clone())
     }
 
+    /// `get_bytes_for_challenge` for the indices of `revealed_msgs`
+    pub fn get_bytes_for_challenge_revealed(
+        &self,
+        revealed_msgs: &BTreeMap<usize, FieldElement>,
+        vk: &PublicKey,
+    ) -> Vec<u8> {
+        self.get_bytes_for_challenge(revealed_msgs.keys().cloned().collect(), vk)
+    }
+
+    /// `verify` for a proof from `PoKOfSignature::init_revealing`. The verifier's own values
+    /// of the revealed messages go into the check, if the holder's differ it fails.
+    pub fn verify_revealed(
+        &self,
+        revealed_msgs: &BTreeMap<usize, FieldElement>,
+        vk: &PublicKey,
+        challenge: &FieldElement,
+    ) -> Result<bool, BBSError> {
+        let revealed = revealed_msgs.iter().map(|(i, m)| (*i, m.clone())).collect();
+        self.verify(vk, revealed, challenge)
+    }
+
     pub fn verify(
         &self,
         vk: &PublicKey,
         revealed_msgs: HashMap<usize, FieldElement>,
         challenge: &FieldElement,
     ) -> Result<bool, BBSError> {
-        vk.validate()?;
-        for i in revealed_msgs.keys() {
-            if *i >= vk.message_count() {
-                return Err(BBSError::from_kind(BBSErrorKind::GeneralError {
-                    msg: format!("Index {} should be less than {}", i, vk.message_count()),
-                }));
-            }
-        }
+        check_revealed(vk, &revealed_msgs)?;
 
         if self.a_prime.is_identity() {
             return Ok(false);
@@ -239,6 +302,34 @@ impl PoKOfSignatureProof {
             return Ok(false);
         }
 
+        self.verify_responses(vk, &revealed_msgs, challenge)
+    }
+
+    /// `verify` with the pairing check added to `acc` instead of computed right away. Returns
+    /// false if anything but the pairing fails, and true says nothing until `acc.check()`.
+    pub fn verify_accumulate(
+        &self,
+        vk: &PublicKey,
+        revealed_msgs: HashMap<usize, FieldElement>,
+        challenge: &FieldElement,
+        acc: &mut PairingAccumulator,
+    ) -> Result<bool, BBSError> {
+        check_revealed(vk, &revealed_msgs)?;
+
+        if self.a_prime.is_identity() || !self.verify_responses(vk, &revealed_msgs, challenge)? {
+            return Ok(false);
+        }
+        acc.add(&self.a_prime, &vk.w, &(-&self.a_bar));
+        Ok(true)
+    }
+
+    // The two Schnorr proofs, everything of `verify` but the pairing
+    fn verify_responses(
+        &self,
+        vk: &PublicKey,
+        revealed_msgs: &HashMap<usize, FieldElement>,
+        challenge: &FieldElement,
+    ) -> Result<bool, BBSError> {
         let mut bases = vec![];
         bases.push(self.a_prime.clone());
         bases.push(vk.h0.clone());
@@ -282,11 +373,56 @@ impl PoKOfSignatureProof {
     }
 }
 
+fn check_revealed(
+    vk: &PublicKey,
+    revealed_msgs: &HashMap<usize, FieldElement>,
+) -> Result<(), BBSError> {
+    vk.validate()?;
+    for i in revealed_msgs.keys() {
+        if *i >= vk.message_count() {
+            return Err(BBSError::from_kind(BBSErrorKind::GeneralError {
+                msg: format!("Index {} should be less than {}", i, vk.message_count()),
+            }));
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use signatures::bbs::keys::generate;
 
+    #[test]
+    fn pok_signature_message_count() {
+        let (verkey, signkey) = generate(3).unwrap();
+        let messages = FieldElementVector::random(3);
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+        let too_few = PoKOfSignature::init(
+            &sig,
+            &verkey,
+            &messages.as_slice()[1..],
+            None,
+            HashSet::new(),
+        );
+        match too_few.unwrap_err().kind() {
+            BBSErrorKind::TooFewMessages {
+                expected: 3,
+                got: 2,
+            } => {}
+            _ => panic!("Expected TooFewMessages"),
+        }
+        let more = FieldElementVector::random(4);
+        let too_many = PoKOfSignature::init(&sig, &verkey, more.as_slice(), None, HashSet::new());
+        match too_many.unwrap_err().kind() {
+            BBSErrorKind::TooManyMessages {
+                expected: 3,
+                got: 4,
+            } => {}
+            _ => panic!("Expected TooManyMessages"),
+        }
+    }
+
     #[test]
     fn pok_signature_no_revealed_messages() {
         let message_count = 5;
@@ -453,4 +589,108 @@ mod tests {
         assert!(proof_1.verify(&vk, HashMap::new(), &chal_verifier).unwrap());
         assert!(proof_2.verify(&vk, HashMap::new(), &chal_verifier).unwrap());
     }
+
+    #[test]
+    fn pok_signature_external_challenge() {
+        let message_count = 3;
+        let messages = FieldElementVector::random(message_count);
+        let (verkey, signkey) = generate(message_count).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+
+        let mut revealed_indices = HashSet::new();
+        revealed_indices.insert(0);
+        // Outer protocol: knowledge of m_1 in y = g^m_1, sharing the blinding with the BBS proof
+        let blinding = FieldElement::random();
+        let pok = PoKOfSignature::init(
+            &sig,
+            &verkey,
+            messages.as_slice(),
+            Some(&[blinding.clone(), FieldElement::random()]),
+            revealed_indices.clone(),
+        )
+        .unwrap();
+        let y = G1::generator() * &messages[1];
+        let mut outer = ProverCommittingG1::new();
+        outer.commit(&G1::generator(), Some(&blinding));
+        let outer = outer.finish();
+
+        let mut transcript = b"outer protocol".to_vec();
+        transcript.append(&mut pok.to_bytes());
+        transcript.append(&mut outer.to_bytes());
+        transcript.append(&mut y.to_bytes());
+        let challenge = FieldElement::from_msg_hash(&transcript);
+
+        let hidden = vec![messages[1].clone(), messages[2].clone()];
+        let proof = pok
+            .clone()
+            .gen_proof_with_challenge(&challenge, &hidden)
+            .unwrap();
+        let outer_proof = outer.gen_proof(&challenge, &[messages[1].clone()]).unwrap();
+
+        let mut revealed_msgs = HashMap::new();
+        revealed_msgs.insert(0, messages[0].clone());
+        assert!(proof.verify(&verkey, revealed_msgs, &challenge).unwrap());
+        assert!(outer_proof
+            .verify(&[G1::generator()], &y, &challenge)
+            .unwrap());
+        // Both proofs are about the same m_1
+        assert_eq!(
+            proof.get_resp_for_message(0).unwrap(),
+            outer_proof.responses[0]
+        );
+
+        let wrong = vec![messages[2].clone(), messages[1].clone()];
+        assert!(pok
+            .clone()
+            .gen_proof_with_challenge(&challenge, &wrong)
+            .is_err());
+        assert!(pok
+            .gen_proof_with_challenge(&challenge, &hidden[..1])
+            .is_err());
+    }
+
+    #[test]
+    fn pok_signature_revealing() {
+        let message_count = 5;
+        let messages = FieldElementVector::random(message_count);
+        let (verkey, signkey) = generate(message_count).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+
+        let revealed_indices: BTreeSet<usize> = vec![0, 2].into_iter().collect();
+        let pok =
+            PoKOfSignature::init_revealing(&sig, &verkey, messages.as_slice(), &revealed_indices)
+                .unwrap();
+        let challenge = FieldElement::from_msg_hash(&pok.to_bytes());
+        let proof = pok.gen_proof(&challenge).unwrap();
+        // Responses only for the 3 hidden messages
+        assert_eq!(proof.proof_vc_2.responses.len(), 2 + 3);
+
+        let mut revealed_msgs = BTreeMap::new();
+        revealed_msgs.insert(0, messages[0].clone());
+        revealed_msgs.insert(2, messages[2].clone());
+        let challenge_verifier = FieldElement::from_msg_hash(
+            &proof.get_bytes_for_challenge_revealed(&revealed_msgs, &verkey),
+        );
+        assert_eq!(challenge, challenge_verifier);
+        assert!(proof
+            .verify_revealed(&revealed_msgs, &verkey, &challenge_verifier)
+            .unwrap());
+
+        let mut wrong = revealed_msgs.clone();
+        wrong.insert(2, FieldElement::random());
+        assert!(!proof
+            .verify_revealed(&wrong, &verkey, &challenge_verifier)
+            .unwrap());
+
+        let out_of_range: BTreeSet<usize> = vec![0, message_count].into_iter().collect();
+        assert!(
+            PoKOfSignature::init_revealing(&sig, &verkey, messages.as_slice(), &out_of_range)
+                .is_err()
+        );
+        let mut out_of_range = revealed_msgs;
+        out_of_range.insert(message_count, FieldElement::random());
+        assert!(proof
+            .verify_revealed(&out_of_range, &verkey, &challenge_verifier)
+            .is_err());
+    }
 }
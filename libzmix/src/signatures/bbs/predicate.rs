@@ -0,0 +1,599 @@
+// Proving a conjunction of predicates over committed attributes, e.g.
+// "18 <= age <= 65 AND country in {..}", without revealing the attributes.
+//
+// Every attribute `m` is put in a Pedersen commitment `C = g^m * h^r` where `g` and `h` are
+// hashed to the curve. Each conjunct consists of
+// - a proof of knowledge of the opening `(m, r)` of `C`. Its response for `m` is what links the
+//   attribute to a hidden message of a `PoKOfSignature` created with the same blinding for that
+//   message under the same challenge, see `PredicateProof::get_resp_for_attribute`.
+// - for `Range { min, max }`: with `k` bits such that `max - min < 2^k`, commitments
+//   `C_j = g^b_j * h^r_j` to the bits of `x = m - min`, with `prod C_j^(2^j) == C / g^min`,
+//   the same for `y = max - m` against `g^max / C`, and for each bit a proof that it commits to
+//   0 or 1. Then `x, y` are in `[0, 2^k)` and `x + y = max - min`, which is only possible for
+//   `min <= m <= max` since `2^65` is far below the group order.
+// - for `Member(set)`: a proof that one of `C / g^s` for `s` in `set` is a power of `h`.
+// "One of" statements are 1-of-n Schnorr proofs (Cramer-Damgard-Schoenmakers): the prover
+// simulates every branch it has no witness for and the branch challenges must add up to the
+// challenge.
+//
+// All conjuncts answer one challenge computed over all of their commitments (and whatever else
+// the caller hashes in, like a `PoKOfSignature` and a nonce). That makes the conjunction a single
+// sigma protocol: two accepting transcripts with different challenges give a witness for every
+// conjunct at once, so a proof can't be assembled from conjuncts that were each proven on their
+// own. The predicates themselves are hashed into the challenge too.
+// Range predicates only make sense for attributes encoded with `encode_u64`.
+
+use super::super::SignatureMessage;
+use super::messages::{encode_u64, to_u64};
+use super::pok_sig::{ProofG1, ProverCommittedG1, ProverCommittingG1};
+use crate::errors::prelude::*;
+
+use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
+use amcl_wrapper::group_elem::GroupElement;
+use amcl_wrapper::group_elem_g1::G1;
+
+pub mod prelude {
+    pub use super::{PoKOfPredicates, Predicate, PredicateProof};
+}
+
+const VALUE_BASE: &[u8] = b"BBS predicate value";
+const BLINDING_BASE: &[u8] = b"BBS predicate blinding";
+
+fn value_base() -> G1 {
+    G1::from_msg_hash(VALUE_BASE)
+}
+
+fn blinding_base() -> G1 {
+    G1::from_msg_hash(BLINDING_BASE)
+}
+
+/// A statement about one committed attribute
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum Predicate {
+    /// `min <= m <= max`
+    Range { min: u64, max: u64 },
+    /// `m` is one of the values
+    Member(Vec<SignatureMessage>),
+}
+
+impl Predicate {
+    fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Predicate::Range { min, max } => {
+                let mut bytes = vec![0];
+                bytes.extend_from_slice(&min.to_be_bytes());
+                bytes.extend_from_slice(&max.to_be_bytes());
+                bytes
+            }
+            Predicate::Member(set) => {
+                let mut bytes = vec![1];
+                bytes.extend_from_slice(&(set.len() as u32).to_be_bytes());
+                for s in set {
+                    bytes.append(&mut s.to_bytes());
+                }
+                bytes
+            }
+        }
+    }
+}
+
+// Number of bits `k` such that `d < 2^k`
+fn bit_length(d: u64) -> usize {
+    std::cmp::max(1, 64 - d.leading_zeros() as usize)
+}
+
+fn power_of_two(j: usize) -> FieldElement {
+    encode_u64(1u64 << j)
+}
+
+// Bit commitments for `value` whose randomness adds up to `blinding` when weighted by 2^j
+fn commit_bits(
+    value: u64,
+    blinding: &FieldElement,
+    k: usize,
+) -> (Vec<G1>, Vec<(usize, FieldElement)>) {
+    let g = value_base();
+    let h = blinding_base();
+    let mut bits = Vec::with_capacity(k);
+    let mut openings = Vec::with_capacity(k);
+    let mut rest = blinding.clone();
+    for j in 0..k {
+        let r = if j == k - 1 {
+            &rest * &power_of_two(j).inverse()
+        } else {
+            let r = FieldElement::random();
+            rest -= &(&r * &power_of_two(j));
+            r
+        };
+        let b = ((value >> j) & 1) as usize;
+        bits.push(g.binary_scalar_mul(&h, &FieldElement::from(b as u64), &r));
+        openings.push((b, r));
+    }
+    (bits, openings)
+}
+
+// The two points of which one is a power of `h` for a bit commitment
+fn bit_points(bit: &G1) -> Vec<G1> {
+    vec![bit.clone(), bit - &value_base()]
+}
+
+fn weighted_sum(bits: &[G1]) -> G1 {
+    let mut sum = G1::identity();
+    for (j, b) in bits.iter().enumerate() {
+        sum += b * &power_of_two(j);
+    }
+    sum
+}
+
+// Prover state of a 1-of-n proof that one of the points is `h^witness`
+#[derive(Debug, Clone)]
+struct OrCommitted {
+    known: usize,
+    witness: FieldElement,
+    blinding: FieldElement,
+    commitments: Vec<G1>,
+    challenges: Vec<FieldElement>,
+    responses: Vec<FieldElement>,
+}
+
+impl OrCommitted {
+    fn new(points: &[G1], known: usize, witness: FieldElement) -> Self {
+        let h = blinding_base();
+        let blinding = FieldElement::random();
+        let mut commitments = Vec::with_capacity(points.len());
+        let mut challenges = Vec::with_capacity(points.len());
+        let mut responses = Vec::with_capacity(points.len());
+        for (i, p) in points.iter().enumerate() {
+            // Every branch is computed the same way so which one is known doesn't show in timing
+            let (c, z) = if i == known {
+                (FieldElement::zero(), blinding.clone())
+            } else {
+                (FieldElement::random(), FieldElement::random())
+            };
+            commitments.push(h.binary_scalar_mul(p, &z, &c));
+            challenges.push(c);
+            responses.push(z);
+        }
+        OrCommitted {
+            known,
+            witness,
+            blinding,
+            commitments,
+            challenges,
+            responses,
+        }
+    }
+
+    fn gen_proof(mut self, challenge: &FieldElement) -> OrProof {
+        let mut c = challenge.clone();
+        for (i, c_i) in self.challenges.iter().enumerate() {
+            if i != self.known {
+                c -= c_i;
+            }
+        }
+        self.responses[self.known] = &self.blinding - &(&c * &self.witness);
+        self.challenges[self.known] = c;
+        OrProof {
+            commitments: self.commitments,
+            challenges: self.challenges,
+            responses: self.responses,
+        }
+    }
+}
+
+/// Proof that one of several points is a power of the blinding base
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrProof {
+    pub commitments: Vec<G1>,
+    pub challenges: Vec<FieldElement>,
+    pub responses: Vec<FieldElement>,
+}
+
+impl OrProof {
+    fn verify(&self, points: &[G1], challenge: &FieldElement) -> bool {
+        if self.commitments.len() != points.len()
+            || self.challenges.len() != points.len()
+            || self.responses.len() != points.len()
+        {
+            return false;
+        }
+        let mut sum = FieldElement::zero();
+        for c in &self.challenges {
+            sum += c;
+        }
+        if sum != *challenge {
+            return false;
+        }
+        let h = blinding_base();
+        points.iter().enumerate().all(|(i, p)| {
+            h.binary_scalar_mul(p, &self.responses[i], &self.challenges[i]) == self.commitments[i]
+        })
+    }
+}
+
+fn conjunct_bytes<'a, I>(
+    predicate: &Predicate,
+    commitment: &G1,
+    opening_commitment: &G1,
+    bits: &[G1],
+    or_commitments: I,
+) -> Vec<u8>
+where
+    I: Iterator<Item = &'a Vec<G1>>,
+{
+    let mut bytes = predicate.to_bytes();
+    bytes.append(&mut commitment.to_bytes());
+    bytes.append(&mut opening_commitment.to_bytes());
+    for b in bits {
+        bytes.append(&mut b.to_bytes());
+    }
+    for commitments in or_commitments {
+        for t in commitments {
+            bytes.append(&mut t.to_bytes());
+        }
+    }
+    bytes
+}
+
+#[derive(Debug, Clone)]
+struct ConjunctCommitted {
+    predicate: Predicate,
+    commitment: G1,
+    opening: ProverCommittedG1,
+    secrets: FieldElementVector,
+    bits: Vec<G1>,
+    ors: Vec<OrCommitted>,
+}
+
+/// Prover state of a `PredicateProof` between committing and receiving the challenge
+#[derive(Debug, Clone)]
+pub struct PoKOfPredicates {
+    conjuncts: Vec<ConjunctCommitted>,
+}
+
+impl PoKOfPredicates {
+    /// Commit to each attribute and prepare the proof that it satisfies its predicate.
+    /// `blindings[i]` is used for the attribute of `statements[i]` in the opening proof, pass
+    /// the blindings given to `PoKOfSignature::init` for the same messages to link them.
+    /// Fails if an attribute doesn't satisfy its predicate.
+    pub fn init(
+        statements: &[(Predicate, SignatureMessage)],
+        blindings: Option<&[FieldElement]>,
+    ) -> Result<Self, BBSError> {
+        if statements.is_empty() {
+            return Err(BBSError::from_kind(BBSErrorKind::GeneralError {
+                msg: "At least one predicate is needed".to_string(),
+            }));
+        }
+        if let Some(b) = blindings {
+            if b.len() != statements.len() {
+                return Err(BBSError::from_kind(BBSErrorKind::GeneralError {
+                    msg: format!("Blindings {} != Predicates {}", b.len(), statements.len()),
+                }));
+            }
+        }
+        let unsatisfied = |i: usize| {
+            BBSError::from_kind(BBSErrorKind::GeneralError {
+                msg: format!("Attribute {} doesn't satisfy its predicate", i),
+            })
+        };
+        let g = value_base();
+        let h = blinding_base();
+
+        let mut conjuncts = Vec::with_capacity(statements.len());
+        for (i, (predicate, attribute)) in statements.iter().enumerate() {
+            let r = FieldElement::random();
+            let commitment = g.binary_scalar_mul(&h, attribute, &r);
+            let mut committing = ProverCommittingG1::new();
+            committing.commit(&g, blindings.map(|b| &b[i]));
+            committing.commit(&h, None);
+            let opening = committing.finish();
+            let mut secrets = FieldElementVector::with_capacity(2);
+            secrets.push(attribute.clone());
+            secrets.push(r.clone());
+
+            let (bits, ors) = match predicate {
+                Predicate::Range { min, max } => {
+                    if min > max {
+                        return Err(unsatisfied(i));
+                    }
+                    let value = to_u64(attribute)
+                        .filter(|v| v >= min && v <= max)
+                        .ok_or_else(|| unsatisfied(i))?;
+                    let k = bit_length(max - min);
+                    let (mut bits, mut openings) = commit_bits(value - min, &r, k);
+                    let (y_bits, y_openings) = commit_bits(max - value, &r.negation(), k);
+                    bits.extend(y_bits);
+                    openings.extend(y_openings);
+                    let ors = bits
+                        .iter()
+                        .zip(openings)
+                        .map(|(bit, (b, r_j))| OrCommitted::new(&bit_points(bit), b, r_j))
+                        .collect();
+                    (bits, ors)
+                }
+                Predicate::Member(set) => {
+                    let known = set
+                        .iter()
+                        .position(|s| s == attribute)
+                        .ok_or_else(|| unsatisfied(i))?;
+                    let points: Vec<G1> = set.iter().map(|s| &commitment - &(&g * s)).collect();
+                    (vec![], vec![OrCommitted::new(&points, known, r)])
+                }
+            };
+            conjuncts.push(ConjunctCommitted {
+                predicate: predicate.clone(),
+                commitment,
+                opening,
+                secrets,
+                bits,
+                ors,
+            });
+        }
+        Ok(PoKOfPredicates { conjuncts })
+    }
+
+    /// Bytes to hash into the challenge, the same as `PredicateProof::get_bytes_for_challenge`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        for c in &self.conjuncts {
+            bytes.append(&mut conjunct_bytes(
+                &c.predicate,
+                &c.commitment,
+                &c.opening.commitment,
+                &c.bits,
+                c.ors.iter().map(|o| &o.commitments),
+            ));
+        }
+        bytes
+    }
+
+    pub fn gen_proof(self, challenge: &FieldElement) -> Result<PredicateProof, BBSError> {
+        let mut conjuncts = Vec::with_capacity(self.conjuncts.len());
+        for c in self.conjuncts {
+            conjuncts.push(ConjunctProof {
+                commitment: c.commitment,
+                opening: c.opening.gen_proof(challenge, c.secrets.as_slice())?,
+                bits: c.bits,
+                ors: c.ors.into_iter().map(|o| o.gen_proof(challenge)).collect(),
+            });
+        }
+        Ok(PredicateProof { conjuncts })
+    }
+}
+
+/// Proof for one predicate of a `PredicateProof`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConjunctProof {
+    pub commitment: G1,
+    pub opening: ProofG1,
+    pub bits: Vec<G1>,
+    pub ors: Vec<OrProof>,
+}
+
+impl ConjunctProof {
+    fn verify(&self, predicate: &Predicate, challenge: &FieldElement) -> Result<bool, BBSError> {
+        let g = value_base();
+        if !self
+            .opening
+            .verify(&[g.clone(), blinding_base()], &self.commitment, challenge)?
+        {
+            return Ok(false);
+        }
+        match predicate {
+            Predicate::Range { min, max } => {
+                if min > max {
+                    return Ok(false);
+                }
+                let k = bit_length(max - min);
+                if self.bits.len() != 2 * k || self.ors.len() != 2 * k {
+                    return Ok(false);
+                }
+                let x = &self.commitment - &(&g * &encode_u64(*min));
+                let y = &(&g * &encode_u64(*max)) - &self.commitment;
+                if weighted_sum(&self.bits[..k]) != x || weighted_sum(&self.bits[k..]) != y {
+                    return Ok(false);
+                }
+                Ok(self
+                    .bits
+                    .iter()
+                    .zip(self.ors.iter())
+                    .all(|(bit, or)| or.verify(&bit_points(bit), challenge)))
+            }
+            Predicate::Member(set) => {
+                if !self.bits.is_empty() || self.ors.len() != 1 {
+                    return Ok(false);
+                }
+                let points: Vec<G1> = set.iter().map(|s| &self.commitment - &(&g * s)).collect();
+                Ok(self.ors[0].verify(&points, challenge))
+            }
+        }
+    }
+}
+
+/// Proof that committed attributes satisfy a conjunction of predicates, see `PoKOfPredicates`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PredicateProof {
+    pub conjuncts: Vec<ConjunctProof>,
+}
+
+impl PredicateProof {
+    /// Bytes to hash into the challenge, `predicates` in the order they were proven
+    pub fn get_bytes_for_challenge(&self, predicates: &[Predicate]) -> Vec<u8> {
+        let mut bytes = vec![];
+        for (c, predicate) in self.conjuncts.iter().zip(predicates) {
+            bytes.append(&mut conjunct_bytes(
+                predicate,
+                &c.commitment,
+                &c.opening.commitment,
+                &c.bits,
+                c.ors.iter().map(|o| &o.commitments),
+            ));
+        }
+        bytes
+    }
+
+    /// Response for the attribute of the predicate at `index`. Equals the response for a hidden
+    /// message of a `PoKOfSignatureProof` under the same challenge and blinding if and only if
+    /// the attribute is that message.
+    pub fn get_resp_for_attribute(&self, index: usize) -> Result<FieldElement, BBSError> {
+        match self.conjuncts.get(index) {
+            Some(c) => Ok(c.opening.responses[0].clone()),
+            None => Err(BBSError::from_kind(BBSErrorKind::GeneralError {
+                msg: format!(
+                    "Predicate index was given {} but should be less than {}",
+                    index,
+                    self.conjuncts.len()
+                ),
+            })),
+        }
+    }
+
+    /// True only if every conjunct holds under `challenge`. The caller recomputes the challenge
+    /// from `get_bytes_for_challenge` and anything else the prover hashed in.
+    pub fn verify(
+        &self,
+        predicates: &[Predicate],
+        challenge: &FieldElement,
+    ) -> Result<bool, BBSError> {
+        if predicates.len() != self.conjuncts.len() {
+            return Err(BBSError::from_kind(BBSErrorKind::GeneralError {
+                msg: format!(
+                    "Proof has {} predicates but {} were given",
+                    self.conjuncts.len(),
+                    predicates.len()
+                ),
+            }));
+        }
+        for (c, predicate) in self.conjuncts.iter().zip(predicates) {
+            if !c.verify(predicate, challenge)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use signatures::bbs::keys::generate;
+    use signatures::bbs::pok_sig::PoKOfSignature;
+    use signatures::bbs::signature::Signature;
+    use std::collections::{HashMap, HashSet};
+
+    #[test]
+    fn predicate_conjunction() {
+        let eu: Vec<SignatureMessage> = ["AT", "DE", "FR", "NL"]
+            .iter()
+            .map(|c| FieldElement::from_msg_hash(c.as_bytes()))
+            .collect();
+        let age = encode_u64(30);
+        let country = eu[2].clone();
+        let name = FieldElement::random();
+        let messages = vec![name.clone(), age.clone(), country.clone()];
+        let (verkey, signkey) = generate(messages.len()).unwrap();
+        let sig = Signature::new(&messages, &signkey, &verkey).unwrap();
+
+        let predicates = vec![
+            Predicate::Range { min: 18, max: 65 },
+            Predicate::Member(eu.clone()),
+        ];
+        let statements = vec![
+            (predicates[0].clone(), age.clone()),
+            (predicates[1].clone(), country.clone()),
+        ];
+
+        // All messages hidden, age and country linked to the predicates by their blindings
+        let blindings = FieldElementVector::random(3);
+        let pok = PoKOfSignature::init(
+            &sig,
+            &verkey,
+            &messages,
+            Some(blindings.as_slice()),
+            HashSet::new(),
+        )
+        .unwrap();
+        let pred = PoKOfPredicates::init(&statements, Some(&blindings.as_slice()[1..])).unwrap();
+        let mut bytes = pok.to_bytes();
+        bytes.append(&mut pred.to_bytes());
+        let challenge = FieldElement::from_msg_hash(&bytes);
+        let pok_proof = pok.gen_proof(&challenge).unwrap();
+        let proof = pred.gen_proof(&challenge).unwrap();
+
+        let mut bytes = pok_proof.get_bytes_for_challenge(HashSet::new(), &verkey);
+        bytes.append(&mut proof.get_bytes_for_challenge(&predicates));
+        let challenge = FieldElement::from_msg_hash(&bytes);
+        assert!(pok_proof
+            .verify(&verkey, HashMap::new(), &challenge)
+            .unwrap());
+        assert!(proof.verify(&predicates, &challenge).unwrap());
+        assert_eq!(
+            proof.get_resp_for_attribute(0).unwrap(),
+            pok_proof.get_resp_for_message(1).unwrap()
+        );
+        assert_eq!(
+            proof.get_resp_for_attribute(1).unwrap(),
+            pok_proof.get_resp_for_message(2).unwrap()
+        );
+
+        // A tighter range or a smaller set doesn't verify
+        let narrower = vec![Predicate::Range { min: 31, max: 65 }, predicates[1].clone()];
+        let challenge = FieldElement::from_msg_hash(&proof.get_bytes_for_challenge(&narrower));
+        assert!(!proof.verify(&narrower, &challenge).unwrap());
+        let smaller = vec![predicates[0].clone(), Predicate::Member(eu[..2].to_vec())];
+        let challenge = FieldElement::from_msg_hash(&proof.get_bytes_for_challenge(&smaller));
+        assert!(!proof.verify(&smaller, &challenge).unwrap());
+        assert!(proof.verify(&predicates[..1], &challenge).is_err());
+    }
+
+    #[test]
+    fn predicate_conjunct_fails() {
+        let eu: Vec<SignatureMessage> = ["AT", "DE"]
+            .iter()
+            .map(|c| FieldElement::from_msg_hash(c.as_bytes()))
+            .collect();
+        let in_range = (Predicate::Range { min: 18, max: 65 }, encode_u64(65));
+        let members = Predicate::Member(eu.clone());
+
+        // The prover can't prove a conjunction with a false conjunct
+        let too_old = (Predicate::Range { min: 18, max: 65 }, encode_u64(66));
+        let too_young = (Predicate::Range { min: 18, max: 65 }, encode_u64(17));
+        let outside = (members.clone(), FieldElement::from_msg_hash(b"US"));
+        for bad in &[too_old, too_young, outside] {
+            assert!(PoKOfPredicates::init(&[in_range.clone(), bad.clone()], None).is_err());
+        }
+
+        // Nor pass off a proof of one conjunction as another
+        let statements = vec![in_range.clone(), (members.clone(), eu[1].clone())];
+        let predicates: Vec<Predicate> = statements.iter().map(|s| s.0.clone()).collect();
+        let pred = PoKOfPredicates::init(&statements, None).unwrap();
+        let challenge = FieldElement::from_msg_hash(&pred.to_bytes());
+        let proof = pred.gen_proof(&challenge).unwrap();
+        assert!(proof.verify(&predicates, &challenge).unwrap());
+        assert!(!proof.verify(&predicates, &FieldElement::random()).unwrap());
+
+        // A conjunct proven under another challenge doesn't combine
+        let other = PoKOfPredicates::init(&statements, None).unwrap();
+        let other = other.gen_proof(&FieldElement::random()).unwrap();
+        let mut mixed = proof.clone();
+        mixed.conjuncts[1] = other.conjuncts[1].clone();
+        let challenge = FieldElement::from_msg_hash(&mixed.get_bytes_for_challenge(&predicates));
+        assert!(!mixed.verify(&predicates, &challenge).unwrap());
+    }
+
+    #[test]
+    fn predicate_full_u64_range() {
+        let predicates = vec![Predicate::Range {
+            min: 1 << 63,
+            max: u64::MAX,
+        }];
+        let statements = vec![(predicates[0].clone(), encode_u64(u64::MAX - 1))];
+        let pred = PoKOfPredicates::init(&statements, None).unwrap();
+        let challenge = FieldElement::from_msg_hash(&pred.to_bytes());
+        let proof = pred.gen_proof(&challenge).unwrap();
+        assert!(proof.verify(&predicates, &challenge).unwrap());
+        let below = vec![(predicates[0].clone(), encode_u64((1 << 63) - 1))];
+        assert!(PoKOfPredicates::init(&below, None).is_err());
+    }
+}
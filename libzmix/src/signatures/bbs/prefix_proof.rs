@@ -0,0 +1,174 @@
+use super::super::SignatureMessage;
+use super::keys::PublicKey;
+use super::messages::encode_string;
+use super::pok_sig::{PoKOfSignature, PoKOfSignatureProof};
+use super::signature::Signature;
+use crate::errors::prelude::*;
+
+use std::collections::{HashMap, HashSet};
+
+use amcl_wrapper::field_elem::FieldElement;
+
+pub mod prelude {
+    pub use super::PrefixProof;
+}
+
+/// Proof that a string attribute starts (or ends) with a public string without revealing the
+/// rest of it.
+/// The attribute must have been signed as the two messages returned by `encode_split`, the head
+/// at `index` and the tail at `index + 1`. The proof is a proof of knowledge of the signature
+/// disclosing only one of the parts, every other message stays hidden. For example an email
+/// split at its `@` can be shown to end in `@example.com` by disclosing the tail.
+/// The verifier encodes the expected string itself so a wrong value fails the proof, and names
+/// the attribute and side it expects so a matching message elsewhere in the credential doesn't
+/// pass for it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrefixProof {
+    pub index: usize,
+    pub suffix: bool, // the tail is disclosed instead of the head
+    pub proof: PoKOfSignatureProof,
+}
+
+impl PrefixProof {
+    /// Disclose the head at `index`, proving the value starts with it
+    pub fn prove_prefix(
+        signature: &Signature,
+        verkey: &PublicKey,
+        messages: &[SignatureMessage],
+        index: usize,
+    ) -> Result<Self, BBSError> {
+        Self::new(signature, verkey, messages, index, false)
+    }
+
+    /// Disclose the tail at `index + 1`, proving the value ends with it
+    pub fn prove_suffix(
+        signature: &Signature,
+        verkey: &PublicKey,
+        messages: &[SignatureMessage],
+        index: usize,
+    ) -> Result<Self, BBSError> {
+        Self::new(signature, verkey, messages, index, true)
+    }
+
+    fn new(
+        signature: &Signature,
+        verkey: &PublicKey,
+        messages: &[SignatureMessage],
+        index: usize,
+        suffix: bool,
+    ) -> Result<Self, BBSError> {
+        if index >= messages.len().saturating_sub(1) {
+            return Err(BBSError::from_kind(BBSErrorKind::GeneralError {
+                msg: format!(
+                    "Index {} and the one after it should be less than {}",
+                    index,
+                    messages.len()
+                ),
+            }));
+        }
+        let disclosed = Self::disclosed_index(index, suffix);
+        let mut revealed = HashSet::new();
+        revealed.insert(disclosed);
+        let pok = PoKOfSignature::init(signature, verkey, messages, None, revealed)?;
+        let challenge = Self::challenge(pok.to_bytes(), disclosed, &messages[disclosed]);
+        let proof = pok.gen_proof(&challenge)?;
+        Ok(PrefixProof {
+            index,
+            suffix,
+            proof,
+        })
+    }
+
+    /// Check that the attribute split at `index` starts with `expected`, or ends with it for
+    /// `suffix`. False if the proof discloses another attribute or the other side.
+    pub fn verify(
+        &self,
+        verkey: &PublicKey,
+        index: usize,
+        suffix: bool,
+        expected: &str,
+    ) -> Result<bool, BBSError> {
+        if self.index != index || self.suffix != suffix {
+            return Ok(false);
+        }
+        let disclosed = Self::disclosed_index(self.index, self.suffix);
+        let message = encode_string(expected);
+        let mut revealed_indices = HashSet::new();
+        revealed_indices.insert(disclosed);
+        let challenge = Self::challenge(
+            self.proof.get_bytes_for_challenge(revealed_indices, verkey),
+            disclosed,
+            &message,
+        );
+        let mut revealed_msgs = HashMap::new();
+        revealed_msgs.insert(disclosed, message);
+        self.proof.verify(verkey, revealed_msgs, &challenge)
+    }
+
+    fn disclosed_index(index: usize, suffix: bool) -> usize {
+        if suffix {
+            index + 1
+        } else {
+            index
+        }
+    }
+
+    fn challenge(mut bytes: Vec<u8>, index: usize, message: &SignatureMessage) -> FieldElement {
+        bytes.extend_from_slice(&(index as u32).to_be_bytes());
+        bytes.append(&mut message.to_bytes());
+        FieldElement::from_msg_hash(&bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use signatures::bbs::keys::generate;
+    use signatures::bbs::messages::encode_split;
+
+    #[test]
+    fn prefix_and_suffix_proofs() {
+        let (verkey, signkey) = generate(4).unwrap();
+        let email = "alice@example.com";
+        let url = "https://example.com/alice";
+        let (local, domain) = encode_split(email, email.find('@').unwrap()).unwrap();
+        let (origin, path) = encode_split(url, "https://example.com".len()).unwrap();
+        let messages = vec![local, domain, origin, path];
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+
+        let proof = PrefixProof::prove_suffix(&sig, &verkey, messages.as_slice(), 0).unwrap();
+        assert!(proof.verify(&verkey, 0, true, "@example.com").unwrap());
+        assert!(!proof.verify(&verkey, 0, true, "@example.org").unwrap());
+        assert!(!proof.verify(&verkey, 0, false, "alice").unwrap());
+
+        let proof = PrefixProof::prove_prefix(&sig, &verkey, messages.as_slice(), 2).unwrap();
+        assert!(proof
+            .verify(&verkey, 2, false, "https://example.com")
+            .unwrap());
+        assert!(!proof.verify(&verkey, 2, false, "https://evil.com").unwrap());
+
+        // Another attribute whose head equals the tail of the email can't stand in for it
+        let (_, host) = encode_split(email, email.find('@').unwrap()).unwrap();
+        let messages = vec![
+            messages[0].clone(),
+            messages[1].clone(),
+            host,
+            messages[3].clone(),
+        ];
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+        let proof = PrefixProof::prove_prefix(&sig, &verkey, messages.as_slice(), 2).unwrap();
+        assert!(proof.verify(&verkey, 2, false, "@example.com").unwrap());
+        assert!(!proof.verify(&verkey, 0, true, "@example.com").unwrap());
+        let mut relabeled = proof.clone();
+        relabeled.index = 0;
+        relabeled.suffix = true;
+        assert!(!relabeled.verify(&verkey, 0, true, "@example.com").unwrap());
+
+        // The tail of the last message doesn't exist
+        assert!(PrefixProof::prove_prefix(&sig, &verkey, messages.as_slice(), 3).is_err());
+        assert!(PrefixProof::prove_suffix(&sig, &verkey, messages.as_slice(), usize::MAX).is_err());
+        assert!(PrefixProof::prove_prefix(&sig, &verkey, &[], 0).is_err());
+        assert!(PrefixProof::prove_prefix(&sig, &verkey, &messages[..1], 0).is_err());
+        assert!(encode_split("\u{e9}", 1).is_err());
+    }
+}
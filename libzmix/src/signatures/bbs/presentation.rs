@@ -0,0 +1,732 @@
+use super::super::SignatureMessage;
+use super::keys::{PublicKey, SecretKey};
+use super::messages::EncodingProfile;
+use super::pairing::PairingAccumulator;
+use super::pok_sig::{PoKOfSignature, PoKOfSignatureProof, ProofG1};
+use super::signature::Signature;
+use crate::errors::prelude::*;
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
+use amcl_wrapper::group_elem::{GroupElement, GroupElementVector};
+use amcl_wrapper::group_elem_g1::{G1Vector, G1};
+
+pub mod prelude {
+    pub use super::{
+        combine_nonces, sign_and_present, verify_presentation, verify_presentation_multi,
+        CompactProof, Presentation, PresentationBatch, PresentationProof, ProofNonce,
+    };
+}
+
+/// Random value chosen by the verifier for each presentation request
+pub type ProofNonce = FieldElement;
+
+const COMBINED_NONCE_DST: &[u8] = b"BBS+ combined nonce";
+
+/// Single nonce standing for the nonces of several verifiers checking one presentation
+/// together. The holder presents with the result and every verifier checks with
+/// `verify_presentation_multi`, so the presentation is bound to each of the nonces and none of
+/// the verifiers has to trust the others to pick a fresh one. The order of `nonces` doesn't
+/// matter, a nonce given twice counts twice.
+pub fn combine_nonces(nonces: &[ProofNonce]) -> ProofNonce {
+    let mut encoded: Vec<Vec<u8>> = nonces.iter().map(|n| n.to_bytes()).collect();
+    encoded.sort();
+    let mut bytes = COMBINED_NONCE_DST.to_vec();
+    bytes.extend_from_slice(&(encoded.len() as u32).to_be_bytes());
+    for n in encoded {
+        bytes.extend(n);
+    }
+    FieldElement::from_msg_hash(&bytes)
+}
+
+/// How the holder shows possession of the signature
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum PresentationProof {
+    /// Proof of knowledge of the signature, the nonce is part of its challenge
+    Knowledge(PoKOfSignatureProof),
+    /// Proof of knowledge of the signature carrying the challenge instead of the commitments of
+    /// its two Schnorr proofs, see `CompactProof`
+    Compact(CompactProof),
+}
+
+/// `PoKOfSignatureProof` with the challenge in place of the two Schnorr commitments, one field
+/// element instead of two `G1` elements. The verifier recomputes the commitments from the
+/// responses and checks that they hash to the challenge. Equally sound, only smaller.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompactProof {
+    pub a_prime: G1,
+    pub a_bar: G1,
+    pub d: G1,
+    pub challenge: FieldElement,
+    pub responses_1: FieldElementVector,
+    pub responses_2: FieldElementVector,
+}
+
+impl CompactProof {
+    fn new(proof: PoKOfSignatureProof, challenge: FieldElement) -> Self {
+        CompactProof {
+            a_prime: proof.a_prime,
+            a_bar: proof.a_bar,
+            d: proof.d,
+            challenge,
+            responses_1: proof.proof_vc_1.responses,
+            responses_2: proof.proof_vc_2.responses,
+        }
+    }
+
+    // The full proof with the Schnorr commitments recomputed from the responses and the
+    // challenge, `None` if the number of responses doesn't fit
+    fn expand(
+        &self,
+        verkey: &PublicKey,
+        revealed_messages: &BTreeMap<usize, SignatureMessage>,
+    ) -> Option<PoKOfSignatureProof> {
+        let hidden = verkey.message_count() - revealed_messages.len();
+        if self.responses_1.len() != 2 || self.responses_2.len() != 2 + hidden {
+            return None;
+        }
+        let c = &self.challenge;
+
+        // t1 = a_prime^r1[0] * h0^r1[1] * (a_bar / d)^c
+        let mut points = G1Vector::with_capacity(3);
+        let mut scalars = FieldElementVector::with_capacity(3);
+        points.push(self.a_prime.clone());
+        points.push(verkey.h0.clone());
+        points.push(&self.a_bar - &self.d);
+        scalars.push(self.responses_1[0].clone());
+        scalars.push(self.responses_1[1].clone());
+        scalars.push(c.clone());
+        let t1 = points.multi_scalar_mul_var_time(&scalars).unwrap();
+
+        // t2 = d^r2[0] * h0^r2[1] * h_j^r2[..] for hidden j * pr^c with
+        // pr = (g1 * h_i^m_i for revealed i)^-1
+        let mut pr = G1::generator();
+        let mut points = G1Vector::with_capacity(3 + hidden);
+        points.push(self.d.clone());
+        points.push(verkey.h0.clone());
+        for i in 0..verkey.message_count() {
+            match revealed_messages.get(&i) {
+                Some(m) => pr += &verkey.h[i] * m,
+                None => points.push(verkey.h[i].clone()),
+            }
+        }
+        points.push(-pr);
+        let mut scalars = self.responses_2.clone();
+        scalars.push(c.clone());
+        let t2 = points.multi_scalar_mul_var_time(&scalars).unwrap();
+
+        Some(PoKOfSignatureProof {
+            a_prime: self.a_prime.clone(),
+            a_bar: self.a_bar.clone(),
+            d: self.d.clone(),
+            proof_vc_1: ProofG1 {
+                commitment: t1,
+                responses: self.responses_1.clone(),
+            },
+            proof_vc_2: ProofG1 {
+                commitment: t2,
+                responses: self.responses_2.clone(),
+            },
+        })
+    }
+}
+
+/// A selective disclosure presentation of a signature. Contains a proof of possession of the
+/// signature, the messages revealed to the verifier and the fingerprint of the
+/// `EncodingProfile` the messages were encoded with.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Presentation {
+    pub proof: PresentationProof,
+    pub revealed_messages: BTreeMap<usize, SignatureMessage>,
+    pub encoding: FieldElement,
+}
+
+impl Presentation {
+    /// Prove knowledge of `signature` revealing the messages at `revealed_indices`.
+    pub fn new(
+        signature: &Signature,
+        verkey: &PublicKey,
+        messages: &[SignatureMessage],
+        revealed_indices: &BTreeSet<usize>,
+        profile: &EncodingProfile,
+        nonce: &ProofNonce,
+    ) -> Result<Self, BBSError> {
+        let pok = PoKOfSignature::init(
+            signature,
+            verkey,
+            messages,
+            None,
+            revealed_indices.iter().cloned().collect(),
+        )?;
+        let revealed_messages = revealed_indices
+            .iter()
+            .map(|i| (*i, messages[*i].clone()))
+            .collect();
+        let encoding = profile.fingerprint();
+        let challenge = compute_challenge(pok.to_bytes(), &revealed_messages, &encoding, nonce);
+        let proof = pok.gen_proof(&challenge)?;
+        Ok(Presentation {
+            proof: PresentationProof::Knowledge(proof),
+            revealed_messages,
+            encoding,
+        })
+    }
+}
+
+impl Signature {
+    /// Presentation revealing only the message at `reveal_index`, e.g. an over 18 flag, with a
+    /// `CompactProof`. Verified with `verify_presentation` like any other.
+    pub fn present_single(
+        &self,
+        messages: &[SignatureMessage],
+        verkey: &PublicKey,
+        reveal_index: usize,
+        profile: &EncodingProfile,
+        nonce: &ProofNonce,
+    ) -> Result<Presentation, BBSError> {
+        let mut revealed_indices = BTreeSet::new();
+        revealed_indices.insert(reveal_index);
+        self.present_compact(messages, verkey, &revealed_indices, profile, nonce)
+    }
+
+    /// Presentation revealing every message, a `CompactProof` with no hidden message so it
+    /// carries only the four responses for `e`, `r2`, `r3` and `s'`. Bound to the nonce by its
+    /// challenge like any proof of knowledge, so it can't be replayed to another verifier. The
+    /// revealed messages make every presentation of the signature linkable.
+    pub fn present_all(
+        &self,
+        messages: &[SignatureMessage],
+        verkey: &PublicKey,
+        profile: &EncodingProfile,
+        nonce: &ProofNonce,
+    ) -> Result<Presentation, BBSError> {
+        let revealed_indices = (0..messages.len()).collect();
+        self.present_compact(messages, verkey, &revealed_indices, profile, nonce)
+    }
+
+    fn present_compact(
+        &self,
+        messages: &[SignatureMessage],
+        verkey: &PublicKey,
+        revealed_indices: &BTreeSet<usize>,
+        profile: &EncodingProfile,
+        nonce: &ProofNonce,
+    ) -> Result<Presentation, BBSError> {
+        let pok = PoKOfSignature::init(
+            self,
+            verkey,
+            messages,
+            None,
+            revealed_indices.iter().cloned().collect(),
+        )?;
+        let revealed_messages = revealed_indices
+            .iter()
+            .map(|i| (*i, messages[*i].clone()))
+            .collect();
+        let encoding = profile.fingerprint();
+        let challenge = compute_challenge(pok.to_bytes(), &revealed_messages, &encoding, nonce);
+        let proof = pok.gen_proof(&challenge)?;
+        Ok(Presentation {
+            proof: PresentationProof::Compact(CompactProof::new(proof, challenge)),
+            revealed_messages,
+            encoding,
+        })
+    }
+}
+
+/// Sign `messages` and present the signature revealing the messages at `reveal`, all in one
+/// call, for demos and tests that play signer and holder at once. A real holder gets the
+/// signature from the signer and presents it with `Presentation::new` later, once per nonce.
+pub fn sign_and_present(
+    messages: &[SignatureMessage],
+    reveal: &BTreeSet<usize>,
+    signkey: &SecretKey,
+    verkey: &PublicKey,
+    profile: &EncodingProfile,
+    nonce: &ProofNonce,
+) -> Result<(Signature, Presentation), BBSError> {
+    let signature = Signature::new(messages, signkey, verkey)?;
+    let presentation = Presentation::new(&signature, verkey, messages, reveal, profile, nonce)?;
+    Ok((signature, presentation))
+}
+
+/// Verify `presentation` against `verkey` and the `nonce` sent to the holder. The verifier
+/// supplies the `EncodingProfile` it expects, a presentation made under any other profile is
+/// rejected with `BBSErrorKind::EncodingMismatch` instead of failing the proof.
+pub fn verify_presentation(
+    presentation: &Presentation,
+    verkey: &PublicKey,
+    profile: &EncodingProfile,
+    nonce: &ProofNonce,
+) -> Result<bool, BBSError> {
+    check_presentation(presentation, verkey, profile, nonce, None)
+}
+
+// `verify_presentation`, or with `acc` everything but the pairings, which go to `acc`
+fn check_presentation(
+    presentation: &Presentation,
+    verkey: &PublicKey,
+    profile: &EncodingProfile,
+    nonce: &ProofNonce,
+    acc: Option<&mut PairingAccumulator>,
+) -> Result<bool, BBSError> {
+    if presentation.encoding != profile.fingerprint() {
+        return Err(BBSError::from_kind(BBSErrorKind::EncodingMismatch));
+    }
+    match &presentation.proof {
+        PresentationProof::Knowledge(proof) => {
+            let revealed_indices: HashSet<usize> =
+                presentation.revealed_messages.keys().cloned().collect();
+            let challenge = compute_challenge(
+                proof.get_bytes_for_challenge(revealed_indices, verkey),
+                &presentation.revealed_messages,
+                &presentation.encoding,
+                nonce,
+            );
+            let revealed_msgs: HashMap<usize, FieldElement> = presentation
+                .revealed_messages
+                .iter()
+                .map(|(i, m)| (*i, m.clone()))
+                .collect();
+            match acc {
+                Some(acc) => proof.verify_accumulate(verkey, revealed_msgs, &challenge, acc),
+                None => proof.verify(verkey, revealed_msgs, &challenge),
+            }
+        }
+        PresentationProof::Compact(compact) => {
+            for i in presentation.revealed_messages.keys() {
+                if *i >= verkey.message_count() {
+                    return Err(BBSError::from_kind(BBSErrorKind::GeneralError {
+                        msg: format!("Index {} should be less than {}", i, verkey.message_count()),
+                    }));
+                }
+            }
+            let proof = match compact.expand(verkey, &presentation.revealed_messages) {
+                Some(proof) => proof,
+                None => return Ok(false),
+            };
+            let revealed_indices: HashSet<usize> =
+                presentation.revealed_messages.keys().cloned().collect();
+            let challenge = compute_challenge(
+                proof.get_bytes_for_challenge(revealed_indices, verkey),
+                &presentation.revealed_messages,
+                &presentation.encoding,
+                nonce,
+            );
+            if challenge != compact.challenge {
+                return Ok(false);
+            }
+            let revealed_msgs: HashMap<usize, FieldElement> = presentation
+                .revealed_messages
+                .iter()
+                .map(|(i, m)| (*i, m.clone()))
+                .collect();
+            match acc {
+                Some(acc) => proof.verify_accumulate(verkey, revealed_msgs, &challenge, acc),
+                None => proof.verify(verkey, revealed_msgs, &challenge),
+            }
+        }
+    }
+}
+
+/// Checks several presentations, e.g. one per credential of a multi-credential request, with
+/// a single multi-pairing. Each proof of knowledge comes down to a pairing equation
+/// `e(p, q) * e(t, g2) == 1`; `add` runs every other check of `verify_presentation` right away
+/// and hands the equation to a `PairingAccumulator`. `check` then computes the Miller loops of all equations, multiplies
+/// their outputs and does one final exponentiation for all of them, instead of one per
+/// presentation, with every `t` summed into a single pairing with `g2`. The random exponent
+/// `PairingAccumulator` puts on each equation keeps a false one from being cancelled by
+/// another. The result is all or nothing: a failing batch doesn't say which presentation
+/// failed, verify them one by one with `verify_presentation` for that.
+#[derive(Debug, Clone, Default)]
+pub struct PresentationBatch {
+    acc: PairingAccumulator,
+    failed: bool,
+}
+
+impl PresentationBatch {
+    pub fn new() -> Self {
+        PresentationBatch::default()
+    }
+
+    /// Add `presentation` to the batch. Errors are those of `verify_presentation`, a
+    /// presentation that fails any check but the pairing makes `check` false.
+    pub fn add(
+        &mut self,
+        presentation: &Presentation,
+        verkey: &PublicKey,
+        profile: &EncodingProfile,
+        nonce: &ProofNonce,
+    ) -> Result<(), BBSError> {
+        if !check_presentation(presentation, verkey, profile, nonce, Some(&mut self.acc))? {
+            self.failed = true;
+        }
+        Ok(())
+    }
+
+    /// Number of pairing equations added, one per presentation that passed the other checks
+    pub fn len(&self) -> usize {
+        self.acc.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.acc.is_empty()
+    }
+
+    /// True only if every added presentation verifies. An empty batch checks trivially.
+    pub fn check(&self) -> bool {
+        !self.failed && self.acc.check()
+    }
+}
+
+/// `verify_presentation` for a presentation made with `combine_nonces(nonces)`
+pub fn verify_presentation_multi(
+    presentation: &Presentation,
+    verkey: &PublicKey,
+    profile: &EncodingProfile,
+    nonces: &[ProofNonce],
+) -> Result<bool, BBSError> {
+    if nonces.is_empty() {
+        return Err(BBSError::from_kind(BBSErrorKind::GeneralError {
+            msg: "At least one nonce is needed".to_string(),
+        }));
+    }
+    verify_presentation(presentation, verkey, profile, &combine_nonces(nonces))
+}
+
+// Fiat-Shamir challenge over the proof commitments, the revealed messages, the encoding and the
+// verifier's nonce.
+fn compute_challenge(
+    mut bytes: Vec<u8>,
+    revealed_messages: &BTreeMap<usize, SignatureMessage>,
+    encoding: &FieldElement,
+    nonce: &ProofNonce,
+) -> FieldElement {
+    for (i, m) in revealed_messages {
+        bytes.extend_from_slice(&(*i as u32).to_be_bytes());
+        bytes.append(&mut m.to_bytes());
+    }
+    bytes.append(&mut encoding.to_bytes());
+    bytes.append(&mut nonce.to_bytes());
+    FieldElement::from_msg_hash(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amcl_wrapper::field_elem::FieldElementVector;
+    use amcl_wrapper::group_elem_g2::G2;
+    use signatures::bbs::keys::generate;
+
+    #[test]
+    fn presentation_encoding_profile() {
+        let message_count = 4;
+        let messages = FieldElementVector::random(message_count);
+        let (verkey, signkey) = generate(message_count).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+
+        let profile = EncodingProfile::new("employee", 1, &["name", "id", "dept", "level"]);
+        let mut revealed = BTreeSet::new();
+        revealed.insert(1);
+        revealed.insert(3);
+        let nonce = ProofNonce::random();
+
+        let presentation = Presentation::new(
+            &sig,
+            &verkey,
+            messages.as_slice(),
+            &revealed,
+            &profile,
+            &nonce,
+        )
+        .unwrap();
+        assert!(verify_presentation(&presentation, &verkey, &profile, &nonce).unwrap());
+        assert!(
+            !verify_presentation(&presentation, &verkey, &profile, &ProofNonce::random()).unwrap()
+        );
+
+        // Verifier expects a newer layout
+        let other = EncodingProfile::new("employee", 2, &["name", "id", "dept", "level"]);
+        let res = verify_presentation(&presentation, &verkey, &other, &nonce);
+        match res.unwrap_err().kind() {
+            BBSErrorKind::EncodingMismatch => {}
+            _ => panic!("Expected EncodingMismatch"),
+        }
+
+        // Claiming a different profile invalidates the proof
+        let mut forged = presentation.clone();
+        forged.encoding = other.fingerprint();
+        assert!(!verify_presentation(&forged, &verkey, &other, &nonce).unwrap());
+    }
+
+    #[test]
+    fn presentation_all_revealed() {
+        let message_count = 4;
+        let messages = FieldElementVector::random(message_count);
+        let (verkey, signkey) = generate(message_count).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+        let profile = EncodingProfile::new("employee", 1, &["name", "id", "dept", "level"]);
+        let nonce = ProofNonce::random();
+
+        let compact = sig
+            .present_all(messages.as_slice(), &verkey, &profile, &nonce)
+            .unwrap();
+        assert!(verify_presentation(&compact, &verkey, &profile, &nonce).unwrap());
+        assert!(!verify_presentation(&compact, &verkey, &profile, &ProofNonce::random()).unwrap());
+
+        let revealed: BTreeSet<usize> = (0..message_count).collect();
+        let full = Presentation::new(
+            &sig,
+            &verkey,
+            messages.as_slice(),
+            &revealed,
+            &profile,
+            &nonce,
+        )
+        .unwrap();
+        assert!(verify_presentation(&full, &verkey, &profile, &nonce).unwrap());
+        let compact_size = serde_json::to_vec(&compact).unwrap().len();
+        assert!(compact_size < serde_json::to_vec(&full).unwrap().len());
+
+        // Dropping or altering a message fails
+        let mut partial = compact.clone();
+        partial.revealed_messages.remove(&0);
+        assert!(!verify_presentation(&partial, &verkey, &profile, &nonce).unwrap());
+        let mut altered = compact.clone();
+        altered.revealed_messages.insert(2, FieldElement::random());
+        assert!(!verify_presentation(&altered, &verkey, &profile, &nonce).unwrap());
+
+        // Replaying to a verifier with another nonce fails, and the old form carrying the
+        // signature with the nonce in clear, rewritten for the new nonce, is not accepted
+        let other_nonce = ProofNonce::random();
+        assert!(!verify_presentation(&compact, &verkey, &profile, &other_nonce).unwrap());
+        let mut replayed = serde_json::to_value(&compact).unwrap();
+        replayed["proof"] = serde_json::json!({
+            "Signature": { "signature": sig, "nonce": other_nonce }
+        });
+        assert!(serde_json::from_value::<Presentation>(replayed).is_err());
+
+        assert!(sig
+            .present_all(&messages.as_slice()[1..], &verkey, &profile, &nonce)
+            .is_err());
+    }
+
+    #[test]
+    fn presentation_multiple_nonces() {
+        let message_count = 3;
+        let messages = FieldElementVector::random(message_count);
+        let (verkey, signkey) = generate(message_count).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+        let profile = EncodingProfile::new("id", 1, &["a", "b", "c"]);
+        let nonces = vec![ProofNonce::random(), ProofNonce::random()];
+
+        let mut revealed = BTreeSet::new();
+        revealed.insert(1);
+        let presentation = Presentation::new(
+            &sig,
+            &verkey,
+            messages.as_slice(),
+            &revealed,
+            &profile,
+            &combine_nonces(&nonces),
+        )
+        .unwrap();
+        assert!(verify_presentation_multi(&presentation, &verkey, &profile, &nonces).unwrap());
+        let swapped = vec![nonces[1].clone(), nonces[0].clone()];
+        assert!(verify_presentation_multi(&presentation, &verkey, &profile, &swapped).unwrap());
+
+        // Bound to every nonce
+        for i in 0..2 {
+            let mut other = nonces.clone();
+            other[i] = ProofNonce::random();
+            assert!(!verify_presentation_multi(&presentation, &verkey, &profile, &other).unwrap());
+            assert!(!verify_presentation(&presentation, &verkey, &profile, &nonces[i]).unwrap());
+        }
+        assert!(
+            !verify_presentation_multi(&presentation, &verkey, &profile, &nonces[..1]).unwrap()
+        );
+        assert!(verify_presentation_multi(&presentation, &verkey, &profile, &[]).is_err());
+    }
+
+    #[test]
+    fn presentation_single_revealed() {
+        let message_count = 5;
+        let messages = FieldElementVector::random(message_count);
+        let (verkey, signkey) = generate(message_count).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+        let profile = EncodingProfile::new("id", 1, &["name", "over_18", "dob", "city", "id"]);
+        let nonce = ProofNonce::random();
+
+        let single = sig
+            .present_single(messages.as_slice(), &verkey, 1, &profile, &nonce)
+            .unwrap();
+        assert_eq!(single.revealed_messages.len(), 1);
+        assert!(verify_presentation(&single, &verkey, &profile, &nonce).unwrap());
+        assert!(!verify_presentation(&single, &verkey, &profile, &ProofNonce::random()).unwrap());
+
+        let revealed: BTreeSet<usize> = vec![1].into_iter().collect();
+        let general = Presentation::new(
+            &sig,
+            &verkey,
+            messages.as_slice(),
+            &revealed,
+            &profile,
+            &nonce,
+        )
+        .unwrap();
+        let single_size = serde_json::to_vec(&single).unwrap().len();
+        assert!(single_size < serde_json::to_vec(&general).unwrap().len());
+
+        let mut altered = single.clone();
+        altered.revealed_messages.insert(1, FieldElement::random());
+        assert!(!verify_presentation(&altered, &verkey, &profile, &nonce).unwrap());
+        let mut moved = single.clone();
+        let m = moved.revealed_messages.remove(&1).unwrap();
+        moved.revealed_messages.insert(2, m);
+        assert!(!verify_presentation(&moved, &verkey, &profile, &nonce).unwrap());
+        let mut tampered = single;
+        if let PresentationProof::Compact(ref mut proof) = tampered.proof {
+            proof.responses_2[2] = FieldElement::random();
+        }
+        assert!(!verify_presentation(&tampered, &verkey, &profile, &nonce).unwrap());
+
+        assert!(sig
+            .present_single(
+                messages.as_slice(),
+                &verkey,
+                message_count,
+                &profile,
+                &nonce
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn sign_and_present_round_trip() {
+        let messages = FieldElementVector::random(4);
+        let (verkey, signkey) = generate(4).unwrap();
+        let profile = EncodingProfile::new("demo", 1, &["a", "b", "c", "d"]);
+        let nonce = ProofNonce::random();
+        let reveal: BTreeSet<usize> = vec![0, 3].into_iter().collect();
+
+        let (sig, presentation) = sign_and_present(
+            messages.as_slice(),
+            &reveal,
+            &signkey,
+            &verkey,
+            &profile,
+            &nonce,
+        )
+        .unwrap();
+        assert!(sig.verify(messages.as_slice(), &verkey).unwrap());
+        assert_eq!(
+            presentation
+                .revealed_messages
+                .keys()
+                .cloned()
+                .collect::<BTreeSet<usize>>(),
+            reveal
+        );
+        assert_eq!(presentation.revealed_messages[&3], messages[3]);
+        assert!(verify_presentation(&presentation, &verkey, &profile, &nonce).unwrap());
+        assert!(
+            !verify_presentation(&presentation, &verkey, &profile, &ProofNonce::random()).unwrap()
+        );
+
+        assert!(sign_and_present(
+            &messages.as_slice()[1..],
+            &reveal,
+            &signkey,
+            &verkey,
+            &profile,
+            &nonce
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn presentation_batch_matches_verify() {
+        let (verkey_1, signkey_1) = generate(4).unwrap();
+        let (verkey_2, signkey_2) = generate(3).unwrap();
+        let profile_1 = EncodingProfile::new("employee", 1, &["name", "id", "dept", "level"]);
+        let profile_2 = EncodingProfile::new("license", 1, &["name", "class", "expiry"]);
+        let messages_1 = FieldElementVector::random(4);
+        let messages_2 = FieldElementVector::random(3);
+        let sig_1 = Signature::new(messages_1.as_slice(), &signkey_1, &verkey_1).unwrap();
+        let sig_2 = Signature::new(messages_2.as_slice(), &signkey_2, &verkey_2).unwrap();
+        let nonce = ProofNonce::random();
+        let reveal: BTreeSet<usize> = vec![1].into_iter().collect();
+
+        // One presentation of each kind of proof
+        let presentations = vec![
+            (
+                Presentation::new(
+                    &sig_1,
+                    &verkey_1,
+                    messages_1.as_slice(),
+                    &reveal,
+                    &profile_1,
+                    &nonce,
+                )
+                .unwrap(),
+                &verkey_1,
+                &profile_1,
+            ),
+            (
+                sig_2
+                    .present_single(messages_2.as_slice(), &verkey_2, 2, &profile_2, &nonce)
+                    .unwrap(),
+                &verkey_2,
+                &profile_2,
+            ),
+            (
+                sig_2
+                    .present_all(messages_2.as_slice(), &verkey_2, &profile_2, &nonce)
+                    .unwrap(),
+                &verkey_2,
+                &profile_2,
+            ),
+        ];
+        let mut batch = PresentationBatch::new();
+        assert!(batch.is_empty() && batch.check());
+        for (presentation, verkey, profile) in &presentations {
+            assert!(verify_presentation(presentation, verkey, profile, &nonce).unwrap());
+            batch.add(presentation, verkey, profile, &nonce).unwrap();
+        }
+        assert_eq!(batch.len(), 3);
+        assert!(batch.check());
+
+        // Another nonce fails the challenge of the proofs, before any pairing
+        let mut batch = PresentationBatch::new();
+        let (presentation, verkey, profile) = &presentations[0];
+        assert!(
+            !verify_presentation(presentation, verkey, profile, &ProofNonce::random()).unwrap()
+        );
+        batch
+            .add(presentation, verkey, profile, &ProofNonce::random())
+            .unwrap();
+        assert!(batch.is_empty() && !batch.check());
+
+        // A key with the same bases but another `w` only fails the pairing
+        let forged_key = PublicKey {
+            w: G2::random(),
+            ..verkey_2.clone()
+        };
+        let (forged, _, _) = &presentations[2];
+        assert!(!verify_presentation(forged, &forged_key, &profile_2, &nonce).unwrap());
+        let mut batch = PresentationBatch::new();
+        for (presentation, verkey, profile) in &presentations {
+            batch.add(presentation, verkey, profile, &nonce).unwrap();
+        }
+        batch.add(forged, &forged_key, &profile_2, &nonce).unwrap();
+        assert_eq!(batch.len(), 4);
+        assert!(!batch.check());
+
+        // Errors are those of `verify_presentation`
+        assert!(batch
+            .add(&presentations[0].0, &verkey_1, &profile_2, &nonce)
+            .is_err());
+    }
+}
@@ -0,0 +1,322 @@
+// Revocation by list: one message of every credential is a revocation id, e.g. a random
+// `FieldElement` the issuer picks per credential, and the registry is the set of revoked ids.
+//
+// A registry is `{ "index": <position of the id among the messages>, "version": <u64>,
+// "revoked": [<ids>] }` with serde, ids sorted. The issuer publishes a `RegistryDelta` for every
+// change, `{ "from_version", "to_version", "revoked", "unrevoked" }`, and verifiers apply them in
+// order with `apply_delta` instead of reloading the whole registry. A delta only applies to the
+// version it was made from, so a missed one is noticed instead of silently skipped.
+//
+// A holder gives a credential up with `prove_surrendered`: a proof of knowledge of the signature
+// revealing only its revocation id, bound to the issuer's nonce. `RevocationRegistry::surrender`
+// checks it and revokes the id. Nothing can show that the holder deleted the signature, a kept
+// copy still makes valid proofs; what surrendering gives is that the issuer now knows the id,
+// revokes it, and every later `verify_with_registry` of the credential fails.
+//
+// The check needs the id in the clear, so it fits `verify` and presentations revealing the id,
+// not a zero knowledge proof of non-revocation, and every use of a credential is linkable by its
+// id. Deltas aren't signed here, they have to come from the issuer over an authenticated
+// channel.
+
+use super::super::SignatureMessage;
+use super::keys::PublicKey;
+use super::pok_sig::{PoKOfSignature, PoKOfSignatureProof};
+use super::presentation::ProofNonce;
+use super::signature::Signature;
+use crate::errors::prelude::*;
+
+use amcl_wrapper::field_elem::FieldElement;
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+pub mod prelude {
+    pub use super::{prove_surrendered, RegistryDelta, RevocationRegistry, SurrenderProof};
+}
+
+const SURRENDER_DST: &[u8] = b"BBS+ surrender";
+
+/// Set of revoked ids, see the top of `revocation.rs`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RevocationRegistry {
+    /// Position of the revocation id among the messages of a credential
+    pub index: usize,
+    pub version: u64,
+    revoked: BTreeSet<SignatureMessage>,
+}
+
+/// Changes from registry `from_version` to `to_version`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RegistryDelta {
+    pub from_version: u64,
+    pub to_version: u64,
+    pub revoked: Vec<SignatureMessage>,
+    /// Ids revoked before and valid again, e.g. after a suspension
+    pub unrevoked: Vec<SignatureMessage>,
+}
+
+impl RevocationRegistry {
+    /// Empty registry at version 0 for credentials with the revocation id at `index`
+    pub fn new(index: usize) -> Self {
+        RevocationRegistry {
+            index,
+            version: 0,
+            revoked: BTreeSet::new(),
+        }
+    }
+
+    pub fn is_revoked(&self, id: &SignatureMessage) -> bool {
+        self.revoked.contains(id)
+    }
+
+    /// Number of revoked ids
+    pub fn len(&self) -> usize {
+        self.revoked.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.revoked.is_empty()
+    }
+
+    /// Issuer side: revoke `revoked`, reinstate `unrevoked` and move to the next version.
+    /// Returns the delta to publish.
+    pub fn update(
+        &mut self,
+        revoked: Vec<SignatureMessage>,
+        unrevoked: Vec<SignatureMessage>,
+    ) -> Result<RegistryDelta, BBSError> {
+        let delta = RegistryDelta {
+            from_version: self.version,
+            to_version: self.version + 1,
+            revoked,
+            unrevoked,
+        };
+        self.apply_delta(&delta)?;
+        Ok(delta)
+    }
+
+    /// Verifier side: bring the registry to `delta.to_version`. Fails, leaving the registry as
+    /// it was, if the delta was made from another version.
+    pub fn apply_delta(&mut self, delta: &RegistryDelta) -> Result<(), BBSError> {
+        if delta.from_version != self.version || delta.to_version <= delta.from_version {
+            return Err(BBSError::from_kind(BBSErrorKind::GeneralError {
+                msg: format!(
+                    "Delta from version {} to {} doesn't apply to registry version {}",
+                    delta.from_version, delta.to_version, self.version
+                ),
+            }));
+        }
+        for id in &delta.unrevoked {
+            self.revoked.remove(id);
+        }
+        self.revoked.extend(delta.revoked.iter().cloned());
+        self.version = delta.to_version;
+        Ok(())
+    }
+
+    /// Revoke the id of a credential its holder surrendered with `prove_surrendered`, see the
+    /// top of `revocation.rs`. Returns the delta to publish, fails if the proof doesn't hold.
+    pub fn surrender(
+        &mut self,
+        proof: &SurrenderProof,
+        verkey: &PublicKey,
+        nonce: &ProofNonce,
+    ) -> Result<RegistryDelta, BBSError> {
+        if !proof.verify(verkey, self, nonce)? {
+            return Err(BBSError::from_kind(BBSErrorKind::GeneralError {
+                msg: "Surrender proof doesn't verify".to_string(),
+            }));
+        }
+        self.update(vec![proof.id.clone()], vec![])
+    }
+}
+
+/// Proof of knowledge of a signature revealing only its revocation id, see the top of
+/// `revocation.rs`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SurrenderProof {
+    pub id: SignatureMessage,
+    pub proof: PoKOfSignatureProof,
+}
+
+fn surrender_challenge(
+    mut bytes: Vec<u8>,
+    index: usize,
+    id: &SignatureMessage,
+    nonce: &ProofNonce,
+) -> FieldElement {
+    let mut out = SURRENDER_DST.to_vec();
+    out.append(&mut bytes);
+    out.extend_from_slice(&(index as u32).to_be_bytes());
+    out.append(&mut id.to_bytes());
+    out.append(&mut nonce.to_bytes());
+    FieldElement::from_msg_hash(&out)
+}
+
+/// Surrender the credential `sig` over `messages`, its revocation id being the message at
+/// `registry.index`, to the issuer that sent `nonce`
+pub fn prove_surrendered(
+    sig: &Signature,
+    messages: &[SignatureMessage],
+    verkey: &PublicKey,
+    registry: &RevocationRegistry,
+    nonce: &ProofNonce,
+) -> Result<SurrenderProof, BBSError> {
+    let id = messages.get(registry.index).cloned().ok_or_else(|| {
+        BBSError::from_kind(BBSErrorKind::GeneralError {
+            msg: format!(
+                "Revocation id index {} should be less than {}",
+                registry.index,
+                messages.len()
+            ),
+        })
+    })?;
+    let mut revealed = HashSet::new();
+    revealed.insert(registry.index);
+    let pok = PoKOfSignature::init(sig, verkey, messages, None, revealed)?;
+    let challenge = surrender_challenge(pok.to_bytes(), registry.index, &id, nonce);
+    Ok(SurrenderProof {
+        id,
+        proof: pok.gen_proof(&challenge)?,
+    })
+}
+
+impl SurrenderProof {
+    /// Whether the holder knows a signature under `verkey` with the id at `registry.index`
+    pub fn verify(
+        &self,
+        verkey: &PublicKey,
+        registry: &RevocationRegistry,
+        nonce: &ProofNonce,
+    ) -> Result<bool, BBSError> {
+        let mut revealed = HashSet::new();
+        revealed.insert(registry.index);
+        let challenge = surrender_challenge(
+            self.proof.get_bytes_for_challenge(revealed, verkey),
+            registry.index,
+            &self.id,
+            nonce,
+        );
+        let mut revealed_msgs = HashMap::new();
+        revealed_msgs.insert(registry.index, self.id.clone());
+        self.proof.verify(verkey, revealed_msgs, &challenge)
+    }
+}
+
+impl Signature {
+    /// `verify` and that the revocation id in `messages` isn't revoked in `registry`
+    pub fn verify_with_registry(
+        &self,
+        messages: &[SignatureMessage],
+        verkey: &PublicKey,
+        registry: &RevocationRegistry,
+    ) -> Result<bool, BBSError> {
+        let id = messages.get(registry.index).ok_or_else(|| {
+            BBSError::from_kind(BBSErrorKind::GeneralError {
+                msg: format!(
+                    "Revocation id index {} should be less than {}",
+                    registry.index,
+                    messages.len()
+                ),
+            })
+        })?;
+        if registry.is_revoked(id) {
+            return Ok(false);
+        }
+        self.verify(messages, verkey)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
+    use signatures::bbs::keys::generate;
+
+    #[test]
+    fn revocation_by_delta() {
+        let messages = FieldElementVector::random(3);
+        let (verkey, signkey) = generate(3).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+
+        let mut issuer = RevocationRegistry::new(2);
+        let mut verifier = issuer.clone();
+        assert!(sig
+            .verify_with_registry(messages.as_slice(), &verkey, &verifier)
+            .unwrap());
+
+        let delta = issuer
+            .update(vec![messages[2].clone(), FieldElement::random()], vec![])
+            .unwrap();
+        let json = serde_json::to_string(&delta).unwrap();
+        verifier
+            .apply_delta(&serde_json::from_str(&json).unwrap())
+            .unwrap();
+        assert_eq!(verifier, issuer);
+        assert_eq!((verifier.version, verifier.len()), (1, 2));
+        assert!(!sig
+            .verify_with_registry(messages.as_slice(), &verkey, &verifier)
+            .unwrap());
+
+        // The same delta again, or one skipping a version, doesn't apply
+        assert!(verifier.apply_delta(&delta).is_err());
+        let missed = issuer.update(vec![], vec![]).unwrap();
+        let next = issuer.update(vec![], vec![messages[2].clone()]).unwrap();
+        assert!(verifier.apply_delta(&next).is_err());
+        assert_eq!(verifier.version, 1);
+        verifier.apply_delta(&missed).unwrap();
+        verifier.apply_delta(&next).unwrap();
+        assert!(sig
+            .verify_with_registry(messages.as_slice(), &verkey, &verifier)
+            .unwrap());
+
+        // A valid id doesn't save a bad signature
+        let mut changed = messages.clone();
+        changed[0] = FieldElement::random();
+        assert!(!sig
+            .verify_with_registry(changed.as_slice(), &verkey, &verifier)
+            .unwrap());
+        assert!(sig
+            .verify_with_registry(messages.as_slice(), &verkey, &RevocationRegistry::new(3))
+            .is_err());
+    }
+
+    #[test]
+    fn surrendered_credential() {
+        let (verkey, signkey) = generate(3).unwrap();
+        let surrendered = FieldElementVector::random(3);
+        let held = FieldElementVector::random(3);
+        let sig = Signature::new(surrendered.as_slice(), &signkey, &verkey).unwrap();
+        let held_sig = Signature::new(held.as_slice(), &signkey, &verkey).unwrap();
+        let mut registry = RevocationRegistry::new(1);
+        let nonce = ProofNonce::random();
+
+        let proof =
+            prove_surrendered(&sig, surrendered.as_slice(), &verkey, &registry, &nonce).unwrap();
+        assert_eq!(proof.id, surrendered[1]);
+        assert!(proof.verify(&verkey, &registry, &nonce).unwrap());
+        assert!(!proof
+            .verify(&verkey, &registry, &ProofNonce::random())
+            .unwrap());
+        assert!(registry
+            .surrender(&proof, &verkey, &ProofNonce::random())
+            .is_err());
+        assert_eq!(registry.version, 0);
+
+        let delta = registry.surrender(&proof, &verkey, &nonce).unwrap();
+        assert_eq!(delta.revoked, vec![surrendered[1].clone()]);
+        assert!(!sig
+            .verify_with_registry(surrendered.as_slice(), &verkey, &registry)
+            .unwrap());
+        // A credential still held is unaffected
+        assert!(!registry.is_revoked(&held[1]));
+        assert!(held_sig
+            .verify_with_registry(held.as_slice(), &verkey, &registry)
+            .unwrap());
+
+        // Claiming another id than the signed one fails
+        let mut forged =
+            prove_surrendered(&held_sig, held.as_slice(), &verkey, &registry, &nonce).unwrap();
+        forged.id = FieldElement::random();
+        assert!(!forged.verify(&verkey, &registry, &nonce).unwrap());
+    }
+}
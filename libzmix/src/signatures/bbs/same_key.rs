@@ -0,0 +1,149 @@
+// Proof that two signatures were made under one known key, for a holder showing a verifier that
+// two credentials come from the same issuer without showing the credentials.
+//
+// The proof is a proof of knowledge of each signature, every message hidden, with one
+// Fiat-Shamir challenge over both proofs, the key and the nonce, so the two proofs were made
+// together for this key and this verifier. The proofs randomize the signatures, nothing in them
+// links back to the credentials.
+//
+// The key is not hidden: BBS+ verification pairs with `w` and uses the bases, so the verifier
+// checks against the key itself and learns who the issuer is. Proving "same issuer" to a party
+// that never learns the key needs issuer hiding credentials, a different scheme this module
+// doesn't attempt.
+
+use super::super::SignatureMessage;
+use super::keys::PublicKey;
+use super::pok_sig::{PoKOfSignature, PoKOfSignatureProof};
+use super::presentation::ProofNonce;
+use super::signature::Signature;
+use crate::errors::prelude::*;
+
+use amcl_wrapper::field_elem::FieldElement;
+
+use std::collections::{HashMap, HashSet};
+
+pub mod prelude {
+    pub use super::{prove_same_key, SameKeyProof};
+}
+
+const CHALLENGE_DST: &[u8] = b"BBS+ same key challenge";
+
+/// Proof that two signatures share a key, see the top of `same_key.rs`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SameKeyProof {
+    pub proof_1: PoKOfSignatureProof,
+    pub proof_2: PoKOfSignatureProof,
+}
+
+fn challenge(
+    verkey: &PublicKey,
+    mut bytes_1: Vec<u8>,
+    mut bytes_2: Vec<u8>,
+    nonce: &ProofNonce,
+) -> FieldElement {
+    let mut bytes = CHALLENGE_DST.to_vec();
+    bytes.append(&mut verkey.to_bytes());
+    bytes.append(&mut bytes_1);
+    bytes.append(&mut bytes_2);
+    bytes.append(&mut nonce.to_bytes());
+    FieldElement::from_msg_hash(&bytes)
+}
+
+/// Prove that `sig_1` over `messages_1` and `sig_2` over `messages_2` both verify under
+/// `verkey`, hiding every message
+pub fn prove_same_key(
+    sig_1: &Signature,
+    messages_1: &[SignatureMessage],
+    sig_2: &Signature,
+    messages_2: &[SignatureMessage],
+    verkey: &PublicKey,
+    nonce: &ProofNonce,
+) -> Result<SameKeyProof, BBSError> {
+    let pok_1 = PoKOfSignature::init(sig_1, verkey, messages_1, None, HashSet::new())?;
+    let pok_2 = PoKOfSignature::init(sig_2, verkey, messages_2, None, HashSet::new())?;
+    let c = challenge(verkey, pok_1.to_bytes(), pok_2.to_bytes(), nonce);
+    Ok(SameKeyProof {
+        proof_1: pok_1.gen_proof(&c)?,
+        proof_2: pok_2.gen_proof(&c)?,
+    })
+}
+
+impl SameKeyProof {
+    /// Whether both signatures verify under `verkey`, proven together for `nonce`
+    pub fn verify(&self, verkey: &PublicKey, nonce: &ProofNonce) -> Result<bool, BBSError> {
+        let c = challenge(
+            verkey,
+            self.proof_1.get_bytes_for_challenge(HashSet::new(), verkey),
+            self.proof_2.get_bytes_for_challenge(HashSet::new(), verkey),
+            nonce,
+        );
+        Ok(self.proof_1.verify(verkey, HashMap::new(), &c)?
+            && self.proof_2.verify(verkey, HashMap::new(), &c)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amcl_wrapper::field_elem::FieldElementVector;
+    use signatures::bbs::keys::generate;
+
+    #[test]
+    fn same_key_proof() {
+        let (verkey, signkey) = generate(3).unwrap();
+        let (other_verkey, other_signkey) = generate(3).unwrap();
+        let messages_1 = FieldElementVector::random(3);
+        let messages_2 = FieldElementVector::random(3);
+        let sig_1 = Signature::new(messages_1.as_slice(), &signkey, &verkey).unwrap();
+        let sig_2 = Signature::new(messages_2.as_slice(), &signkey, &verkey).unwrap();
+        let nonce = ProofNonce::random();
+
+        let proof = prove_same_key(
+            &sig_1,
+            messages_1.as_slice(),
+            &sig_2,
+            messages_2.as_slice(),
+            &verkey,
+            &nonce,
+        )
+        .unwrap();
+        assert!(proof.verify(&verkey, &nonce).unwrap());
+        assert!(!proof.verify(&verkey, &ProofNonce::random()).unwrap());
+        assert!(!proof.verify(&other_verkey, &nonce).unwrap());
+
+        // A signature of another issuer doesn't pass for one of the first
+        let foreign = Signature::new(messages_2.as_slice(), &other_signkey, &other_verkey).unwrap();
+        let proof = prove_same_key(
+            &sig_1,
+            messages_1.as_slice(),
+            &foreign,
+            messages_2.as_slice(),
+            &verkey,
+            &nonce,
+        )
+        .unwrap();
+        assert!(!proof.verify(&verkey, &nonce).unwrap());
+
+        // Nor does swapping in a proof made for the other key
+        let other_proof = prove_same_key(
+            &foreign,
+            messages_2.as_slice(),
+            &foreign,
+            messages_2.as_slice(),
+            &other_verkey,
+            &nonce,
+        )
+        .unwrap();
+        let mut mixed = prove_same_key(
+            &sig_1,
+            messages_1.as_slice(),
+            &sig_2,
+            messages_2.as_slice(),
+            &verkey,
+            &nonce,
+        )
+        .unwrap();
+        mixed.proof_2 = other_proof.proof_2;
+        assert!(!mixed.verify(&verkey, &nonce).unwrap());
+    }
+}
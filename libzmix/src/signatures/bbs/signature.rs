@@ -1,6 +1,12 @@
 use super::super::SignatureBlinding;
 use super::super::SignatureMessage;
-use super::keys::{PublicKey, SecretKey};
+use super::keys::{
+    read_g1, write_g1, Params, PreparedSecretKey, PublicKey, SecretKey, G1_COMPRESSED_SIZE,
+};
+use super::messages::PreparedMessages;
+#[cfg(any(test, feature = "metrics"))]
+use super::metrics;
+use super::pairing::PairingAccumulator;
 use crate::errors::prelude::*;
 use amcl_wrapper::{
     constants::{GroupG1_SIZE, MODBYTES},
@@ -8,22 +14,70 @@ use amcl_wrapper::{
     field_elem::FieldElement,
     group_elem::{GroupElement, GroupElementVector},
     group_elem_g1::G1,
-    group_elem_g2::G2,
+    group_elem_g2::{G2LookupTable, G2},
 };
 
 use amcl_wrapper::field_elem::FieldElementVector;
 use amcl_wrapper::group_elem_g1::G1Vector;
 
+use std::collections::BTreeMap;
+use subtle::{Choice, ConstantTimeEq};
+use zeroize::Zeroizing;
+
 macro_rules! check_verkey_message {
     ($statment:expr, $count1:expr, $count2:expr) => {
         if $statment {
-            return Err(BBSError::from_kind(
-                BBSErrorKind::SigningErrorMessageCountMismatch($count1, $count2),
-            ));
+            return Err(BBSError::from_kind(BBSErrorKind::message_count(
+                $count1, $count2,
+            )));
         }
     };
 }
 
+// Fails compilation when `$statement` is false
+macro_rules! const_assert {
+    ($statement:expr) => {
+        const _: [(); 0 - !($statement) as usize] = [];
+    };
+}
+
+/// Byte size of a serialized signature, `a` followed by `e` and `s`
+pub const SIGNATURE_SIZE: usize = 193;
+
+/// Byte size of a signature from `to_bytes_compressed`
+pub const SIGNATURE_COMPRESSED_SIZE: usize = G1_COMPRESSED_SIZE + MODBYTES * 2;
+
+// Catch layout drift if the amcl constants ever change
+const_assert!(SIGNATURE_SIZE == GroupG1_SIZE + MODBYTES * 2);
+
+/// Messages `Signature::verify_lazy` holds at a time
+pub const LAZY_CHUNK_SIZE: usize = 64;
+
+const NONCE_RESPONSE_DST: &[u8] = b"BBS+ nonce response";
+const DETERMINISTIC_SIGNING_DST: &[u8] = b"BBS+ deterministic signing";
+
+/// Group operations of one `Signature::verify`, counted the way the `metrics` feature counts
+/// them. Scalar multiplications in `G1` and `G2` aren't told apart, nor are additions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VerifyCost {
+    pub scalar_muls: usize,
+    pub additions: usize,
+    pub pairings: usize,
+}
+
+/// Cost of `Signature::verify` for `message_count` messages: `b` takes a multi-scalar
+/// multiplication over `g1`, `h0` and every base, `g2^e * w` one more scalar multiplication
+/// and addition, then a 2-pairing. An estimate for capacity planning, actual time also depends
+/// on the sizes of the scalars. Kept by hand like the `metrics::record` calls, the test of
+/// `metrics.rs` checks the two agree.
+pub fn cost_model(message_count: usize) -> VerifyCost {
+    VerifyCost {
+        scalar_muls: message_count + 3,
+        additions: message_count + 3,
+        pairings: 2,
+    }
+}
+
 /// A BBS+ signature.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Signature {
@@ -34,45 +88,140 @@ pub struct Signature {
 
 // https://eprint.iacr.org/2016/663.pdf Section 4.3
 impl Signature {
+    /// Size of the byte representation returned by `to_bytes`
+    pub fn size(&self) -> usize {
+        SIGNATURE_SIZE
+    }
+
+    /// `a` uncompressed, `e` and `s`, `SIGNATURE_SIZE` bytes. Stays uncompressed although
+    /// tolerant decoding was asked for with a compressed default: `SIGNATURE_SIZE`,
+    /// `from_bytes_prefix`, `IssuanceRecord` and stored signatures all depend on this layout,
+    /// so changing it would break every reader of existing bytes while `from_bytes` already
+    /// takes both. Use `to_bytes_compressed` where size matters.
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut out = Vec::with_capacity(GroupG1_SIZE + MODBYTES * 2);
+        let mut out = Vec::with_capacity(SIGNATURE_SIZE);
         out.extend_from_slice(self.a.to_bytes().as_slice());
         out.extend_from_slice(self.e.to_bytes().as_slice());
         out.extend_from_slice(self.s.to_bytes().as_slice());
         out
     }
 
+    /// Reads both `to_bytes` and `to_bytes_compressed`, told apart by their sizes.
+    /// Fails with `InvalidSignaturePoint` if `a` is the identity or outside the prime order
+    /// subgroup and with `SignatureValueIncorrectSize` if `e` or `s` is zero.
     pub fn from_bytes(data: &[u8]) -> Result<Signature, BBSError> {
-        let expected = GroupG1_SIZE + MODBYTES * 2;
-        if data.len() != expected {
+        if data.len() == SIGNATURE_COMPRESSED_SIZE {
+            return Signature::from_bytes_compressed(data);
+        }
+        if data.len() != SIGNATURE_SIZE {
             return Err(BBSError::from_kind(BBSErrorKind::SignatureIncorrectSize(
                 data.len(),
             )));
         }
-        let mut index = 0;
         let a = G1::from_bytes(&data[0..GroupG1_SIZE])
             .map_err(|_| BBSError::from_kind(BBSErrorKind::SignatureValueIncorrectSize))?;
-        index += GroupG1_SIZE;
-        let e = FieldElement::from_bytes(&data[index..(index + MODBYTES)])
+        Signature::from_a_and_scalars(a, &data[GroupG1_SIZE..])
+    }
+
+    /// Same as `to_bytes` with `a` compressed to its x coordinate and a prefix byte holding
+    /// the sign of y, `SIGNATURE_COMPRESSED_SIZE` bytes instead of `SIGNATURE_SIZE`.
+    /// Decompressing costs a square root in `from_bytes_compressed`.
+    pub fn to_bytes_compressed(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(SIGNATURE_COMPRESSED_SIZE);
+        write_g1(&self.a, true, &mut out);
+        out.extend_from_slice(self.e.to_bytes().as_slice());
+        out.extend_from_slice(self.s.to_bytes().as_slice());
+        out
+    }
+
+    /// Signature of `to_bytes_compressed`, with the same checks as `from_bytes`
+    pub fn from_bytes_compressed(data: &[u8]) -> Result<Signature, BBSError> {
+        if data.len() != SIGNATURE_COMPRESSED_SIZE || !(data[0] == 0x02 || data[0] == 0x03) {
+            return Err(BBSError::from_kind(BBSErrorKind::SignatureIncorrectSize(
+                data.len(),
+            )));
+        }
+        let mut index = 0;
+        let a = read_g1(data, &mut index)
+            .map_err(|_| BBSError::from_kind(BBSErrorKind::SignatureValueIncorrectSize))?;
+        Signature::from_a_and_scalars(a, &data[index..])
+    }
+
+    // `e` and `s` from the 2 * MODBYTES of `data` following `a`
+    fn from_a_and_scalars(a: G1, data: &[u8]) -> Result<Signature, BBSError> {
+        let e = FieldElement::from_bytes(&data[..MODBYTES])
             .map_err(|_| BBSError::from_kind(BBSErrorKind::SignatureValueIncorrectSize))?;
-        index += MODBYTES;
-        let s = FieldElement::from_bytes(&data[index..(index + MODBYTES)])
+        let s = FieldElement::from_bytes(&data[MODBYTES..])
             .map_err(|_| BBSError::from_kind(BBSErrorKind::SignatureValueIncorrectSize))?;
+        // `verify` would reject these anyway, refusing them here keeps them out of any
+        // other code path one is carried into
+        if a.is_identity() || !a.has_correct_order() {
+            return Err(BBSError::from_kind(BBSErrorKind::InvalidSignaturePoint));
+        }
+        if e.is_zero() || s.is_zero() {
+            return Err(BBSError::from_kind(
+                BBSErrorKind::SignatureValueIncorrectSize,
+            ));
+        }
         Ok(Signature { a, e, s })
     }
 
+    /// Parse a signature from the front of `data` which may hold more bytes after it.
+    /// Returns the signature and the number of bytes it took up.
+    /// Like `from_bytes` `a` may be compressed, which its first byte tells.
+    pub fn from_bytes_prefix(data: &[u8]) -> Result<(Signature, usize), BBSError> {
+        let size = match data.first() {
+            Some(0x02) | Some(0x03) => SIGNATURE_COMPRESSED_SIZE,
+            _ => SIGNATURE_SIZE,
+        };
+        if data.len() < size {
+            return Err(BBSError::from_kind(BBSErrorKind::SignatureIncorrectSize(
+                data.len(),
+            )));
+        }
+        let signature = Signature::from_bytes(&data[..size])?;
+        Ok((signature, size))
+    }
+
     // No committed messages, All messages known to signer.
     pub fn new(
         messages: &[SignatureMessage],
         signkey: &SecretKey,
         verkey: &PublicKey,
     ) -> Result<Self, BBSError> {
-        check_verkey_message!(messages.is_empty(), verkey.message_count(), messages.len());
+        check_verkey_message!(
+            messages.len() != verkey.message_count(),
+            verkey.message_count(),
+            messages.len()
+        );
         Signature::new_with_committed_messages(&G1::new(), messages, signkey, verkey)
     }
 
+    /// Same as `new` with the generators of `params`, verify with `verify_with_params`
+    pub fn new_with_params(
+        messages: &[SignatureMessage],
+        signkey: &SecretKey,
+        verkey: &PublicKey,
+        params: &Params,
+    ) -> Result<Self, BBSError> {
+        check_verkey_message!(
+            messages.len() != verkey.message_count(),
+            verkey.message_count(),
+            messages.len()
+        );
+        let (e, exp) = random_e(signkey);
+        let s = FieldElement::random();
+        let b = compute_b_const_time_with_params(params, &G1::new(), verkey, messages, &s, 0);
+        #[cfg(any(test, feature = "metrics"))]
+        metrics::record(1, 0, 0);
+        let a = b * exp;
+        Ok(Signature { a, e, s })
+    }
+
     // 1 or more messages are captured in `commitment`. The remaining known messages are in `messages`.
-    // This is a blind signature.
+    // This is a blind signature. More known messages than the key has bases leave a negative
+    // number, `verkey.message_count() - messages.len()`, for the commitment and fail with
+    // `TooManyMessages`.
     pub fn new_with_committed_messages(
         commitment: &G1,
         messages: &[SignatureMessage],
@@ -84,7 +233,7 @@ impl Signature {
             verkey.message_count(),
             messages.len()
         );
-        let e = FieldElement::random();
+        let (e, exp) = random_e(signkey);
         let s = FieldElement::random();
         let b = compute_b_const_time(
             commitment,
@@ -93,9 +242,152 @@ impl Signature {
             &s,
             verkey.message_count() - messages.len(),
         );
-        let mut exp = signkey.clone();
-        exp += &e;
-        exp.inverse_mut();
+        #[cfg(any(test, feature = "metrics"))]
+        metrics::record(1, 0, 0);
+        let a = b * exp;
+        Ok(Signature { a, e, s })
+    }
+
+    /// Same as `new` but `e` and `s` are derived from `signkey` and `messages`, in the manner of
+    /// RFC 6979, instead of drawn from the system RNG. The same inputs always give the same
+    /// signature, which is what test vectors need. Signing the same messages twice gives the
+    /// same signature, so a holder can't tell two issuances apart.
+    pub fn new_deterministic(
+        messages: &[SignatureMessage],
+        signkey: &SecretKey,
+        verkey: &PublicKey,
+    ) -> Result<Self, BBSError> {
+        Signature::new_deterministic_tagged(messages, signkey, verkey, &[])
+    }
+
+    /// Same as `new_deterministic` with `issuer_tag` mixed into the derivation of `e` and `s`,
+    /// so issuers sharing test inputs can tell their signatures apart when debugging. The tag
+    /// adds no security, an empty tag gives the signature of `new_deterministic`.
+    pub fn new_deterministic_tagged(
+        messages: &[SignatureMessage],
+        signkey: &SecretKey,
+        verkey: &PublicKey,
+        issuer_tag: &[u8],
+    ) -> Result<Self, BBSError> {
+        check_verkey_message!(
+            messages.len() != verkey.message_count(),
+            verkey.message_count(),
+            messages.len()
+        );
+        // The buffers hold the secret key so they're allocated once at their final size and
+        // zeroed when dropped
+        let seed_len = DETERMINISTIC_SIGNING_DST.len()
+            + MODBYTES * (messages.len() + 1)
+            + 8
+            + issuer_tag.len();
+        let mut seed = Zeroizing::new(Vec::with_capacity(seed_len));
+        seed.extend_from_slice(DETERMINISTIC_SIGNING_DST);
+        seed.extend_from_slice(&Zeroizing::new(signkey.to_bytes()));
+        seed.extend_from_slice(&(messages.len() as u32).to_be_bytes());
+        for m in messages {
+            seed.append(&mut m.to_bytes());
+        }
+        if !issuer_tag.is_empty() {
+            seed.extend_from_slice(&(issuer_tag.len() as u32).to_be_bytes());
+            seed.extend_from_slice(issuer_tag);
+        }
+        let derive = |tag: u8, counter: u32| {
+            let mut bytes = Zeroizing::new(Vec::with_capacity(seed_len + 5));
+            bytes.extend_from_slice(&seed);
+            bytes.push(tag);
+            bytes.extend_from_slice(&counter.to_be_bytes());
+            FieldElement::from_msg_hash(&bytes)
+        };
+        // Like `random_e`, derive again with the next counter in the negligible case x + e = 0
+        let mut counter = 0u32;
+        let (e, exp) = loop {
+            let e = derive(b'e', counter);
+            let mut exp = signkey + &e;
+            if !bool::from(ct_is_zero(&exp)) {
+                exp.inverse_mut();
+                break (e, exp);
+            }
+            counter += 1;
+        };
+        let s = derive(b's', 0);
+        let b = compute_b_const_time(&G1::new(), verkey, messages, &s, 0);
+        let a = b * exp;
+        Ok(Signature { a, e, s })
+    }
+
+    /// Same as `new` for all messages taking `e` and its inverse from the table of `signkey`
+    /// so most signatures need no field inversion.
+    pub fn new_prepared(
+        messages: &[SignatureMessage],
+        signkey: &mut PreparedSecretKey,
+        verkey: &PublicKey,
+    ) -> Result<Self, BBSError> {
+        check_verkey_message!(
+            messages.len() != verkey.message_count(),
+            verkey.message_count(),
+            messages.len()
+        );
+        let s = FieldElement::random();
+        let b = compute_b_const_time(&G1::new(), verkey, messages, &s, 0);
+        let (e, exp) = signkey.next_inverse();
+        let a = b * exp;
+        Ok(Signature { a, e, s })
+    }
+
+    /// Sign every message set in `message_sets`, e.g. all credentials issued to one holder at
+    /// once. Every signature gets its own random `e` and `s`, only the `1/(x + e)` of all of
+    /// them are computed with a single batch inversion, see `PreparedSecretKey`. Nothing is
+    /// signed unless every set has the key's number of messages.
+    pub fn sign_batch(
+        message_sets: &[Vec<SignatureMessage>],
+        signkey: &SecretKey,
+        verkey: &PublicKey,
+    ) -> Result<Vec<Self>, BBSError> {
+        for messages in message_sets {
+            check_verkey_message!(
+                messages.len() != verkey.message_count(),
+                verkey.message_count(),
+                messages.len()
+            );
+        }
+        let mut prepared = PreparedSecretKey::new(signkey, message_sets.len());
+        message_sets
+            .iter()
+            .map(|messages| Signature::new_prepared(messages.as_slice(), &mut prepared, verkey))
+            .collect()
+    }
+
+    // Same as `new_with_committed_messages` except that the known messages are keyed by their
+    // index and can be anywhere. Every index not present in `messages` must be in `commitment`.
+    pub fn new_with_committed_messages_at(
+        commitment: &G1,
+        messages: &BTreeMap<usize, SignatureMessage>,
+        signkey: &SecretKey,
+        verkey: &PublicKey,
+    ) -> Result<Self, BBSError> {
+        check_verkey_message!(
+            messages.len() > verkey.message_count(),
+            verkey.message_count(),
+            messages.len()
+        );
+        if let Some(i) = messages.keys().find(|i| **i >= verkey.message_count()) {
+            return Err(BBSError::from_kind(BBSErrorKind::GeneralError {
+                msg: format!("Index {} should be less than {}", i, verkey.message_count()),
+            }));
+        }
+        let (e, exp) = random_e(signkey);
+        let s = FieldElement::random();
+        let mut points = G1Vector::with_capacity(messages.len() + 2);
+        let mut scalars = FieldElementVector::with_capacity(messages.len() + 2);
+        points.push(G1::generator());
+        scalars.push(FieldElement::one());
+        points.push(verkey.h0.clone());
+        scalars.push(s.clone());
+        for (i, m) in messages {
+            points.push(verkey.h[*i].clone());
+            scalars.push(m.clone());
+        }
+        let b = commitment + points.multi_scalar_mul_const_time(&scalars).unwrap();
         let a = b * exp;
         Ok(Signature { a, e, s })
     }
@@ -114,86 +406,1100 @@ impl Signature {
         }
     }
 
+    /// Same as `get_unblinded_signature` then checks the result against `messages`, all of
+    /// them, hidden and known, in order. Fails with `InvalidBlindSignature` if it doesn't
+    /// verify: the signer used other bases or messages, or `messages` or `blinding` aren't the
+    /// ones committed to, the two can't be told apart from here.
+    pub fn get_unblinded_signature_checked(
+        &self,
+        blinding: &SignatureBlinding,
+        messages: &[SignatureMessage],
+        verkey: &PublicKey,
+    ) -> Result<Self, BBSError> {
+        let sig = self.get_unblinded_signature(blinding);
+        if sig.verify(messages, verkey)? {
+            Ok(sig)
+        } else {
+            Err(BBSError::from_kind(BBSErrorKind::InvalidBlindSignature))
+        }
+    }
+
+    /// Drop `s`, see `ThinSignature`
+    pub fn thin(&self) -> ThinSignature {
+        ThinSignature {
+            a: self.a.clone(),
+            e: self.e.clone(),
+        }
+    }
+
+    /// Pedersen commitment `a + h0 * blinding` to `a` for an outer proof that keeps `a` hidden.
+    /// With a uniformly random `blinding` it reveals nothing about `a`. Opening it to a point of
+    /// the prover's choice needs the discrete log of that point to `h0`, but any other `blinding`
+    /// opens it to some point, so the outer proof must also show that the opened point is part
+    /// of a valid signature, as `PoKOfSignature` does for its randomized `a_prime`, and
+    /// `blinding` must never be reused across commitments.
+    pub fn commit_to_a(&self, verkey: &PublicKey, blinding: &FieldElement) -> G1 {
+        &self.a + &verkey.h0 * blinding
+    }
+
+    /// Whether `commitment` is `commit_to_a` of this signature's `a` with `blinding`
+    pub fn opens_a_commitment(
+        &self,
+        commitment: &G1,
+        verkey: &PublicKey,
+        blinding: &FieldElement,
+    ) -> bool {
+        self.commit_to_a(verkey, blinding) == *commitment
+    }
+
+    /// Whether both are forms of one issued signature, differing at most in `s`, e.g. the
+    /// blinded signature and its unblinding. Compares `a` and `e` only: a signer never picks the
+    /// same `e` for two signatures, randomly or with `new_deterministic` over other messages,
+    /// so equal `a` and `e` mean one issuance. It says nothing about validity, of signatures
+    /// equal under this at most one verifies over given messages since `s` is part of `b`.
+    pub fn eq_ignoring_blinding(&self, other: &Signature) -> bool {
+        self.a == other.a && self.e == other.e
+    }
+
+    /// Rewrite a signature stored under `old_scheme` so it is valid under `new_scheme`, see
+    /// `BlindingScheme`. Both schemes differ only in the sign of `s`, so this works on blinded
+    /// signatures too: unblinding the result the way `new_scheme` does gives the same signature
+    /// as unblinding the original the way `old_scheme` does. `a` and `e` are untouched.
+    pub fn migrate_blinding(&self, old_scheme: BlindingScheme, new_scheme: BlindingScheme) -> Self {
+        let s = if old_scheme == new_scheme {
+            self.s.clone()
+        } else {
+            self.s.negation()
+        };
+        Signature {
+            a: self.a.clone(),
+            s,
+            e: self.e.clone(),
+        }
+    }
+
     // Verify a signature. During proof of knowledge also, this method is used after extending the verkey
     pub fn verify(
         &self,
         messages: &[SignatureMessage],
         verkey: &PublicKey,
+    ) -> Result<bool, BBSError> {
+        self.verify_with_params(messages, verkey, &Params::default())
+    }
+
+    /// Same as `verify` for a key of a deployment with its own generators, see `Params`
+    pub fn verify_with_params(
+        &self,
+        messages: &[SignatureMessage],
+        verkey: &PublicKey,
+        params: &Params,
     ) -> Result<bool, BBSError> {
         check_verkey_message!(
             messages.len() != verkey.message_count(),
             verkey.message_count(),
             messages.len()
         );
-        let b = compute_b_var_time(&G1::new(), verkey, messages, &self.s, 0);
+        let b = compute_b_var_time_with_params(params, &G1::new(), verkey, messages, &self.s, 0);
+        let a = (&params.g2 * &self.e) + &verkey.w;
+        #[cfg(any(test, feature = "metrics"))]
+        metrics::record(1, 1, 2);
+        Ok(GT::ate_2_pairing(&self.a, &a, &(-&b), &params.g2).is_one())
+    }
+
+    /// Same result as `verify` for messages pulled one by one from `fetch`, called with every
+    /// index below `count` exactly once and in order, e.g. reading from storage. At most
+    /// `LAZY_CHUNK_SIZE` messages are held at a time, `b` is summed from one multi-scalar
+    /// multiplication per chunk, which can be slower than the single one of `verify`.
+    pub fn verify_lazy<F>(
+        &self,
+        count: usize,
+        fetch: F,
+        verkey: &PublicKey,
+    ) -> Result<bool, BBSError>
+    where
+        F: Fn(usize) -> SignatureMessage,
+    {
+        check_verkey_message!(
+            count != verkey.message_count(),
+            verkey.message_count(),
+            count
+        );
+        let mut b = G1::generator() + &verkey.h0 * &self.s;
+        #[cfg(any(test, feature = "metrics"))]
+        metrics::record(2, 2, 0);
+        let mut start = 0;
+        while start < count {
+            let end = (start + LAZY_CHUNK_SIZE).min(count);
+            let mut points = G1Vector::with_capacity(end - start);
+            let mut scalars = FieldElementVector::with_capacity(end - start);
+            for i in start..end {
+                points.push(verkey.h[i].clone());
+                scalars.push(fetch(i));
+            }
+            b += points.multi_scalar_mul_var_time(&scalars).unwrap();
+            #[cfg(any(test, feature = "metrics"))]
+            metrics::record(points.len(), points.len(), 0);
+            start = end;
+        }
         let a = (&G2::generator() * &self.e) + &verkey.w;
+        #[cfg(any(test, feature = "metrics"))]
+        metrics::record(1, 1, 2);
         Ok(GT::ate_2_pairing(&self.a, &a, &(-&b), &G2::generator()).is_one())
     }
-}
-
-fn prep_vec_for_b(
-    public_key: &PublicKey,
-    messages: &[FieldElement],
-    blinding_factor: &FieldElement,
-    offset: usize,
-) -> (G1Vector, FieldElementVector) {
-    let mut points = G1Vector::with_capacity(messages.len() + 2);
-    let mut scalars = FieldElementVector::with_capacity(messages.len() + 2);
-    // XXX: g1 should not be a generator but a setup param
-    // prep for g1*h0^blinding_factor*hi^mi.....
-    points.push(G1::generator());
-    scalars.push(FieldElement::one());
-    points.push(public_key.h0.clone());
-    scalars.push(blinding_factor.clone());
 
-    for i in 0..messages.len() {
-        points.push(public_key.h[offset + i].clone());
-        scalars.push(messages[i].clone());
+    /// Same result as `verify` building `b` in the buffers of `scratch`, see `VerifyScratch`
+    pub fn verify_in(
+        &self,
+        messages: &[SignatureMessage],
+        verkey: &PublicKey,
+        scratch: &mut VerifyScratch,
+    ) -> Result<bool, BBSError> {
+        check_verkey_message!(
+            messages.len() != verkey.message_count(),
+            verkey.message_count(),
+            messages.len()
+        );
+        scratch.clear();
+        scratch.points.push(G1::generator());
+        scratch.scalars.push(FieldElement::one());
+        scratch.points.push(verkey.h0.clone());
+        scratch.scalars.push(self.s.clone());
+        for (h, m) in verkey.h.iter().zip(messages) {
+            scratch.points.push(h.clone());
+            scratch.scalars.push(m.clone());
+        }
+        let b = scratch
+            .points
+            .multi_scalar_mul_var_time(&scratch.scalars)
+            .unwrap();
+        let a = (&G2::generator() * &self.e) + &verkey.w;
+        #[cfg(any(test, feature = "metrics"))]
+        metrics::record(scratch.points.len() + 1, scratch.points.len() + 1, 2);
+        Ok(GT::ate_2_pairing(&self.a, &a, &(-&b), &G2::generator()).is_one())
     }
-    (points, scalars)
-}
-
-/// Helper function for computing the `b` value. Internal helper function
-pub fn compute_b_const_time(
-    starting_value: &G1,
-    public_key: &PublicKey,
-    messages: &[FieldElement],
-    blinding_factor: &FieldElement,
-    offset: usize,
-) -> G1 {
-    let (points, scalars) = prep_vec_for_b(public_key, messages, blinding_factor, offset);
-    starting_value + points.multi_scalar_mul_const_time(&scalars).unwrap()
-}
 
-/// Helper function for computing the `b` value. Internal helper function
-pub fn compute_b_var_time(
-    starting_value: &G1,
-    public_key: &PublicKey,
-    messages: &[FieldElement],
-    blinding_factor: &FieldElement,
-    offset: usize,
-) -> G1 {
-    let (points, scalars) = prep_vec_for_b(public_key, messages, blinding_factor, offset);
-    starting_value + points.multi_scalar_mul_var_time(&scalars).unwrap()
-}
+    /// Same result as `verify` computing `g2^e` with the table of `verifier`
+    pub fn verify_prepared(
+        &self,
+        messages: &[SignatureMessage],
+        verifier: &PreparedVerifier,
+    ) -> Result<bool, BBSError> {
+        let verkey = &verifier.verkey;
+        check_verkey_message!(
+            messages.len() != verkey.message_count(),
+            verkey.message_count(),
+            messages.len()
+        );
+        let b = compute_b_var_time(&G1::new(), verkey, messages, &self.s, 0);
+        let a = verifier.g2_mul(&self.e) + &verkey.w;
+        #[cfg(any(test, feature = "metrics"))]
+        metrics::record(1, 1, 2);
+        Ok(GT::ate_2_pairing(&self.a, &a, &(-&b), &G2::generator()).is_one())
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::super::keys::generate;
-    use super::super::pok_sig::ProverCommittingG1;
-    use super::*;
+    /// Response to a verifier's `nonce` for `verify_with_nonce`, a hash of the nonce and the
+    /// whole signature.
+    pub fn nonce_response(&self, nonce: &[u8]) -> FieldElement {
+        let mut bytes = NONCE_RESPONSE_DST.to_vec();
+        bytes.append(&mut self.to_bytes());
+        bytes.extend_from_slice(nonce);
+        FieldElement::from_msg_hash(&bytes)
+    }
 
-    #[test]
-    fn signature_serialization() {
-        let sig = Signature {
-            a: G1::random(),
-            e: FieldElement::random(),
-            s: FieldElement::random(),
-        };
-        let bytes = sig.to_bytes();
-        assert_eq!(bytes.len(), GroupG1_SIZE + MODBYTES * 2);
-        let sig_2 = Signature::from_bytes(bytes.as_slice()).unwrap();
-        assert_eq!(sig, sig_2);
+    /// `verify` and check that `response` is `nonce_response(nonce)`. This only shows that the
+    /// response was made after the verifier picked `nonce`, not who made it: the response
+    /// needs nothing but the signature, so anyone who saw the signature before, e.g. in an
+    /// earlier presentation, can answer too. Fine for trusted holders over an authenticated
+    /// channel, otherwise use a proof of knowledge, see `Presentation`. This is also the
+    /// freshness check of a session with such a holder: every challenge of the server is a new
+    /// `nonce`, answered with `nonce_response` and checked here, linkable across the session.
+    pub fn verify_with_nonce(
+        &self,
+        messages: &[SignatureMessage],
+        verkey: &PublicKey,
+        nonce: &[u8],
+        response: &FieldElement,
+    ) -> Result<bool, BBSError> {
+        if self.nonce_response(nonce) != *response {
+            return Ok(false);
+        }
+        self.verify(messages, verkey)
+    }
+
+    /// Same as `verify` for messages held by reference, e.g. shared between several message
+    /// sets, without cloning them
+    pub fn verify_refs(
+        &self,
+        messages: &[&SignatureMessage],
+        verkey: &PublicKey,
+    ) -> Result<bool, BBSError> {
+        check_verkey_message!(
+            messages.len() != verkey.message_count(),
+            verkey.message_count(),
+            messages.len()
+        );
+        let b = compute_b_var_time_refs(&G1::new(), verkey, messages, &self.s, 0);
+        let a = (&G2::generator() * &self.e) + &verkey.w;
+        #[cfg(any(test, feature = "metrics"))]
+        metrics::record(1, 1, 2);
+        Ok(GT::ate_2_pairing(&self.a, &a, &(-&b), &G2::generator()).is_one())
+    }
+
+    /// Same as `verify` with `b` computed in constant time like during signing. `verify` takes
+    /// the variable time path since a verifier normally learns the messages anyway. Use this
+    /// one when the messages are secret to whoever can observe the verifier, e.g. a holder
+    /// checking its credential on a shared machine. See `benches/bbs.rs` for the cost.
+    pub fn verify_const_time(
+        &self,
+        messages: &[SignatureMessage],
+        verkey: &PublicKey,
+    ) -> Result<bool, BBSError> {
+        check_verkey_message!(
+            messages.len() != verkey.message_count(),
+            verkey.message_count(),
+            messages.len()
+        );
+        let b = compute_b_const_time(&G1::new(), verkey, messages, &self.s, 0);
+        let a = (&G2::generator() * &self.e) + &verkey.w;
+        #[cfg(any(test, feature = "metrics"))]
+        metrics::record(1, 1, 2);
+        Ok(GT::ate_2_pairing(&self.a, &a, &(-&b), &G2::generator()).is_one())
+    }
+
+    /// `verify` under a name that says which path it takes, for code that picks between this
+    /// and `verify_const_time`. `verify` already computes `b` with `compute_b_var_time`, so
+    /// this is no faster. Timing depends on the messages: don't use it on messages that must
+    /// stay secret from whoever can observe the verifier, use `verify_const_time` then.
+    pub fn verify_var_time(
+        &self,
+        messages: &[SignatureMessage],
+        verkey: &PublicKey,
+    ) -> Result<bool, BBSError> {
+        self.verify(messages, verkey)
+    }
+
+    /// Same as `verify` with the pairing computed the way `level` selects, see `OptLevel`.
+    /// Every level accepts exactly the same signatures.
+    pub fn verify_opt(
+        &self,
+        messages: &[SignatureMessage],
+        verkey: &PublicKey,
+        level: OptLevel,
+    ) -> Result<bool, BBSError> {
+        check_verkey_message!(
+            messages.len() != verkey.message_count(),
+            verkey.message_count(),
+            messages.len()
+        );
+        let b = compute_b_var_time(&G1::new(), verkey, messages, &self.s, 0);
+        let a = (&G2::generator() * &self.e) + &verkey.w;
+        let g2 = G2::generator();
+        #[cfg(any(test, feature = "metrics"))]
+        metrics::record(1, 1, 2);
+        Ok(match level {
+            OptLevel::Separate => GT::ate_pairing(&self.a, &a) == GT::ate_pairing(&b, &g2),
+            OptLevel::Balanced => GT::ate_2_pairing(&self.a, &a, &(-&b), &g2).is_one(),
+            OptLevel::Multi => GT::ate_multi_pairing(vec![(&self.a, &a), (&(-&b), &g2)]).is_one(),
+        })
+    }
+
+    /// The intermediate values of `verify`, for protocols building on the verification
+    /// equation, see `VerifyComponents`. Verification succeeds exactly when
+    /// `VerifyComponents::is_valid` is true.
+    pub fn verify_components(
+        &self,
+        messages: &[SignatureMessage],
+        verkey: &PublicKey,
+    ) -> Result<VerifyComponents, BBSError> {
+        check_verkey_message!(
+            messages.len() != verkey.message_count(),
+            verkey.message_count(),
+            messages.len()
+        );
+        let b = compute_b_var_time(&G1::new(), verkey, messages, &self.s, 0);
+        let w_e = (&G2::generator() * &self.e) + &verkey.w;
+        let pairing = GT::ate_2_pairing(&self.a, &w_e, &(-&b), &G2::generator());
+        Ok(VerifyComponents { b, w_e, pairing })
+    }
+
+    /// Same as `verify` but says why verification failed. Usage errors like a malformed key
+    /// are still returned as `BBSError`.
+    pub fn verify_reasoned(
+        &self,
+        messages: &[SignatureMessage],
+        verkey: &PublicKey,
+    ) -> Result<Result<(), VerifyFailure>, BBSError> {
+        if messages.len() != verkey.message_count() {
+            return Ok(Err(VerifyFailure::CountMismatch));
+        }
+        if self.a.is_identity() {
+            return Ok(Err(VerifyFailure::IdentityA));
+        }
+        if !self.a.has_correct_order() {
+            return Ok(Err(VerifyFailure::OffSubgroupA));
+        }
+        if !self.verify(messages, verkey)? {
+            return Ok(Err(VerifyFailure::PairingMismatch));
+        }
+        Ok(Ok(()))
+    }
+
+    /// Verify against a key whose message bases are not in signing order. `mapping[i]` is the
+    /// position in `verkey.h` of the base for `messages[i]`, see `PublicKey::match_bases`.
+    pub fn verify_reordered(
+        &self,
+        messages: &[SignatureMessage],
+        verkey: &PublicKey,
+        mapping: &[usize],
+    ) -> Result<bool, BBSError> {
+        self.verify(messages, &verkey.reordered(mapping)?)
+    }
+
+    /// Same as `verify` but takes messages prepared with `PreparedMessages::new` so checking the
+    /// same messages against several signatures or keys doesn't rebuild the message vector.
+    pub fn verify_prepared_messages(
+        &self,
+        prepared: &PreparedMessages,
+        verkey: &PublicKey,
+    ) -> Result<bool, BBSError> {
+        check_verkey_message!(
+            prepared.len() != verkey.message_count(),
+            verkey.message_count(),
+            prepared.len()
+        );
+        // References only, the prepared scalars with `s` in the blinding slot
+        let g1 = G1::generator();
+        let mut points = Vec::with_capacity(prepared.scalars.len());
+        points.push(&g1);
+        points.push(&verkey.h0);
+        points.extend(verkey.h.iter());
+        let mut scalars: Vec<&FieldElement> = prepared.scalars.iter().collect();
+        scalars[1] = &self.s;
+        #[cfg(any(test, feature = "metrics"))]
+        metrics::record(points.len(), points.len() - 1, 0);
+        let b = G1Vector::multi_scalar_mul_var_time_from_ref_vecs(points, scalars).unwrap();
+        let a = (&G2::generator() * &self.e) + &verkey.w;
+        #[cfg(any(test, feature = "metrics"))]
+        metrics::record(1, 1, 2);
+        Ok(GT::ate_2_pairing(&self.a, &a, &(-&b), &G2::generator()).is_one())
+    }
+
+    /// Verify with the messages given as two groups of `(index, message)`, the ones always
+    /// revealed and the ones controlled by a policy. The groups together must have exactly one
+    /// message per index. Along with the result returns the group of each message in signing
+    /// order so policy evaluation knows where each base came from.
+    pub fn verify_grouped(
+        &self,
+        always: &[(usize, SignatureMessage)],
+        policy: &[(usize, SignatureMessage)],
+        verkey: &PublicKey,
+    ) -> Result<(bool, Vec<MessageGroup>), BBSError> {
+        check_verkey_message!(
+            always.len() + policy.len() != verkey.message_count(),
+            verkey.message_count(),
+            always.len() + policy.len()
+        );
+        let mut messages = vec![None; verkey.message_count()];
+        let groups = always
+            .iter()
+            .map(|m| (m, MessageGroup::Always))
+            .chain(policy.iter().map(|m| (m, MessageGroup::Policy)));
+        for ((i, m), group) in groups {
+            match messages.get_mut(*i) {
+                Some(slot @ None) => *slot = Some((m.clone(), group)),
+                _ => {
+                    return Err(BBSError::from_kind(BBSErrorKind::GeneralError {
+                        msg: format!("Index {} is out of range or given twice", i),
+                    }))
+                }
+            }
+        }
+        // Every slot is filled since there are exactly as many messages as slots
+        let (messages, groups): (Vec<_>, Vec<_>) = messages.into_iter().map(|m| m.unwrap()).unzip();
+        Ok((self.verify(messages.as_slice(), verkey)?, groups))
+    }
+
+    /// Recompute `a` from the messages, `e`, `s` and the secret key, i.e.
+    /// `(g1 * h0^s * h_i^m_i...)^(1/(x + e))`. Signer side only.
+    pub fn recompute_a(
+        &self,
+        messages: &[SignatureMessage],
+        signkey: &SecretKey,
+        verkey: &PublicKey,
+    ) -> Result<G1, BBSError> {
+        check_verkey_message!(
+            messages.len() != verkey.message_count(),
+            verkey.message_count(),
+            messages.len()
+        );
+        let b = compute_b_const_time(&G1::new(), verkey, messages, &self.s, 0);
+        let mut exp = signkey.clone();
+        exp += &self.e;
+        exp.inverse_mut();
+        Ok(b * exp)
+    }
+
+    /// Integrity check for the signer before persisting a signature, true if `a` is the one
+    /// `recompute_a` gives. Cheaper than `verify` as it needs no pairing.
+    pub fn check_a(
+        &self,
+        messages: &[SignatureMessage],
+        signkey: &SecretKey,
+        verkey: &PublicKey,
+    ) -> Result<bool, BBSError> {
+        Ok(self.recompute_a(messages, signkey, verkey)? == self.a)
+    }
+
+    /// Dual control check, true only if `self` verifies under `verkey1` and `sig2` under
+    /// `verkey2`, both over `messages`. Both equations are checked with one multi-pairing.
+    /// Fails when both keys are the same since that is a single issuer signing twice.
+    pub fn verify_dual(
+        &self,
+        sig2: &Signature,
+        messages: &[SignatureMessage],
+        verkey1: &PublicKey,
+        verkey2: &PublicKey,
+    ) -> Result<bool, BBSError> {
+        if verkey1 == verkey2 {
+            return Ok(false);
+        }
+        let mut acc = PairingAccumulator::new();
+        self.verify_accumulate(messages, verkey1, &mut acc)?;
+        sig2.verify_accumulate(messages, verkey2, &mut acc)?;
+        Ok(acc.check())
+    }
+
+    /// Index of the first key in `keys` under which the signature is valid, or `None` if there
+    /// is none, e.g. to accept both the old and the new key during a key rotation. Keys for a
+    /// different number of messages than given are skipped rather than treated as an error.
+    pub fn verify_any(
+        &self,
+        messages: &[SignatureMessage],
+        keys: &[PublicKey],
+    ) -> Result<Option<usize>, BBSError> {
+        for (i, verkey) in keys.iter().enumerate() {
+            if verkey.message_count() != messages.len() {
+                continue;
+            }
+            if self.verify(messages, verkey)? {
+                return Ok(Some(i));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Index of the first signature in `sigs` not valid over the messages at the same index
+    /// in `messages`, or `None` if all are valid. Halves of the batch are checked with one
+    /// multi-pairing each, descending into the first half that fails, so one bad signature
+    /// among `N` is found in about `log2(N)` batch checks. A message set of the wrong size
+    /// counts as invalid.
+    pub fn find_invalid(
+        sigs: &[Signature],
+        messages: &[Vec<SignatureMessage>],
+        verkey: &PublicKey,
+    ) -> Result<Option<usize>, BBSError> {
+        if sigs.len() != messages.len() {
+            return Err(BBSError::from_kind(BBSErrorKind::GeneralError {
+                msg: format!(
+                    "Got {} signatures but {} message sets",
+                    sigs.len(),
+                    messages.len()
+                ),
+            }));
+        }
+        let batch_valid = |start: usize, end: usize| {
+            let mut acc = PairingAccumulator::new();
+            for i in start..end {
+                if sigs[i]
+                    .verify_accumulate(messages[i].as_slice(), verkey, &mut acc)
+                    .is_err()
+                {
+                    return false;
+                }
+            }
+            acc.check()
+        };
+        let (mut start, mut end) = (0, sigs.len());
+        if batch_valid(start, end) {
+            return Ok(None);
+        }
+        // Invariant: [start, end) holds an invalid signature
+        while end - start > 1 {
+            let mid = start + (end - start) / 2;
+            if batch_valid(start, mid) {
+                start = mid;
+            } else {
+                end = mid;
+            }
+        }
+        Ok(Some(start))
+    }
+
+    /// Verify many signatures, under one or several keys, with a single multi-pairing. The
+    /// verification equation of entry `i` is scaled by `delta = r^i` for one random `r` so an
+    /// invalid signature can't be cancelled out by another one: written in the exponent, the
+    /// combined check is `sum(r^i * x_i) = 0` where `x_i` is zero exactly when entry `i` is
+    /// valid. If any `x_i` isn't zero that is a nonzero polynomial in `r` of degree below `N`,
+    /// with at most `N - 1` roots, and `r` is drawn after the entries are fixed, so an invalid
+    /// batch passes with probability at most `(N - 1) / q` for the group order `q`, negligible
+    /// like with `N` independent weights. That argument takes every `x_i` in the prime order
+    /// group: a point outside it has components of small order that powers of `r` can cancel,
+    /// so entries whose `a` is the identity or outside the subgroup fail the batch up front.
+    /// Splitting `e(a, g2^e * w)` into `e(a, w) * e(a^e, g2)` lets all signatures under the
+    /// same `w` share one pairing, so `N` signatures under `K` keys take `K + 1` Miller loops
+    /// and one final exponentiation instead of `N` times `ate_2_pairing`. `b` is computed by `compute_b_const_time` as when signing.
+    /// False if any signature is invalid, see `find_invalid` to tell which. An empty batch is
+    /// valid.
+    pub fn verify_batch(
+        entries: &[(&Signature, &[SignatureMessage], &PublicKey)],
+    ) -> Result<bool, BBSError> {
+        // `w`, the sum of `a * delta` and the sum of `(a^e / b) * delta` of its signatures
+        let mut groups: Vec<(&G2, G1, G1)> = Vec::new();
+        let r = FieldElement::random();
+        let mut delta = FieldElement::one();
+        for (sig, messages, verkey) in entries {
+            check_verkey_message!(
+                messages.len() != verkey.message_count(),
+                verkey.message_count(),
+                messages.len()
+            );
+            // Deserialized signatures skip the checks of `from_bytes`, and the weights only
+            // keep entries apart in the prime order group
+            if sig.a.is_identity() || !sig.a.has_correct_order() {
+                return Ok(false);
+            }
+            let b = compute_b_const_time(&G1::new(), verkey, messages, &sig.s, 0);
+            let a = &sig.a * &delta;
+            let g2_term = &(&a * &sig.e) - &(&b * &delta);
+            delta = &delta * &r;
+            #[cfg(any(test, feature = "metrics"))]
+            metrics::record(3, 3, 0);
+            match groups.iter_mut().find(|(w, _, _)| **w == verkey.w) {
+                Some((_, sum_a, sum_g2)) => {
+                    *sum_a += a;
+                    *sum_g2 += g2_term;
+                }
+                None => groups.push((&verkey.w, a, g2_term)),
+            }
+        }
+        let mut acc = PairingAccumulator::new();
+        for (w, a, g2_term) in &groups {
+            acc.add(a, w, g2_term);
+        }
+        Ok(acc.check())
+    }
+
+    /// Add this signature's verification equation to `acc` instead of checking it right away.
+    /// Nothing is known about validity until `acc.check()` is called which verifies all
+    /// accumulated statements at once.
+    pub fn verify_accumulate(
+        &self,
+        messages: &[SignatureMessage],
+        verkey: &PublicKey,
+        acc: &mut PairingAccumulator,
+    ) -> Result<(), BBSError> {
+        check_verkey_message!(
+            messages.len() != verkey.message_count(),
+            verkey.message_count(),
+            messages.len()
+        );
+        let b = compute_b_var_time(&G1::new(), verkey, messages, &self.s, 0);
+        let a = (&G2::generator() * &self.e) + &verkey.w;
+        #[cfg(any(test, feature = "metrics"))]
+        metrics::record(1, 1, 0);
+        acc.add(&self.a, &a, &(-&b));
+        Ok(())
+    }
+}
+
+/// A verifier's key with a table of odd multiples of the `G2` generator, so `verify_prepared`
+/// computes `g2^e` with windowed NAF instead of recomputing the table for every signature.
+/// Variable time in `e`, which is public. Build once per key and reuse. amcl's own
+/// multiplication turned out about as fast, so don't expect a speedup, see `benches/bbs.rs`.
+pub struct PreparedVerifier {
+    pub verkey: PublicKey,
+    g2_table: G2LookupTable,
+}
+
+impl PreparedVerifier {
+    pub fn new(verkey: &PublicKey) -> Self {
+        PreparedVerifier {
+            verkey: verkey.clone(),
+            g2_table: G2::generator().to_wnaf_lookup_table(5),
+        }
+    }
+
+    /// `g2^e` from the table
+    pub fn g2_mul(&self, e: &FieldElement) -> G2 {
+        G2::wnaf_mul(&self.g2_table, &e.to_wnaf(5))
+    }
+}
+
+/// Buffers for the points and scalars of `b` that `verify_in` refills for every verification
+/// instead of allocating new vectors. They grow to the largest message count seen and keep
+/// that capacity, so after the first verification of a size nothing is allocated for them.
+/// Contents don't carry over between calls. amcl still allocates inside the multi-scalar
+/// multiplication for its lookup tables, the scratch only saves the input vectors.
+pub struct VerifyScratch {
+    points: G1Vector,
+    scalars: FieldElementVector,
+}
+
+impl VerifyScratch {
+    pub fn new() -> Self {
+        VerifyScratch::with_capacity(0)
+    }
+
+    /// Scratch with room for keys of up to `message_count` messages
+    pub fn with_capacity(message_count: usize) -> Self {
+        VerifyScratch {
+            points: G1Vector::with_capacity(message_count + 2),
+            scalars: FieldElementVector::with_capacity(message_count + 2),
+        }
+    }
+
+    // Empty both buffers, keeping their capacity
+    fn clear(&mut self) {
+        while self.points.pop().is_some() {}
+        while self.scalars.pop().is_some() {}
+    }
+}
+
+impl Default for VerifyScratch {
+    fn default() -> Self {
+        VerifyScratch::new()
+    }
+}
+
+/// A signature without `s`, 48 bytes smaller. Neither verifiable nor usable in a proof on its
+/// own: `s` is part of `b` so it has to come back through `fatten` first, and there is no way
+/// to recover it from `a` and `e`. Thinning is only safe when `s` is kept elsewhere, e.g. in a
+/// separate store, or the application can recompute it, otherwise the signature is lost. A
+/// wrong `s` gives a signature that doesn't verify, so check the fattened signature before
+/// relying on it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ThinSignature {
+    pub a: G1,
+    pub e: FieldElement,
+}
+
+impl ThinSignature {
+    /// Restore the full signature with its `s`
+    pub fn fatten(&self, s: &FieldElement) -> Signature {
+        Signature {
+            a: self.a.clone(),
+            e: self.e.clone(),
+            s: s.clone(),
+        }
+    }
+}
+
+/// Values computed by `Signature::verify_components`. The verification equation is
+/// `e(a, w_e) == e(b, g2)`, checked as `e(a, w_e) * e(-b, g2) == 1` with both Miller loops
+/// sharing one final exponentiation.
+#[derive(Debug, Clone)]
+pub struct VerifyComponents {
+    /// `g1 * h0^s * h_i^m_i...`, what `a` is `b^(1/(x + e))` of
+    pub b: G1,
+    /// `g2^e * w`
+    pub w_e: G2,
+    /// `e(a, w_e) * e(-b, g2)`
+    pub pairing: GT,
+}
+
+impl VerifyComponents {
+    pub fn is_valid(&self) -> bool {
+        self.pairing.is_one()
+    }
+}
+
+/// Why `Signature::verify_reasoned` rejected a signature
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyFailure {
+    /// Number of messages differs from the key's
+    CountMismatch,
+    /// `a` is the point at infinity
+    IdentityA,
+    /// `a` is on the curve but outside the prime order subgroup
+    OffSubgroupA,
+    /// The verification equation doesn't hold
+    PairingMismatch,
+}
+
+impl VerifyFailure {
+    /// Short machine parseable name for logs
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VerifyFailure::CountMismatch => "count_mismatch",
+            VerifyFailure::IdentityA => "identity_a",
+            VerifyFailure::OffSubgroupA => "off_subgroup_a",
+            VerifyFailure::PairingMismatch => "pairing_mismatch",
+        }
+    }
+}
+
+impl std::fmt::Display for VerifyFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Group a message was given in to `Signature::verify_grouped`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageGroup {
+    Always,
+    Policy,
+}
+
+/// Meaning of `s` in a stored signature. Everything in this crate signs, unblinds and verifies
+/// with `Additive`, `Negated` is only understood by `Signature::migrate_blinding` so stores
+/// holding signatures in the other convention can upgrade them in place.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlindingScheme {
+    /// `b = g1 * h0^s * h_i^m_i...` and unblinding adds the blinding to `s`
+    Additive,
+    /// `s` is kept negated, `b = g1 * h0^-s * h_i^m_i...`, and unblinding subtracts the blinding
+    Negated,
+}
+
+/// Pairing engine path used by `Signature::verify_opt`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OptLevel {
+    /// Two independent pairings, each with its own final exponentiation, compared in `GT`.
+    /// No shared state, but the most work per verification.
+    Separate,
+    /// Both Miller loops share one final exponentiation. What `verify` does.
+    #[default]
+    Balanced,
+    /// amcl's generic multi-pairing which allocates its working state up front. Pays off when
+    /// the same path is used for batches, see `PairingAccumulator`.
+    Multi,
+}
+
+// Side channel note: the only branches taken before the pairing in `verify` and during signing
+// depend on message counts and `offset`, which are public. Messages, blinding factor and secret
+// key never decide a branch or the number of operations, they are only fed to the constant time
+// multi-scalar multiplication in `compute_b_const_time` and the scalar multiplication of `b`.
+fn prep_vec_for_b(
+    g1: &G1,
+    public_key: &PublicKey,
+    messages: &[FieldElement],
+    blinding_factor: &FieldElement,
+    offset: usize,
+) -> (G1Vector, FieldElementVector) {
+    let mut points = G1Vector::with_capacity(messages.len() + 2);
+    let mut scalars = FieldElementVector::with_capacity(messages.len() + 2);
+    // prep for g1*h0^blinding_factor*hi^mi.....
+    points.push(g1.clone());
+    scalars.push(FieldElement::one());
+    points.push(public_key.h0.clone());
+    scalars.push(blinding_factor.clone());
+
+    for i in 0..messages.len() {
+        points.push(public_key.h[offset + i].clone());
+        scalars.push(messages[i].clone());
+    }
+    (points, scalars)
+}
+
+/// Whether `value` is zero, without branching on or returning early for any of its bytes
+pub fn ct_is_zero(value: &FieldElement) -> Choice {
+    value.to_bytes().ct_eq(&[0u8; MODBYTES][..])
+}
+
+// Random `e` and `1/(x + e)`. `x + e` is zero with negligible probability, if it is `e` is
+// drawn again. The check is constant time so it doesn't leak how close `e` is to `-x`.
+fn random_e(signkey: &SecretKey) -> (FieldElement, FieldElement) {
+    loop {
+        let e = FieldElement::random();
+        let mut exp = signkey + &e;
+        if !bool::from(ct_is_zero(&exp)) {
+            exp.inverse_mut();
+            return (e, exp);
+        }
+    }
+}
+
+/// Helper function for computing the `b` value. Internal helper function
+pub fn compute_b_const_time(
+    starting_value: &G1,
+    public_key: &PublicKey,
+    messages: &[FieldElement],
+    blinding_factor: &FieldElement,
+    offset: usize,
+) -> G1 {
+    compute_b_const_time_with_params(
+        &Params::default(),
+        starting_value,
+        public_key,
+        messages,
+        blinding_factor,
+        offset,
+    )
+}
+
+/// Same as `compute_b_const_time` with `params.g1` in place of the default generator
+pub fn compute_b_const_time_with_params(
+    params: &Params,
+    starting_value: &G1,
+    public_key: &PublicKey,
+    messages: &[FieldElement],
+    blinding_factor: &FieldElement,
+    offset: usize,
+) -> G1 {
+    let (points, scalars) =
+        prep_vec_for_b(&params.g1, public_key, messages, blinding_factor, offset);
+    #[cfg(any(test, feature = "metrics"))]
+    metrics::record(points.len(), points.len(), 0);
+    starting_value + points.multi_scalar_mul_const_time(&scalars).unwrap()
+}
+
+/// Number of points from which `compute_b_var_time` switches to Pippenger's method. Below it
+/// amcl's windowed multi-scalar multiplication is as fast or faster, the two crossed between
+/// 200 and 300 messages, see the numbers at `compute_b_benchmark` in `benches/bbs.rs`.
+/// Signing always uses the constant time multiplication, `new` included: which bucket a point
+/// lands in depends on the digits of its scalar, and when signing the scalars are `s` and the
+/// messages, which timing must not reveal.
+pub const PIPPENGER_THRESHOLD: usize = 256;
+
+/// Same as `compute_b_var_time` using Pippenger's bucket method for the multi-scalar
+/// multiplication, which needs fewer additions once there are many messages.
+/// Variable time so only for verification.
+pub fn compute_b_pippenger(
+    starting_value: &G1,
+    public_key: &PublicKey,
+    messages: &[FieldElement],
+    blinding_factor: &FieldElement,
+    offset: usize,
+) -> G1 {
+    compute_b_pippenger_with_params(
+        &Params::default(),
+        starting_value,
+        public_key,
+        messages,
+        blinding_factor,
+        offset,
+    )
+}
+
+/// Same as `compute_b_pippenger` with `params.g1` in place of the default generator
+pub fn compute_b_pippenger_with_params(
+    params: &Params,
+    starting_value: &G1,
+    public_key: &PublicKey,
+    messages: &[FieldElement],
+    blinding_factor: &FieldElement,
+    offset: usize,
+) -> G1 {
+    let (points, scalars) =
+        prep_vec_for_b(&params.g1, public_key, messages, blinding_factor, offset);
+    #[cfg(any(test, feature = "metrics"))]
+    metrics::record(points.len(), points.len(), 0);
+    starting_value + multi_scalar_mul_pippenger(points.as_slice(), scalars.as_slice())
+}
+
+// Splits the scalars in windows of `c` bits. For each window the points are summed in buckets
+// by their digit and the buckets are combined with a running sum, giving the window's
+// contribution in about `n + 2^c` additions instead of a scalar multiplication per point.
+// No `usize::div_ceil`, it needs Rust 1.73
+#[allow(clippy::manual_div_ceil)]
+fn multi_scalar_mul_pippenger(points: &[G1], scalars: &[FieldElement]) -> G1 {
+    let c = pippenger_window(points.len());
+    let scalars: Vec<Vec<u8>> = scalars.iter().map(|s| s.to_bytes()).collect();
+    // Scalars are big endian, skip the leading zero bytes shared by all of them
+    let bytes = scalars.first().map(|s| s.len()).unwrap_or(0);
+    let leading = (0..bytes)
+        .take_while(|i| scalars.iter().all(|s| s[*i] == 0))
+        .count();
+    let bits = (bytes - leading) * 8;
+
+    let mut result = G1::identity();
+    for w in (0..(bits + c - 1) / c).rev() {
+        for _ in 0..c {
+            result.double_mut();
+        }
+        let mut buckets = vec![G1::identity(); (1 << c) - 1];
+        for (p, s) in points.iter().zip(&scalars) {
+            let digit = window_digit(s, w * c, c);
+            if digit != 0 {
+                buckets[digit - 1].add_assign_(p);
+            }
+        }
+        let mut running = G1::identity();
+        let mut window = G1::identity();
+        for b in buckets.iter().rev() {
+            running.add_assign_(b);
+            window.add_assign_(&running);
+        }
+        result.add_assign_(&window);
+    }
+    result
+}
+
+// Window minimizing the additions over 255 bit scalars, roughly `windows * (n + buckets)`
+fn pippenger_window(n: usize) -> usize {
+    (2..16)
+        .min_by_key(|c| (255 / c + 1) * (n + (1 << c)))
+        .unwrap()
+}
+
+// `c` bits of big endian `bytes` starting at bit `start` counted from the least significant bit
+fn window_digit(bytes: &[u8], start: usize, c: usize) -> usize {
+    let mut digit = 0;
+    for i in (start..start + c).rev() {
+        let byte = i / 8;
+        let bit = if byte < bytes.len() {
+            (bytes[bytes.len() - 1 - byte] >> (i % 8)) & 1
+        } else {
+            0
+        };
+        digit = (digit << 1) | bit as usize;
+    }
+    digit
+}
+
+/// Helper function for computing the `b` value. Internal helper function
+pub fn compute_b_var_time(
+    starting_value: &G1,
+    public_key: &PublicKey,
+    messages: &[FieldElement],
+    blinding_factor: &FieldElement,
+    offset: usize,
+) -> G1 {
+    compute_b_var_time_with_params(
+        &Params::default(),
+        starting_value,
+        public_key,
+        messages,
+        blinding_factor,
+        offset,
+    )
+}
+
+/// Same as `compute_b_var_time` with `params.g1` in place of the default generator
+pub fn compute_b_var_time_with_params(
+    params: &Params,
+    starting_value: &G1,
+    public_key: &PublicKey,
+    messages: &[FieldElement],
+    blinding_factor: &FieldElement,
+    offset: usize,
+) -> G1 {
+    let (points, scalars) =
+        prep_vec_for_b(&params.g1, public_key, messages, blinding_factor, offset);
+    #[cfg(any(test, feature = "metrics"))]
+    metrics::record(points.len(), points.len(), 0);
+    if points.len() >= PIPPENGER_THRESHOLD {
+        return starting_value + multi_scalar_mul_pippenger(points.as_slice(), scalars.as_slice());
+    }
+    starting_value + points.multi_scalar_mul_var_time(&scalars).unwrap()
+}
+
+/// Same as `compute_b_var_time` with messages borrowed, neither the messages nor the key's
+/// bases are copied. Always uses amcl's windowed multiplication, also above
+/// `PIPPENGER_THRESHOLD`.
+pub fn compute_b_var_time_refs(
+    starting_value: &G1,
+    public_key: &PublicKey,
+    messages: &[&FieldElement],
+    blinding_factor: &FieldElement,
+    offset: usize,
+) -> G1 {
+    let g1 = G1::generator();
+    let one = FieldElement::one();
+    let mut points = Vec::with_capacity(messages.len() + 2);
+    let mut scalars = Vec::with_capacity(messages.len() + 2);
+    points.push(&g1);
+    scalars.push(&one);
+    points.push(&public_key.h0);
+    scalars.push(blinding_factor);
+    for (i, m) in messages.iter().enumerate() {
+        points.push(&public_key.h[offset + i]);
+        scalars.push(*m);
+    }
+    #[cfg(any(test, feature = "metrics"))]
+    metrics::record(points.len(), points.len(), 0);
+    starting_value + G1Vector::multi_scalar_mul_var_time_from_ref_vecs(points, scalars).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::keys::generate;
+    use super::super::pok_sig::ProverCommittingG1;
+    use super::*;
+
+    #[test]
+    fn signature_serialization() {
+        let sig = Signature {
+            a: G1::random(),
+            e: FieldElement::random(),
+            s: FieldElement::random(),
+        };
+        let bytes = sig.to_bytes();
+        assert_eq!(bytes.len(), GroupG1_SIZE + MODBYTES * 2);
+        let sig_2 = Signature::from_bytes(bytes.as_slice()).unwrap();
+        assert_eq!(sig, sig_2);
+    }
+
+    #[test]
+    fn signature_from_bytes_prefix() {
+        let sig = Signature {
+            a: G1::random(),
+            e: FieldElement::random(),
+            s: FieldElement::random(),
+        };
+        let mut bytes = sig.to_bytes();
+        bytes.extend_from_slice(b"trailing");
+        assert!(Signature::from_bytes(bytes.as_slice()).is_err());
+        let (sig_2, read) = Signature::from_bytes_prefix(bytes.as_slice()).unwrap();
+        assert_eq!(sig, sig_2);
+        assert_eq!(read, SIGNATURE_SIZE);
+        assert_eq!(&bytes[read..], b"trailing");
+
+        assert!(Signature::from_bytes_prefix(&bytes[..SIGNATURE_SIZE - 1]).is_err());
+    }
+
+    #[test]
+    fn compute_b_pippenger_matches() {
+        let (verkey, _) = generate(40).unwrap();
+        let blinding = FieldElement::random();
+        let start = G1::random();
+        for count in &[1, 5, 40] {
+            let messages = FieldElementVector::random(*count);
+            let expected = compute_b_const_time(&start, &verkey, messages.as_slice(), &blinding, 0);
+            let b = compute_b_pippenger(&start, &verkey, messages.as_slice(), &blinding, 0);
+            assert_eq!(expected, b);
+        }
+        // Zero and small scalars, at an offset
+        let messages = vec![
+            FieldElement::zero(),
+            FieldElement::one(),
+            FieldElement::zero(),
+        ];
+        assert_eq!(
+            compute_b_const_time(&start, &verkey, &messages, &FieldElement::one(), 7),
+            compute_b_pippenger(&start, &verkey, &messages, &FieldElement::one(), 7)
+        );
+
+        // Verification above the threshold goes through Pippenger
+        let message_count = PIPPENGER_THRESHOLD;
+        let messages = FieldElementVector::random(message_count);
+        let (verkey, signkey) = generate(message_count).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+        assert!(sig.verify(messages.as_slice(), &verkey).unwrap());
+        let mut messages = messages;
+        messages[3] = FieldElement::random();
+        assert!(!sig.verify(messages.as_slice(), &verkey).unwrap());
+    }
+
+    #[test]
+    fn signature_size() {
+        let message_count = 3;
+        let messages = FieldElementVector::random(message_count);
+        let (verkey, signkey) = generate(message_count).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+        assert_eq!(sig.size(), sig.to_bytes().len());
+        assert_eq!(sig.size(), SIGNATURE_SIZE);
     }
 
     #[test]
@@ -229,6 +1535,334 @@ mod tests {
         assert!(!res.unwrap());
     }
 
+    #[test]
+    fn signing_op_count_independent_of_messages() {
+        let message_count = 5;
+        let (verkey, signkey) = generate(message_count).unwrap();
+
+        let mut counts = Vec::new();
+        for _ in 0..2 {
+            let messages = FieldElementVector::random(message_count);
+            metrics::reset();
+            Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+            counts.push(metrics::get());
+        }
+        // g1, h0 and one base per message, plus the final scalar multiplication of `b`
+        assert_eq!(counts[0].scalar_muls, message_count + 3);
+        assert_eq!(counts[0].additions, message_count + 2);
+        assert_eq!(counts[0], counts[1]);
+
+        // Special values must not shortcut anything
+        let messages = vec![FieldElement::zero(); message_count];
+        metrics::reset();
+        Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+        assert_eq!(counts[0], metrics::get());
+    }
+
+    #[test]
+    fn signature_reordered_bases() {
+        let message_count = 5;
+        let messages = FieldElementVector::random(message_count);
+        let (verkey, signkey) = generate(message_count).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+
+        let shuffled = verkey.reordered(&[3, 0, 4, 1, 2]).unwrap();
+        assert!(!sig.verify(messages.as_slice(), &shuffled).unwrap());
+        match shuffled.check_order(&verkey).unwrap_err().kind() {
+            BBSErrorKind::BaseOrderMismatch => {}
+            _ => panic!("Expected BaseOrderMismatch"),
+        }
+        assert!(verkey.check_order(&verkey).is_ok());
+        assert!(PublicKey::random(message_count)
+            .check_order(&verkey)
+            .is_err());
+
+        let mapping = shuffled.match_bases(&verkey).unwrap();
+        assert_eq!(mapping, vec![1, 3, 4, 0, 2]);
+        assert!(sig
+            .verify_reordered(messages.as_slice(), &shuffled, &mapping)
+            .unwrap());
+        assert!(sig
+            .verify_reordered(messages.as_slice(), &shuffled, &[0, 0, 1, 2, 3])
+            .is_err());
+    }
+
+    #[test]
+    fn signature_prepared_messages() {
+        let message_count = 5;
+        let messages = FieldElementVector::random(message_count);
+        let prepared = PreparedMessages::new(messages.as_slice());
+        assert_eq!(prepared.len(), message_count);
+
+        let (verkey_1, signkey_1) = generate(message_count).unwrap();
+        let (verkey_2, _) = generate(message_count).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey_1, &verkey_1).unwrap();
+        for verkey in &[&verkey_1, &verkey_2] {
+            assert_eq!(
+                sig.verify(messages.as_slice(), verkey).unwrap(),
+                sig.verify_prepared_messages(&prepared, verkey).unwrap()
+            );
+        }
+        assert!(sig.verify_prepared_messages(&prepared, &verkey_1).unwrap());
+
+        let other = PreparedMessages::new(FieldElementVector::random(message_count).as_slice());
+        assert!(!sig.verify_prepared_messages(&other, &verkey_1).unwrap());
+        let short = PreparedMessages::new(&messages.as_slice()[1..]);
+        assert!(sig.verify_prepared_messages(&short, &verkey_1).is_err());
+
+        // Deserializing checks the layout
+        let json = serde_json::to_value(&prepared).unwrap();
+        let parsed: PreparedMessages = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(parsed, prepared);
+        let mut no_slots = json.clone();
+        no_slots["scalars"] = serde_json::to_value(FieldElementVector::new(1)).unwrap();
+        assert!(serde_json::from_value::<PreparedMessages>(no_slots).is_err());
+        let mut not_one = json;
+        not_one["scalars"]["elems"][0] = serde_json::to_value(FieldElement::random()).unwrap();
+        assert!(serde_json::from_value::<PreparedMessages>(not_one).is_err());
+    }
+
+    #[test]
+    fn signature_accumulated_verification() {
+        let message_count = 5;
+        let (verkey_1, signkey_1) = generate(message_count).unwrap();
+        let (verkey_2, signkey_2) = generate(message_count - 2).unwrap();
+        let messages_1 = FieldElementVector::random(message_count);
+        let messages_2 = FieldElementVector::random(message_count - 2);
+        let sig_1 = Signature::new(messages_1.as_slice(), &signkey_1, &verkey_1).unwrap();
+        let sig_2 = Signature::new(messages_2.as_slice(), &signkey_2, &verkey_2).unwrap();
+
+        let mut acc = PairingAccumulator::new();
+        sig_1
+            .verify_accumulate(messages_1.as_slice(), &verkey_1, &mut acc)
+            .unwrap();
+        sig_2
+            .verify_accumulate(messages_2.as_slice(), &verkey_2, &mut acc)
+            .unwrap();
+        assert_eq!(acc.len(), 2);
+        assert!(acc.check());
+
+        // One bad statement fails the whole accumulator
+        let mut acc = PairingAccumulator::new();
+        sig_1
+            .verify_accumulate(messages_1.as_slice(), &verkey_1, &mut acc)
+            .unwrap();
+        sig_2
+            .verify_accumulate(messages_2.as_slice(), &verkey_1, &mut acc)
+            .unwrap_err();
+        sig_2
+            .verify_accumulate(
+                FieldElementVector::random(message_count - 2).as_slice(),
+                &verkey_2,
+                &mut acc,
+            )
+            .unwrap();
+        assert!(!acc.check());
+    }
+
+    #[test]
+    fn signature_verify_reasoned() {
+        let message_count = 3;
+        let messages = FieldElementVector::random(message_count);
+        let (verkey, signkey) = generate(message_count).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+        assert_eq!(
+            sig.verify_reasoned(messages.as_slice(), &verkey).unwrap(),
+            Ok(())
+        );
+
+        let res = sig
+            .verify_reasoned(&messages.as_slice()[1..], &verkey)
+            .unwrap();
+        assert_eq!(res.unwrap_err().as_str(), "count_mismatch");
+
+        let mut tampered = sig.clone();
+        tampered.a = G1::identity();
+        let res = tampered
+            .verify_reasoned(messages.as_slice(), &verkey)
+            .unwrap();
+        assert_eq!(res.unwrap_err().as_str(), "identity_a");
+
+        tampered.a = off_subgroup_point();
+        let res = tampered
+            .verify_reasoned(messages.as_slice(), &verkey)
+            .unwrap();
+        assert_eq!(res.unwrap_err().as_str(), "off_subgroup_a");
+
+        tampered.a = G1::random();
+        let res = tampered
+            .verify_reasoned(messages.as_slice(), &verkey)
+            .unwrap();
+        assert_eq!(res.unwrap_err(), VerifyFailure::PairingMismatch);
+        assert_eq!(
+            VerifyFailure::PairingMismatch.to_string(),
+            "pairing_mismatch"
+        );
+    }
+
+    #[test]
+    fn signature_grouped_messages() {
+        let message_count = 4;
+        let messages = FieldElementVector::random(message_count);
+        let (verkey, signkey) = generate(message_count).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+
+        let always = vec![(2, messages[2].clone()), (0, messages[0].clone())];
+        let policy = vec![(3, messages[3].clone()), (1, messages[1].clone())];
+        let (valid, groups) = sig.verify_grouped(&always, &policy, &verkey).unwrap();
+        assert!(valid);
+        assert_eq!(
+            groups,
+            vec![
+                MessageGroup::Always,
+                MessageGroup::Policy,
+                MessageGroup::Always,
+                MessageGroup::Policy
+            ]
+        );
+
+        let swapped = vec![(3, messages[1].clone()), (1, messages[3].clone())];
+        let (valid, _) = sig.verify_grouped(&always, &swapped, &verkey).unwrap();
+        assert!(!valid);
+
+        let duplicate = vec![(2, messages[3].clone()), (1, messages[1].clone())];
+        assert!(sig.verify_grouped(&always, &duplicate, &verkey).is_err());
+        let out_of_range = vec![(4, messages[3].clone()), (1, messages[1].clone())];
+        assert!(sig.verify_grouped(&always, &out_of_range, &verkey).is_err());
+        assert!(sig.verify_grouped(&always, &policy[..1], &verkey).is_err());
+    }
+
+    #[test]
+    fn signature_prepared_secret_key() {
+        let message_count = 4;
+        let messages = FieldElementVector::random(message_count);
+        let (verkey, signkey) = generate(message_count).unwrap();
+
+        let es = vec![FieldElement::random(), FieldElement::random()];
+        let mut prepared = PreparedSecretKey::with_nonces(&signkey, &es);
+        for e in &es {
+            let sig = Signature::new_prepared(messages.as_slice(), &mut prepared, &verkey).unwrap();
+            assert_eq!(&sig.e, e);
+            // `a` is what `new` computes for this `e` and `s`
+            assert_eq!(
+                sig.a,
+                sig.recompute_a(messages.as_slice(), &signkey, &verkey)
+                    .unwrap()
+            );
+            assert!(sig.verify(messages.as_slice(), &verkey).unwrap());
+        }
+
+        // An `e` of `-x` is drawn again instead of zeroing the batch of inverses
+        let es = vec![FieldElement::random(), -&signkey, FieldElement::random()];
+        let mut zero_sum = PreparedSecretKey::with_nonces(&signkey, &es);
+        for (i, e) in es.iter().enumerate() {
+            let sig = Signature::new_prepared(messages.as_slice(), &mut zero_sum, &verkey).unwrap();
+            assert_eq!(&sig.e == e, i != 1);
+            assert!(sig.verify(messages.as_slice(), &verkey).unwrap());
+        }
+
+        // Refills once used up
+        assert_eq!(prepared.remaining(), 0);
+        let sig = Signature::new_prepared(messages.as_slice(), &mut prepared, &verkey).unwrap();
+        assert_eq!(prepared.remaining(), 1);
+        assert!(sig.verify(messages.as_slice(), &verkey).unwrap());
+        assert!(
+            Signature::new_prepared(&messages.as_slice()[1..], &mut prepared, &verkey).is_err()
+        );
+    }
+
+    #[test]
+    fn signature_recompute_a() {
+        let message_count = 4;
+        let messages = FieldElementVector::random(message_count);
+        let (verkey, signkey) = generate(message_count).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+        assert_eq!(
+            sig.recompute_a(messages.as_slice(), &signkey, &verkey)
+                .unwrap(),
+            sig.a
+        );
+        assert!(sig.check_a(messages.as_slice(), &signkey, &verkey).unwrap());
+
+        let mut tampered = sig.clone();
+        tampered.a = G1::random();
+        assert!(!tampered
+            .check_a(messages.as_slice(), &signkey, &verkey)
+            .unwrap());
+        let (_, other_key) = generate(message_count).unwrap();
+        assert!(!sig
+            .check_a(messages.as_slice(), &other_key, &verkey)
+            .unwrap());
+    }
+
+    #[test]
+    fn signature_find_invalid() {
+        let message_count = 2;
+        let (verkey, signkey) = generate(message_count).unwrap();
+        let messages: Vec<Vec<SignatureMessage>> = (0..16)
+            .map(|_| {
+                FieldElementVector::random(message_count)
+                    .as_slice()
+                    .to_vec()
+            })
+            .collect();
+        let mut sigs: Vec<Signature> = messages
+            .iter()
+            .map(|m| Signature::new(m.as_slice(), &signkey, &verkey).unwrap())
+            .collect();
+        assert_eq!(
+            Signature::find_invalid(&sigs, &messages, &verkey).unwrap(),
+            None
+        );
+
+        for bad in &[0, 5, 11, 15] {
+            let mut corrupted = sigs.clone();
+            corrupted[*bad].s = FieldElement::random();
+            assert_eq!(
+                Signature::find_invalid(&corrupted, &messages, &verkey).unwrap(),
+                Some(*bad)
+            );
+        }
+
+        // The first of several is reported
+        sigs[9].a = G1::random();
+        sigs[3].e = FieldElement::random();
+        assert_eq!(
+            Signature::find_invalid(&sigs, &messages, &verkey).unwrap(),
+            Some(3)
+        );
+        assert!(Signature::find_invalid(&sigs[1..], &messages, &verkey).is_err());
+    }
+
+    #[test]
+    fn signature_dual_control() {
+        let message_count = 4;
+        let messages = FieldElementVector::random(message_count);
+        let (verkey_1, signkey_1) = generate(message_count).unwrap();
+        let (verkey_2, signkey_2) = generate(message_count).unwrap();
+        let sig_1 = Signature::new(messages.as_slice(), &signkey_1, &verkey_1).unwrap();
+        let sig_2 = Signature::new(messages.as_slice(), &signkey_2, &verkey_2).unwrap();
+        assert!(sig_1
+            .verify_dual(&sig_2, messages.as_slice(), &verkey_1, &verkey_2)
+            .unwrap());
+
+        // Second signature over other messages
+        let other = FieldElementVector::random(message_count);
+        let sig_3 = Signature::new(other.as_slice(), &signkey_2, &verkey_2).unwrap();
+        assert!(!sig_1
+            .verify_dual(&sig_3, messages.as_slice(), &verkey_1, &verkey_2)
+            .unwrap());
+        // Keys swapped
+        assert!(!sig_1
+            .verify_dual(&sig_2, messages.as_slice(), &verkey_2, &verkey_1)
+            .unwrap());
+        // One issuer signing twice
+        let sig_4 = Signature::new(messages.as_slice(), &signkey_1, &verkey_1).unwrap();
+        assert!(!sig_1
+            .verify_dual(&sig_4, messages.as_slice(), &verkey_1, &verkey_1)
+            .unwrap());
+    }
+
     #[test]
     fn signature_committed_messages() {
         let message_count = 4;
@@ -281,4 +1915,832 @@ mod tests {
         assert!(res.is_ok());
         assert!(res.unwrap());
     }
+
+    #[test]
+    fn verify_opt_levels_agree() {
+        let message_count = 4;
+        let messages = FieldElementVector::random(message_count);
+        let (verkey, signkey) = generate(message_count).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+        let (other_verkey, _) = generate(message_count).unwrap();
+
+        for level in &[OptLevel::Separate, OptLevel::Balanced, OptLevel::Multi] {
+            assert!(sig
+                .verify_opt(messages.as_slice(), &verkey, *level)
+                .unwrap());
+            assert!(!sig
+                .verify_opt(messages.as_slice(), &other_verkey, *level)
+                .unwrap());
+        }
+        assert_eq!(OptLevel::default(), OptLevel::Balanced);
+        assert!(sig
+            .verify_opt(&messages.as_slice()[1..], &verkey, OptLevel::Multi)
+            .is_err());
+    }
+
+    #[test]
+    fn verify_any_finds_key() {
+        let message_count = 3;
+        let messages = FieldElementVector::random(message_count);
+        let mut keys: Vec<PublicKey> = (0..3).map(|_| generate(message_count).unwrap().0).collect();
+        let (verkey, signkey) = generate(message_count).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+        assert_eq!(sig.verify_any(messages.as_slice(), &keys).unwrap(), None);
+
+        keys.insert(2, verkey.clone());
+        // A key for another message count doesn't get in the way
+        keys.insert(0, generate(message_count + 1).unwrap().0);
+        assert_eq!(sig.verify_any(messages.as_slice(), &keys).unwrap(), Some(3));
+        assert_eq!(sig.verify_any(messages.as_slice(), &[]).unwrap(), None);
+    }
+
+    #[test]
+    fn migrate_blinding_scheme() {
+        let message_count = 3;
+        let messages = FieldElementVector::random(message_count);
+        let (verkey, signkey) = generate(message_count).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+
+        // A store holding the signature in the negated convention
+        let stored = sig.migrate_blinding(BlindingScheme::Additive, BlindingScheme::Negated);
+        assert!(!stored.verify(messages.as_slice(), &verkey).unwrap());
+        let migrated = stored.migrate_blinding(BlindingScheme::Negated, BlindingScheme::Additive);
+        assert_eq!(migrated, sig);
+        assert!(migrated.verify(messages.as_slice(), &verkey).unwrap());
+        assert_eq!(
+            sig.migrate_blinding(BlindingScheme::Additive, BlindingScheme::Additive),
+            sig
+        );
+
+        // A blind signature kept negated by the store, still to be unblinded
+        let blinding = Signature::generate_blinding();
+        let blinded = Signature {
+            a: sig.a.clone(),
+            s: &sig.s - &blinding,
+            e: sig.e.clone(),
+        };
+        let stored = blinded.migrate_blinding(BlindingScheme::Additive, BlindingScheme::Negated);
+        let migrated = stored.migrate_blinding(BlindingScheme::Negated, BlindingScheme::Additive);
+        let unblinded = migrated.get_unblinded_signature(&blinding);
+        assert!(unblinded.verify(messages.as_slice(), &verkey).unwrap());
+    }
+
+    #[test]
+    fn verify_const_time_agrees() {
+        for message_count in &[1, 5, 20] {
+            let messages = FieldElementVector::random(*message_count);
+            let (verkey, signkey) = generate(*message_count).unwrap();
+            let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+            let mut other = messages.clone();
+            other[0] = FieldElement::random();
+            for m in &[messages, other] {
+                assert_eq!(
+                    sig.verify_const_time(m.as_slice(), &verkey).unwrap(),
+                    sig.verify(m.as_slice(), &verkey).unwrap()
+                );
+            }
+            assert!(sig.verify_const_time(&[], &verkey).is_err());
+        }
+    }
+
+    #[test]
+    fn verify_with_nonce_response() {
+        let messages = FieldElementVector::random(3);
+        let (verkey, signkey) = generate(3).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+
+        let response = sig.nonce_response(b"nonce 1");
+        assert!(sig
+            .verify_with_nonce(messages.as_slice(), &verkey, b"nonce 1", &response)
+            .unwrap());
+        // Replayed for another nonce
+        assert!(!sig
+            .verify_with_nonce(messages.as_slice(), &verkey, b"nonce 2", &response)
+            .unwrap());
+        // Response of another signature
+        let other = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+        let response = other.nonce_response(b"nonce 1");
+        assert!(!sig
+            .verify_with_nonce(messages.as_slice(), &verkey, b"nonce 1", &response)
+            .unwrap());
+    }
+
+    #[test]
+    fn sign_batch_independent() {
+        let (verkey, signkey) = generate(3).unwrap();
+        let message_sets: Vec<Vec<SignatureMessage>> = (0..4)
+            .map(|_| FieldElementVector::random(3).as_slice().to_vec())
+            .collect();
+        let sigs = Signature::sign_batch(&message_sets, &signkey, &verkey).unwrap();
+        assert_eq!(sigs.len(), 4);
+        for (sig, messages) in sigs.iter().zip(message_sets.iter()) {
+            assert!(sig.verify(messages.as_slice(), &verkey).unwrap());
+        }
+        for i in 0..sigs.len() {
+            for j in i + 1..sigs.len() {
+                assert_ne!(sigs[i].e, sigs[j].e);
+                assert_ne!(sigs[i].s, sigs[j].s);
+            }
+        }
+
+        let mut bad = message_sets.clone();
+        bad[2].pop();
+        assert!(Signature::sign_batch(&bad, &signkey, &verkey).is_err());
+        assert!(Signature::sign_batch(&[], &signkey, &verkey)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn ct_is_zero_detects_zero() {
+        assert!(bool::from(ct_is_zero(&FieldElement::zero())));
+        assert!(bool::from(ct_is_zero(
+            &(&FieldElement::one() - &FieldElement::one())
+        )));
+        assert!(!bool::from(ct_is_zero(&FieldElement::one())));
+        assert!(!bool::from(ct_is_zero(&FieldElement::random())));
+        let x = FieldElement::random();
+        assert!(bool::from(ct_is_zero(&(&x + &x.negation()))));
+    }
+
+    #[test]
+    fn verify_refs_agrees() {
+        let messages = FieldElementVector::random(4);
+        let (verkey, signkey) = generate(4).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+        let shared = FieldElement::random();
+
+        let refs: Vec<&FieldElement> = messages.iter().collect();
+        assert!(sig.verify_refs(&refs, &verkey).unwrap());
+        let mut other = refs.clone();
+        other[2] = &shared;
+        let owned: Vec<FieldElement> = other.iter().map(|m| (*m).clone()).collect();
+        assert_eq!(
+            sig.verify_refs(&other, &verkey).unwrap(),
+            sig.verify(&owned, &verkey).unwrap()
+        );
+        assert!(!sig.verify_refs(&other, &verkey).unwrap());
+        assert!(sig.verify_refs(&refs[1..], &verkey).is_err());
+    }
+
+    #[test]
+    fn verify_components_recompose() {
+        let messages = FieldElementVector::random(3);
+        let (verkey, signkey) = generate(3).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+        let mut other = messages.clone();
+        other[1] = FieldElement::random();
+
+        for m in &[messages, other] {
+            let components = sig.verify_components(m.as_slice(), &verkey).unwrap();
+            assert_eq!(
+                components.b,
+                compute_b_const_time(&G1::new(), &verkey, m.as_slice(), &sig.s, 0)
+            );
+            assert_eq!(components.w_e, &verkey.w + &(&G2::generator() * &sig.e));
+            let recomposed =
+                GT::ate_2_pairing(&sig.a, &components.w_e, &(-&components.b), &G2::generator());
+            assert_eq!(recomposed, components.pairing);
+            assert_eq!(
+                GT::ate_pairing(&sig.a, &components.w_e)
+                    == GT::ate_pairing(&components.b, &G2::generator()),
+                components.is_valid()
+            );
+            assert_eq!(
+                components.is_valid(),
+                sig.verify(m.as_slice(), &verkey).unwrap()
+            );
+        }
+        assert!(sig.verify_components(&[], &verkey).is_err());
+    }
+
+    #[test]
+    fn verify_batch_mixed() {
+        let message_count = 3;
+        let (verkey_1, signkey_1) = generate(message_count).unwrap();
+        let (verkey_2, signkey_2) = generate(message_count + 1).unwrap();
+        let messages: Vec<Vec<SignatureMessage>> = (0..4)
+            .map(|i| {
+                FieldElementVector::random(message_count + i % 2)
+                    .as_slice()
+                    .to_vec()
+            })
+            .collect();
+        let sigs: Vec<Signature> = messages
+            .iter()
+            .enumerate()
+            .map(|(i, m)| {
+                if i % 2 == 0 {
+                    Signature::new(m, &signkey_1, &verkey_1).unwrap()
+                } else {
+                    Signature::new(m, &signkey_2, &verkey_2).unwrap()
+                }
+            })
+            .collect();
+        let keys = [&verkey_1, &verkey_2];
+        let entries: Vec<(&Signature, &[SignatureMessage], &PublicKey)> = sigs
+            .iter()
+            .zip(messages.iter())
+            .enumerate()
+            .map(|(i, (s, m))| (s, m.as_slice(), keys[i % 2]))
+            .collect();
+
+        metrics::reset();
+        assert!(Signature::verify_batch(&entries).unwrap());
+        // One pairing per key and one for the `G2` generator
+        assert_eq!(metrics::get().pairings, 3);
+        assert!(Signature::verify_batch(&[]).unwrap());
+
+        // One signature over other messages
+        let mut altered = messages[2].clone();
+        altered[0] = FieldElement::random();
+        let mut invalid = entries.clone();
+        invalid[2] = (&sigs[2], altered.as_slice(), &verkey_1);
+        assert!(!Signature::verify_batch(&invalid).unwrap());
+        // Signature under the other key of the same size
+        let (verkey_3, _) = generate(message_count).unwrap();
+        let mut wrong_key = entries.clone();
+        wrong_key[0] = (&sigs[0], messages[0].as_slice(), &verkey_3);
+        assert!(!Signature::verify_batch(&wrong_key).unwrap());
+
+        let mut mismatch = entries.clone();
+        mismatch[1] = (&sigs[1], messages[0].as_slice(), &verkey_2);
+        assert!(Signature::verify_batch(&mismatch).is_err());
+
+        // `a` the identity or outside the subgroup, as a deserialized signature can have
+        let mut bad_a = sigs[1].clone();
+        for a in &[G1::identity(), off_subgroup_point()] {
+            bad_a.a = a.clone();
+            let json = serde_json::to_string(&bad_a).unwrap();
+            let parsed: Signature = serde_json::from_str(&json).unwrap();
+            let mut invalid = entries.clone();
+            invalid[1] = (&parsed, messages[1].as_slice(), &verkey_2);
+            assert!(!Signature::verify_batch(&invalid).unwrap());
+        }
+    }
+
+    // A curve point without cofactor clearing is outside the subgroup
+    fn off_subgroup_point() -> G1 {
+        use amcl_wrapper::ECCurve::big::BIG;
+        use amcl_wrapper::ECCurve::ecp::ECP;
+
+        let mut x = 1;
+        let point = loop {
+            let p = ECP::new_bigint(&BIG::new_int(x), 0);
+            if !p.is_infinity() {
+                break G1::from(p);
+            }
+            x += 1;
+        };
+        assert!(!point.has_correct_order());
+        point
+    }
+
+    #[test]
+    fn thin_signature_round_trip() {
+        let messages = FieldElementVector::random(3);
+        let (verkey, signkey) = generate(3).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+
+        let thin = sig.thin();
+        let fat = thin.fatten(&sig.s);
+        assert_eq!(fat, sig);
+        assert!(fat.verify(messages.as_slice(), &verkey).unwrap());
+        assert!(!thin
+            .fatten(&FieldElement::random())
+            .verify(messages.as_slice(), &verkey)
+            .unwrap());
+    }
+
+    #[test]
+    fn signature_deterministic() {
+        let message_count = 4;
+        let messages = FieldElementVector::random(message_count);
+        let (verkey, signkey) = generate(message_count).unwrap();
+
+        let sig = Signature::new_deterministic(messages.as_slice(), &signkey, &verkey).unwrap();
+        assert!(sig.verify(messages.as_slice(), &verkey).unwrap());
+        let again = Signature::new_deterministic(messages.as_slice(), &signkey, &verkey).unwrap();
+        assert_eq!(sig.to_bytes(), again.to_bytes());
+        assert_eq!(sig.to_bytes().len(), SIGNATURE_SIZE);
+
+        // One changed message byte changes e and s
+        let mut bytes = messages[2].to_bytes();
+        bytes[MODBYTES - 1] ^= 1;
+        let mut changed = messages.clone();
+        changed[2] = FieldElement::from_bytes(&bytes).unwrap();
+        let other = Signature::new_deterministic(changed.as_slice(), &signkey, &verkey).unwrap();
+        assert_ne!(sig.e, other.e);
+        assert_ne!(sig.s, other.s);
+        assert!(other.verify(changed.as_slice(), &verkey).unwrap());
+
+        let (_, other_signkey) = generate(message_count).unwrap();
+        let other =
+            Signature::new_deterministic(messages.as_slice(), &other_signkey, &verkey).unwrap();
+        assert_ne!(sig.e, other.e);
+        assert!(
+            Signature::new_deterministic(&messages.as_slice()[1..], &signkey, &verkey).is_err()
+        );
+    }
+
+    #[test]
+    fn signature_from_bytes_rejects_degenerate_values() {
+        let message_count = 2;
+        let messages = FieldElementVector::random(message_count);
+        let (verkey, signkey) = generate(message_count).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+        let bytes = sig.to_bytes();
+        assert_eq!(Signature::from_bytes(&bytes).unwrap(), sig);
+
+        let mut zero_a = bytes.clone();
+        for b in zero_a[..GroupG1_SIZE].iter_mut() {
+            *b = 0;
+        }
+        let kind = Signature::from_bytes(&zero_a).unwrap_err().kind();
+        assert!(matches!(kind, BBSErrorKind::InvalidSignaturePoint));
+        let mut identity = G1::identity().to_bytes();
+        identity.extend_from_slice(&bytes[GroupG1_SIZE..]);
+        let kind = Signature::from_bytes(&identity).unwrap_err().kind();
+        assert!(matches!(kind, BBSErrorKind::InvalidSignaturePoint));
+
+        let zero = FieldElement::zero().to_bytes();
+        let mut zero_e = bytes.clone();
+        zero_e[GroupG1_SIZE..GroupG1_SIZE + MODBYTES].copy_from_slice(&zero);
+        let kind = Signature::from_bytes(&zero_e).unwrap_err().kind();
+        assert!(matches!(kind, BBSErrorKind::SignatureValueIncorrectSize));
+        let mut zero_s = bytes.clone();
+        zero_s[GroupG1_SIZE + MODBYTES..].copy_from_slice(&zero);
+        let kind = Signature::from_bytes(&zero_s).unwrap_err().kind();
+        assert!(matches!(kind, BBSErrorKind::SignatureValueIncorrectSize));
+    }
+
+    #[test]
+    fn signature_with_params() {
+        use super::super::keys::generate_with_params;
+
+        let message_count = 3;
+        let messages = FieldElementVector::random(message_count);
+        let params = Params::new(G1::random(), G2::random()).unwrap();
+        let (verkey, signkey) = generate_with_params(message_count, &params).unwrap();
+        assert_eq!(verkey.w, &params.g2 * &signkey);
+
+        let sig =
+            Signature::new_with_params(messages.as_slice(), &signkey, &verkey, &params).unwrap();
+        assert!(sig
+            .verify_with_params(messages.as_slice(), &verkey, &params)
+            .unwrap());
+        // Not under the default generators
+        assert!(!sig.verify(messages.as_slice(), &verkey).unwrap());
+        let other = Params::new(G1::random(), params.g2.clone()).unwrap();
+        assert!(!sig
+            .verify_with_params(messages.as_slice(), &verkey, &other)
+            .unwrap());
+
+        // The default params are today's generators
+        let (verkey, signkey) = generate(message_count).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+        assert!(sig
+            .verify_with_params(messages.as_slice(), &verkey, &Params::default())
+            .unwrap());
+        let sig =
+            Signature::new_with_params(messages.as_slice(), &signkey, &verkey, &Params::default())
+                .unwrap();
+        assert!(sig.verify(messages.as_slice(), &verkey).unwrap());
+
+        assert!(Params::new(G1::identity(), G2::generator()).is_err());
+        assert!(Params::new(G1::generator(), G2::identity()).is_err());
+    }
+
+    #[test]
+    fn signature_with_params_pippenger() {
+        use super::super::keys::generate_with_params;
+
+        // Enough messages that verification goes through Pippenger
+        let message_count = PIPPENGER_THRESHOLD;
+        let messages = FieldElementVector::random(message_count);
+        let params = Params::new(G1::random(), G2::random()).unwrap();
+        let (verkey, signkey) = generate_with_params(message_count, &params).unwrap();
+        let blinding = FieldElement::random();
+        assert_eq!(
+            compute_b_pippenger_with_params(
+                &params,
+                &G1::new(),
+                &verkey,
+                messages.as_slice(),
+                &blinding,
+                0
+            ),
+            compute_b_const_time_with_params(
+                &params,
+                &G1::new(),
+                &verkey,
+                messages.as_slice(),
+                &blinding,
+                0
+            )
+        );
+
+        let sig =
+            Signature::new_with_params(messages.as_slice(), &signkey, &verkey, &params).unwrap();
+        assert!(sig
+            .verify_with_params(messages.as_slice(), &verkey, &params)
+            .unwrap());
+        assert!(!sig.verify(messages.as_slice(), &verkey).unwrap());
+        let mut messages = messages;
+        messages[3] = FieldElement::random();
+        assert!(!sig
+            .verify_with_params(messages.as_slice(), &verkey, &params)
+            .unwrap());
+    }
+
+    #[test]
+    fn signature_verify_prepared() {
+        let message_count = 4;
+        let messages = FieldElementVector::random(message_count);
+        let (verkey, signkey) = generate(message_count).unwrap();
+        let verifier = PreparedVerifier::new(&verkey);
+
+        for _ in 0..3 {
+            let e = FieldElement::random();
+            assert_eq!(verifier.g2_mul(&e), &G2::generator() * &e);
+        }
+        assert_eq!(verifier.g2_mul(&FieldElement::zero()), G2::identity());
+        assert_eq!(verifier.g2_mul(&FieldElement::one()), G2::generator());
+
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+        assert!(sig.verify_prepared(messages.as_slice(), &verifier).unwrap());
+        let mut changed = messages.clone();
+        changed[0] = FieldElement::random();
+        assert_eq!(
+            sig.verify_prepared(changed.as_slice(), &verifier).unwrap(),
+            sig.verify(changed.as_slice(), &verkey).unwrap()
+        );
+        assert!(!sig.verify_prepared(changed.as_slice(), &verifier).unwrap());
+        assert!(sig
+            .verify_prepared(&messages.as_slice()[1..], &verifier)
+            .is_err());
+    }
+
+    #[test]
+    fn signature_eq_ignoring_blinding() {
+        let messages = FieldElementVector::random(3);
+        let (verkey, signkey) = generate(3).unwrap();
+        let sig = Signature::new_deterministic(messages.as_slice(), &signkey, &verkey).unwrap();
+
+        let reblinded_1 = sig.get_unblinded_signature(&Signature::generate_blinding());
+        let reblinded_2 = sig.get_unblinded_signature(&Signature::generate_blinding());
+        assert_ne!(reblinded_1, reblinded_2);
+        assert!(reblinded_1.eq_ignoring_blinding(&reblinded_2));
+        assert!(sig.eq_ignoring_blinding(&reblinded_1));
+        assert!(!reblinded_1.verify(messages.as_slice(), &verkey).unwrap());
+
+        let again = Signature::new_deterministic(messages.as_slice(), &signkey, &verkey).unwrap();
+        assert!(sig.eq_ignoring_blinding(&again));
+        let other = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+        assert!(!sig.eq_ignoring_blinding(&other));
+    }
+
+    #[test]
+    fn signature_compressed_bytes() {
+        let messages = FieldElementVector::random(2);
+        let (verkey, signkey) = generate(2).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+
+        let compressed = sig.to_bytes_compressed();
+        assert_eq!(compressed.len(), SIGNATURE_COMPRESSED_SIZE);
+        assert!(compressed.len() < sig.to_bytes().len());
+        assert_eq!(
+            &compressed[G1_COMPRESSED_SIZE..],
+            &sig.to_bytes()[GroupG1_SIZE..]
+        );
+        let parsed = Signature::from_bytes_compressed(&compressed).unwrap();
+        assert_eq!(parsed, sig);
+        assert!(parsed.verify(messages.as_slice(), &verkey).unwrap());
+
+        assert!(Signature::from_bytes_compressed(&sig.to_bytes()).is_err());
+        assert!(Signature::from_bytes_compressed(&compressed[1..]).is_err());
+        let mut zero_e = compressed.clone();
+        for b in zero_e[G1_COMPRESSED_SIZE..G1_COMPRESSED_SIZE + MODBYTES].iter_mut() {
+            *b = 0;
+        }
+        assert!(Signature::from_bytes_compressed(&zero_e).is_err());
+    }
+
+    #[test]
+    fn signature_verify_var_time() {
+        let messages = FieldElementVector::random(3);
+        let (verkey, signkey) = generate(3).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+        let mut changed = messages.clone();
+        changed[1] = FieldElement::random();
+
+        for m in &[messages, changed] {
+            let expected = sig.verify(m.as_slice(), &verkey).unwrap();
+            assert_eq!(
+                sig.verify_var_time(m.as_slice(), &verkey).unwrap(),
+                expected
+            );
+            assert_eq!(
+                sig.verify_const_time(m.as_slice(), &verkey).unwrap(),
+                expected
+            );
+        }
+        assert!(sig.verify_var_time(&[], &verkey).is_err());
+    }
+
+    #[test]
+    fn signature_committed_messages_nonce() {
+        let message_count = 3;
+        let messages = FieldElementVector::random(message_count);
+        let (verkey, signkey) = generate(message_count).unwrap();
+        let blinding = Signature::generate_blinding();
+        let commitment = &verkey.h0 * &blinding + &verkey.h[0] * &messages[0];
+        let bases = vec![verkey.h0.clone(), verkey.h[0].clone()];
+
+        // The signer picks a fresh nonce per session
+        let nonce_a = FieldElement::random().to_bytes();
+        let nonce_b = FieldElement::random().to_bytes();
+
+        let mut committing = ProverCommittingG1::new();
+        committing.commit(&verkey.h0, None);
+        committing.commit(&verkey.h[0], None);
+        let committed = committing.finish();
+        let challenge = committed.gen_challenge_with_nonce(commitment.to_bytes(), &nonce_a);
+        let proof = committed
+            .gen_proof(&challenge, &[blinding.clone(), messages[0].clone()])
+            .unwrap();
+
+        assert!(proof
+            .verify_with_nonce(&bases, &commitment, commitment.to_bytes(), &nonce_a)
+            .unwrap());
+        // Replayed into another session
+        assert!(!proof
+            .verify_with_nonce(&bases, &commitment, commitment.to_bytes(), &nonce_b)
+            .unwrap());
+        assert!(!proof
+            .verify_with_nonce(&bases, &commitment, commitment.to_bytes(), &[])
+            .unwrap());
+
+        let sig = Signature::new_with_committed_messages(
+            &commitment,
+            &messages.as_slice()[1..],
+            &signkey,
+            &verkey,
+        )
+        .unwrap()
+        .get_unblinded_signature(&blinding);
+        assert!(sig.verify(messages.as_slice(), &verkey).unwrap());
+    }
+
+    #[test]
+    fn signature_message_count_errors() {
+        let messages = FieldElementVector::random(4);
+        let (verkey, signkey) = generate(3).unwrap();
+        let too_few = Signature::new(&messages.as_slice()[..2], &signkey, &verkey).unwrap_err();
+        match too_few.kind() {
+            BBSErrorKind::TooFewMessages { expected, got } => assert_eq!((expected, got), (3, 2)),
+            kind => panic!("unexpected {:?}", kind),
+        }
+        let too_many = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap_err();
+        match too_many.kind() {
+            BBSErrorKind::TooManyMessages { expected, got } => assert_eq!((expected, got), (3, 4)),
+            kind => panic!("unexpected {:?}", kind),
+        }
+        let err = Signature::new(&[], &signkey, &verkey).unwrap_err();
+        assert_eq!(err.code(), 11);
+
+        // Fewer known messages than bases are the blind case, more never fit
+        let commitment = &verkey.h[0] * &messages[0];
+        assert!(Signature::new_with_committed_messages(
+            &commitment,
+            &messages.as_slice()[1..3],
+            &signkey,
+            &verkey
+        )
+        .is_ok());
+        let err = Signature::new_with_committed_messages(
+            &commitment,
+            messages.as_slice(),
+            &signkey,
+            &verkey,
+        )
+        .unwrap_err();
+        match err.kind() {
+            BBSErrorKind::TooManyMessages { expected, got } => assert_eq!((expected, got), (3, 4)),
+            kind => panic!("unexpected {:?}", kind),
+        }
+        let sig = Signature::new(&messages.as_slice()[..3], &signkey, &verkey).unwrap();
+        assert_eq!(
+            sig.verify(&messages.as_slice()[..2], &verkey)
+                .unwrap_err()
+                .code(),
+            11
+        );
+    }
+
+    #[test]
+    fn unblinded_signature_checked() {
+        let message_count = 3;
+        let messages = FieldElementVector::random(message_count);
+        let (verkey, signkey) = generate(message_count).unwrap();
+        let blinding = Signature::generate_blinding();
+        let commitment = &verkey.h0 * &blinding + &verkey.h[0] * &messages[0];
+
+        let blind_sig = Signature::new_with_committed_messages(
+            &commitment,
+            &messages.as_slice()[1..],
+            &signkey,
+            &verkey,
+        )
+        .unwrap();
+        let sig = blind_sig
+            .get_unblinded_signature_checked(&blinding, messages.as_slice(), &verkey)
+            .unwrap();
+        assert_eq!(sig, blind_sig.get_unblinded_signature(&blinding));
+
+        // A signer that signs another committed value
+        let cheating = Signature::new_with_committed_messages(
+            &(&verkey.h0 * &blinding + &verkey.h[0] * &FieldElement::random()),
+            &messages.as_slice()[1..],
+            &signkey,
+            &verkey,
+        )
+        .unwrap();
+        let err = cheating
+            .get_unblinded_signature_checked(&blinding, messages.as_slice(), &verkey)
+            .unwrap_err();
+        assert_eq!(err.code(), BBSErrorKind::InvalidBlindSignature.code());
+        assert!(blind_sig
+            .get_unblinded_signature_checked(
+                &Signature::generate_blinding(),
+                messages.as_slice(),
+                &verkey
+            )
+            .is_err());
+        // Wrong number of messages is reported as such
+        let err = blind_sig
+            .get_unblinded_signature_checked(&blinding, &messages.as_slice()[1..], &verkey)
+            .unwrap_err();
+        assert_eq!(err.code(), 11);
+    }
+
+    #[test]
+    fn commitment_to_a() {
+        let messages = FieldElementVector::random(2);
+        let (verkey, signkey) = generate(2).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+
+        let blinding = FieldElement::random();
+        let commitment = sig.commit_to_a(&verkey, &blinding);
+        assert_ne!(commitment, sig.a);
+        assert!(sig.opens_a_commitment(&commitment, &verkey, &blinding));
+        assert!(!sig.opens_a_commitment(&commitment, &verkey, &FieldElement::random()));
+        let other = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+        assert!(!other.opens_a_commitment(&commitment, &verkey, &blinding));
+        // Fresh blindings give unlinkable commitments
+        assert_ne!(
+            sig.commit_to_a(&verkey, &FieldElement::random()),
+            commitment
+        );
+    }
+
+    #[test]
+    fn signature_from_either_encoding() {
+        let messages = FieldElementVector::random(2);
+        let (verkey, signkey) = generate(2).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+
+        let uncompressed = sig.to_bytes();
+        let compressed = sig.to_bytes_compressed();
+        assert_eq!(Signature::from_bytes(&uncompressed).unwrap(), sig);
+        assert_eq!(Signature::from_bytes(&compressed).unwrap(), sig);
+
+        let mut framed = compressed.clone();
+        framed.extend_from_slice(&[1, 2, 3]);
+        assert_eq!(
+            Signature::from_bytes_prefix(&framed).unwrap(),
+            (sig.clone(), SIGNATURE_COMPRESSED_SIZE)
+        );
+        let mut framed = uncompressed;
+        framed.extend_from_slice(&[1, 2, 3]);
+        assert_eq!(
+            Signature::from_bytes_prefix(&framed).unwrap(),
+            (sig, SIGNATURE_SIZE)
+        );
+
+        // A compressed length with an uncompressed prefix is neither
+        let mut wrong_prefix = compressed;
+        wrong_prefix[0] = 0x04;
+        assert!(Signature::from_bytes(&wrong_prefix).is_err());
+        assert!(Signature::from_bytes(&wrong_prefix[1..]).is_err());
+    }
+
+    #[test]
+    fn signature_deterministic_tagged() {
+        let messages = FieldElementVector::random(3);
+        let (verkey, signkey) = generate(3).unwrap();
+        let tagged = |tag: &[u8]| {
+            Signature::new_deterministic_tagged(messages.as_slice(), &signkey, &verkey, tag)
+                .unwrap()
+        };
+
+        let sig_a = tagged(b"issuer-a");
+        let sig_b = tagged(b"issuer-b");
+        assert_ne!(sig_a, sig_b);
+        assert_eq!(sig_a, tagged(b"issuer-a"));
+        assert!(sig_a.verify(messages.as_slice(), &verkey).unwrap());
+        assert!(sig_b.verify(messages.as_slice(), &verkey).unwrap());
+        assert_eq!(
+            tagged(b""),
+            Signature::new_deterministic(messages.as_slice(), &signkey, &verkey).unwrap()
+        );
+        assert_ne!(tagged(b""), sig_a);
+    }
+
+    #[test]
+    fn verify_batch_powers() {
+        let (verkey, signkey) = generate(1).unwrap();
+        let messages = FieldElementVector::random(64);
+        let sigs: Vec<Signature> = messages
+            .iter()
+            .map(|m| Signature::new(std::slice::from_ref(m), &signkey, &verkey).unwrap())
+            .collect();
+        let messages: Vec<Vec<SignatureMessage>> =
+            messages.iter().map(|m| vec![m.clone()]).collect();
+
+        for size in &[1, 2, 5, 64] {
+            let mut batch: Vec<Signature> = sigs[..*size].to_vec();
+            let entries = |batch: &[Signature]| {
+                let entries: Vec<(&Signature, &[SignatureMessage], &PublicKey)> = batch
+                    .iter()
+                    .zip(messages.iter())
+                    .map(|(s, m)| (s, m.as_slice(), &verkey))
+                    .collect();
+                Signature::verify_batch(&entries).unwrap()
+            };
+            assert!(entries(&batch));
+            for bad in &[0, size / 2, size - 1] {
+                let good = batch[*bad].clone();
+                batch[*bad].s = FieldElement::random();
+                assert!(!entries(&batch));
+                // A malformed `a` with the rest of the entry intact
+                batch[*bad] = good.clone();
+                batch[*bad].a = off_subgroup_point();
+                assert!(!entries(&batch));
+                batch[*bad].a = &good.a + &off_subgroup_point();
+                assert!(!entries(&batch));
+                batch[*bad] = good;
+            }
+        }
+    }
+
+    #[test]
+    fn verify_in_scratch() {
+        let mut scratch = VerifyScratch::new();
+        for message_count in &[4, 1, 6, 4] {
+            let messages = FieldElementVector::random(*message_count);
+            let (verkey, signkey) = generate(*message_count).unwrap();
+            let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+            let mut changed = messages.clone();
+            changed[0] = FieldElement::random();
+            for m in &[messages, changed] {
+                assert_eq!(
+                    sig.verify_in(m.as_slice(), &verkey, &mut scratch).unwrap(),
+                    sig.verify(m.as_slice(), &verkey).unwrap()
+                );
+            }
+            assert!(sig.verify_in(&[], &verkey, &mut scratch).is_err());
+        }
+    }
+
+    #[test]
+    fn verify_lazy_matches_verify() {
+        use std::cell::Cell;
+
+        let message_count = LAZY_CHUNK_SIZE + 6;
+        let messages = FieldElementVector::random(message_count);
+        let (verkey, signkey) = generate(message_count).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+
+        let fetched = Cell::new(0);
+        let fetch = |i: usize| {
+            assert_eq!(i, fetched.get());
+            fetched.set(i + 1);
+            messages[i].clone()
+        };
+        assert!(sig.verify_lazy(message_count, fetch, &verkey).unwrap());
+        assert_eq!(fetched.get(), message_count);
+
+        let changed = |i: usize| {
+            if i == message_count - 1 {
+                FieldElement::random()
+            } else {
+                messages[i].clone()
+            }
+        };
+        assert!(!sig.verify_lazy(message_count, changed, &verkey).unwrap());
+        assert!(sig
+            .verify_lazy(message_count - 1, |i| messages[i].clone(), &verkey)
+            .is_err());
+    }
 }
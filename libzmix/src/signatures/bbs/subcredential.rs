@@ -0,0 +1,165 @@
+// Sub-credentials: one message of a parent credential is a Pedersen commitment to the messages
+// of another credential, so the parent signer vouches for a set of values it never saw.
+//
+// The holder commits to the sub-credential messages with `commit_subcredential` and hands the
+// commitment to the parent signer, who signs `subcredential_message(commitment)` as one of the
+// parent messages. Later the holder shows a verifier the commitment and its opening and
+// `verify_subcredential` checks both that the parent signature holds and that the opening is
+// the one committed to. Any party who can open the commitment can thus show values as endorsed
+// by the parent signer, so this delegates: the signer endorses whatever the holder committed
+// to, nothing more is checked about the values. The commitment is binding under the discrete
+// log assumption and hiding because of its blinding, but the parent message is a hash of it:
+// the link can only be checked with the commitment in the clear, it can't be proven in zero
+// knowledge with `PoKOfSignature`, and every presentation of the same commitment is linkable.
+
+use super::super::SignatureMessage;
+use super::keys::PublicKey;
+use super::signature::Signature;
+use crate::errors::prelude::*;
+
+use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
+use amcl_wrapper::group_elem::{GroupElement, GroupElementVector};
+use amcl_wrapper::group_elem_g1::{G1Vector, G1};
+
+pub mod prelude {
+    pub use super::{commit_subcredential, subcredential_message, SubCredentialOpening};
+}
+
+const BASES_DST: &[u8] = b"BBS+ sub-credential bases";
+const MESSAGE_DST: &[u8] = b"BBS+ sub-credential message";
+
+/// Messages of a sub-credential and the blinding of their commitment
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubCredentialOpening {
+    pub messages: Vec<SignatureMessage>,
+    pub blinding: FieldElement,
+}
+
+impl SubCredentialOpening {
+    /// `g_0^blinding * g_1^m_1 * ... * g_n^m_n` with every `g_i` hashed to the curve from `i`
+    /// and the number of messages, so no discrete log relation between them is known
+    pub fn commitment(&self) -> G1 {
+        let count = self.messages.len();
+        let mut bases = G1Vector::with_capacity(count + 1);
+        let mut scalars = FieldElementVector::with_capacity(count + 1);
+        bases.push(base(0, count));
+        scalars.push(self.blinding.clone());
+        for (i, m) in self.messages.iter().enumerate() {
+            bases.push(base(i + 1, count));
+            scalars.push(m.clone());
+        }
+        bases.multi_scalar_mul_const_time(&scalars).unwrap()
+    }
+}
+
+fn base(index: usize, count: usize) -> G1 {
+    let mut bytes = BASES_DST.to_vec();
+    bytes.extend_from_slice(&(index as u32).to_be_bytes());
+    bytes.extend_from_slice(&(count as u32).to_be_bytes());
+    G1::from_msg_hash(&bytes)
+}
+
+/// Commit to the sub-credential `messages` with a random blinding. Returns the commitment for
+/// the parent signer and the opening the holder keeps.
+pub fn commit_subcredential(
+    messages: &[SignatureMessage],
+) -> Result<(G1, SubCredentialOpening), BBSError> {
+    if messages.is_empty() {
+        return Err(BBSError::from_kind(BBSErrorKind::GeneralError {
+            msg: "A sub-credential needs at least one message".to_string(),
+        }));
+    }
+    let opening = SubCredentialOpening {
+        messages: messages.to_vec(),
+        blinding: FieldElement::random(),
+    };
+    Ok((opening.commitment(), opening))
+}
+
+/// The parent message standing for `commitment`
+pub fn subcredential_message(commitment: &G1) -> SignatureMessage {
+    let mut bytes = MESSAGE_DST.to_vec();
+    bytes.append(&mut commitment.to_bytes());
+    FieldElement::from_msg_hash(&bytes)
+}
+
+impl Signature {
+    /// Check that `self` is a parent signature whose message at `index` is `commitment` and
+    /// that `sub_opening` opens it, see the top of `subcredential.rs`. `parent_messages` are
+    /// all parent messages, the one at `index` included.
+    pub fn verify_subcredential(
+        &self,
+        parent_messages: &[SignatureMessage],
+        index: usize,
+        commitment: &G1,
+        sub_opening: &SubCredentialOpening,
+        verkey: &PublicKey,
+    ) -> Result<bool, BBSError> {
+        let message = parent_messages.get(index).ok_or_else(|| {
+            BBSError::from_kind(BBSErrorKind::GeneralError {
+                msg: format!(
+                    "Index {} should be less than {}",
+                    index,
+                    parent_messages.len()
+                ),
+            })
+        })?;
+        if *message != subcredential_message(commitment) {
+            return Ok(false);
+        }
+        if sub_opening.commitment() != *commitment {
+            return Ok(false);
+        }
+        self.verify(parent_messages, verkey)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use signatures::bbs::keys::generate;
+
+    #[test]
+    fn subcredential_link() {
+        let sub_messages = FieldElementVector::random(3);
+        let (commitment, opening) = commit_subcredential(sub_messages.as_slice()).unwrap();
+
+        let (verkey, signkey) = generate(2).unwrap();
+        let parent_messages = vec![FieldElement::random(), subcredential_message(&commitment)];
+        let parent = Signature::new(&parent_messages, &signkey, &verkey).unwrap();
+        assert!(parent
+            .verify_subcredential(&parent_messages, 1, &commitment, &opening, &verkey)
+            .unwrap());
+
+        // Another opening, commitment or index doesn't link
+        let mut wrong_opening = opening.clone();
+        wrong_opening.messages[0] = FieldElement::random();
+        assert!(!parent
+            .verify_subcredential(&parent_messages, 1, &commitment, &wrong_opening, &verkey)
+            .unwrap());
+        let mut wrong_blinding = opening.clone();
+        wrong_blinding.blinding = FieldElement::random();
+        assert!(!parent
+            .verify_subcredential(&parent_messages, 1, &commitment, &wrong_blinding, &verkey)
+            .unwrap());
+        let (other, other_opening) = commit_subcredential(sub_messages.as_slice()).unwrap();
+        assert_ne!(other, commitment);
+        assert!(!parent
+            .verify_subcredential(&parent_messages, 1, &other, &other_opening, &verkey)
+            .unwrap());
+        assert!(!parent
+            .verify_subcredential(&parent_messages, 0, &commitment, &opening, &verkey)
+            .unwrap());
+        assert!(parent
+            .verify_subcredential(&parent_messages, 2, &commitment, &opening, &verkey)
+            .is_err());
+
+        // The parent signature has to hold as well
+        let (_, other_signkey) = generate(2).unwrap();
+        let forged = Signature::new(&parent_messages, &other_signkey, &verkey).unwrap();
+        assert!(!forged
+            .verify_subcredential(&parent_messages, 1, &commitment, &opening, &verkey)
+            .unwrap());
+        assert!(commit_subcredential(&[]).is_err());
+    }
+}
@@ -0,0 +1,155 @@
+use super::super::SignatureMessage;
+use super::keys::PublicKey;
+use super::signature::{compute_b_var_time, Signature};
+use crate::errors::prelude::*;
+
+use amcl_wrapper::extension_field_gt::GT;
+use amcl_wrapper::field_elem::FieldElement;
+use amcl_wrapper::group_elem::GroupElement;
+use amcl_wrapper::group_elem_g1::G1;
+use amcl_wrapper::group_elem_g2::G2;
+
+pub mod prelude {
+    pub use super::Transcript;
+}
+
+/// Record of a signature verification: every public input, the intermediate values `b` and
+/// `g2 * e + w` and the value of `e(a, g2 * e + w) * e(-b, g2)` that decided the result.
+///
+/// `check_consistency` lets a third party confirm without any pairing that the inputs lead to
+/// the recorded intermediate values and that the result matches the recorded pairing value, so
+/// inputs or result can't be changed after the fact. It takes the pairing value on trust, only
+/// `recheck` which computes the pairing again shows the signature is valid. For
+/// non-repudiation the verifier signs `digest` with a key of its own.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Transcript {
+    pub signature: Signature,
+    pub messages: Vec<SignatureMessage>,
+    pub verkey: PublicKey,
+    pub b: G1,
+    pub g2_term: G2,
+    pub pairing: Vec<u8>,
+    pub result: bool,
+}
+
+impl Transcript {
+    /// True if the recorded values follow from the inputs and the recorded pairing value
+    pub fn check_consistency(&self) -> Result<bool, BBSError> {
+        if self.messages.len() != self.verkey.message_count() {
+            return Ok(false);
+        }
+        let b = compute_b_var_time(
+            &G1::new(),
+            &self.verkey,
+            self.messages.as_slice(),
+            &self.signature.s,
+            0,
+        );
+        let g2_term = (&G2::generator() * &self.signature.e) + &self.verkey.w;
+        let pairing = GT::from_bytes(self.pairing.as_slice()).map_err(|_| {
+            BBSError::from_kind(BBSErrorKind::GeneralError {
+                msg: "Malformed pairing value".to_string(),
+            })
+        })?;
+        Ok(b == self.b && g2_term == self.g2_term && pairing.is_one() == self.result)
+    }
+
+    /// Verify the recorded signature again, pairing included
+    pub fn recheck(&self) -> Result<bool, BBSError> {
+        Ok(self.check_consistency()?
+            && self
+                .signature
+                .verify(self.messages.as_slice(), &self.verkey)?
+                == self.result)
+    }
+
+    /// Hash over every recorded value in a fixed order
+    pub fn digest(&self) -> FieldElement {
+        let mut bytes = self.signature.to_bytes();
+        bytes.extend_from_slice(&(self.messages.len() as u32).to_be_bytes());
+        for m in &self.messages {
+            bytes.append(&mut m.to_bytes());
+        }
+        bytes.append(&mut self.verkey.to_bytes());
+        bytes.append(&mut self.b.to_bytes());
+        bytes.append(&mut self.g2_term.to_bytes());
+        bytes.extend_from_slice(self.pairing.as_slice());
+        bytes.push(self.result as u8);
+        FieldElement::from_msg_hash(&bytes)
+    }
+}
+
+impl Signature {
+    /// `verify` that also returns a `Transcript` of the verification
+    pub fn verify_with_transcript(
+        &self,
+        messages: &[SignatureMessage],
+        verkey: &PublicKey,
+    ) -> Result<(bool, Transcript), BBSError> {
+        if messages.len() != verkey.message_count() {
+            return Err(BBSError::from_kind(BBSErrorKind::message_count(
+                verkey.message_count(),
+                messages.len(),
+            )));
+        }
+        let b = compute_b_var_time(&G1::new(), verkey, messages, &self.s, 0);
+        let g2_term = (&G2::generator() * &self.e) + &verkey.w;
+        let pairing = GT::ate_2_pairing(&self.a, &g2_term, &(-&b), &G2::generator());
+        let result = pairing.is_one();
+        let transcript = Transcript {
+            signature: self.clone(),
+            messages: messages.to_vec(),
+            verkey: verkey.clone(),
+            b,
+            g2_term,
+            pairing: pairing.to_bytes(),
+            result,
+        };
+        Ok((result, transcript))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amcl_wrapper::field_elem::FieldElementVector;
+    use signatures::bbs::keys::generate;
+    use signatures::bbs::metrics;
+
+    #[test]
+    fn verification_transcript() {
+        let message_count = 3;
+        let messages = FieldElementVector::random(message_count);
+        let (verkey, signkey) = generate(message_count).unwrap();
+        let sig = Signature::new(messages.as_slice(), &signkey, &verkey).unwrap();
+
+        let (valid, transcript) = sig
+            .verify_with_transcript(messages.as_slice(), &verkey)
+            .unwrap();
+        assert!(valid);
+        let json = serde_json::to_string(&transcript).unwrap();
+        let transcript: Transcript = serde_json::from_str(&json).unwrap();
+        metrics::reset();
+        assert!(transcript.check_consistency().unwrap());
+        assert_eq!(metrics::get().pairings, 0);
+        assert!(transcript.recheck().unwrap());
+
+        // A failed verification is recorded as such
+        let other = FieldElementVector::random(message_count);
+        let (valid, failed) = sig
+            .verify_with_transcript(other.as_slice(), &verkey)
+            .unwrap();
+        assert!(!valid);
+        assert!(failed.check_consistency().unwrap());
+        assert!(failed.recheck().unwrap());
+        assert_ne!(failed.digest(), transcript.digest());
+
+        // Flipping the result or swapping the messages afterwards is caught
+        let mut flipped = failed.clone();
+        flipped.result = true;
+        assert!(!flipped.check_consistency().unwrap());
+        let mut swapped = transcript.clone();
+        swapped.messages = other.as_slice().to_vec();
+        assert!(!swapped.check_consistency().unwrap());
+    }
+}
@@ -0,0 +1,269 @@
+// Signing a list as a single message through a vector commitment, so that a single position
+// can be opened later without revealing the rest of the list.
+//
+// The commitment to `x_0, ..., x_{n-1}` is the Pedersen vector commitment
+// `C = u^r * g_0^x_0 * ... * g_{n-1}^x_{n-1}` where `r` is a random blinding and `u`, `g_i`
+// are hashed to the curve so nobody knows discrete logs between them, which makes `C` binding
+// to every position. The signer signs `H(C || n)` as the only message of a key, so the length
+// `n` is signed with the commitment and a proof can't claim positions past the end of the list.
+// To show that `x_i` is at position `i` the holder reveals `C`, `i` and `x_i` and proves
+// knowledge of `r` and every other `x_j` in `C / g_i^x_i = u^r * prod_{j != i} g_j^x_j`.
+// The other elements stay hidden but `C` and the signature are revealed so showings are
+// linkable.
+
+use super::super::SignatureMessage;
+use super::keys::{PublicKey, SecretKey};
+use super::pok_sig::{ProofG1, ProverCommittingG1};
+use super::signature::Signature;
+use crate::errors::prelude::*;
+
+use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
+use amcl_wrapper::group_elem::{GroupElement, GroupElementVector};
+use amcl_wrapper::group_elem_g1::{G1Vector, G1};
+
+pub mod prelude {
+    pub use super::{prove_position, PositionProof, VectorOpening};
+}
+
+const BLINDING_BASE: &[u8] = b"BBS vector commitment blinding";
+const ELEMENT_BASE: &[u8] = b"BBS vector commitment element";
+
+fn blinding_base() -> G1 {
+    G1::from_msg_hash(BLINDING_BASE)
+}
+
+// Positions and lengths are encoded in 4 bytes, longer lists are refused
+fn encode_position(n: usize) -> Option<[u8; 4]> {
+    if n > u32::MAX as usize {
+        return None;
+    }
+    Some((n as u32).to_be_bytes())
+}
+
+// `index` fits in 4 bytes, callers check with `encode_position`
+fn element_base(index: usize) -> G1 {
+    let mut bytes = ELEMENT_BASE.to_vec();
+    bytes.extend_from_slice(&(index as u32).to_be_bytes());
+    G1::from_msg_hash(&bytes)
+}
+
+// Message that gets signed for a commitment to `length` elements, `length` fits in 4 bytes
+fn commitment_message(commitment: &G1, length: usize) -> SignatureMessage {
+    let mut bytes = commitment.to_bytes();
+    bytes.extend_from_slice(&(length as u32).to_be_bytes());
+    FieldElement::from_msg_hash(&bytes)
+}
+
+/// Everything the holder needs to open positions of a signed vector commitment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VectorOpening {
+    pub commitment: G1,
+    pub elements: Vec<SignatureMessage>,
+    blinding: FieldElement,
+}
+
+impl Signature {
+    /// Commit to `elements` and sign the commitment as the only message of `verkey`.
+    pub fn new_vector_commitment(
+        elements: &[SignatureMessage],
+        signkey: &SecretKey,
+        verkey: &PublicKey,
+    ) -> Result<(Self, VectorOpening), BBSError> {
+        if verkey.message_count() != 1 {
+            return Err(BBSError::from_kind(BBSErrorKind::GeneralError {
+                msg: format!(
+                    "Key must have exactly 1 message base, has {}",
+                    verkey.message_count()
+                ),
+            }));
+        }
+        if elements.is_empty() || encode_position(elements.len()).is_none() {
+            return Err(BBSError::from_kind(BBSErrorKind::GeneralError {
+                msg: format!(
+                    "Vector commitment needs 1 to {} elements, got {}",
+                    u32::MAX,
+                    elements.len()
+                ),
+            }));
+        }
+        let blinding = FieldElement::random();
+        let mut bases = G1Vector::with_capacity(elements.len() + 1);
+        let mut scalars = FieldElementVector::with_capacity(elements.len() + 1);
+        bases.push(blinding_base());
+        scalars.push(blinding.clone());
+        for (i, e) in elements.iter().enumerate() {
+            bases.push(element_base(i));
+            scalars.push(e.clone());
+        }
+        let commitment = bases.multi_scalar_mul_const_time(&scalars).unwrap();
+        let signature = Signature::new(
+            &[commitment_message(&commitment, elements.len())],
+            signkey,
+            verkey,
+        )?;
+        Ok((
+            signature,
+            VectorOpening {
+                commitment,
+                elements: elements.to_vec(),
+                blinding,
+            },
+        ))
+    }
+}
+
+/// Proof that `element` is at position `index` of a signed vector commitment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PositionProof {
+    pub commitment: G1,
+    pub index: usize,
+    pub element: SignatureMessage,
+    pub length: usize,
+    pub proof: ProofG1,
+}
+
+pub fn prove_position(
+    index: usize,
+    element: &SignatureMessage,
+    opening: &VectorOpening,
+) -> Result<PositionProof, BBSError> {
+    if opening.elements.get(index) != Some(element) {
+        return Err(BBSError::from_kind(BBSErrorKind::GeneralError {
+            msg: format!("Element is not at position {}", index),
+        }));
+    }
+    let length = opening.elements.len();
+    let bases = hidden_bases(index, length);
+    let mut secrets = FieldElementVector::with_capacity(length);
+    secrets.push(opening.blinding.clone());
+    let mut committing = ProverCommittingG1::new();
+    for b in bases.as_slice() {
+        committing.commit(b, None);
+    }
+    for (j, e) in opening.elements.iter().enumerate() {
+        if j != index {
+            secrets.push(e.clone());
+        }
+    }
+    let committed = committing.finish();
+    let challenge = position_challenge(
+        &opening.commitment,
+        index,
+        element,
+        length,
+        &committed.commitment,
+    );
+    let proof = committed.gen_proof(&challenge, secrets.as_slice())?;
+    Ok(PositionProof {
+        commitment: opening.commitment.clone(),
+        index,
+        element: element.clone(),
+        length,
+        proof,
+    })
+}
+
+impl PositionProof {
+    /// Check that `signature` is over `self.commitment` and `self.length` and that the element
+    /// is at its position. `length` comes from the holder, it is checked against the signature
+    /// before anything of that size is computed.
+    pub fn verify(&self, signature: &Signature, verkey: &PublicKey) -> Result<bool, BBSError> {
+        if self.index >= self.length || encode_position(self.length).is_none() {
+            return Ok(false);
+        }
+        if !signature.verify(&[commitment_message(&self.commitment, self.length)], verkey)? {
+            return Ok(false);
+        }
+        let bases = hidden_bases(self.index, self.length);
+        // C / g_i^x_i
+        let rest = &self.commitment - &(&element_base(self.index) * &self.element);
+        let challenge = position_challenge(
+            &self.commitment,
+            self.index,
+            &self.element,
+            self.length,
+            &self.proof.commitment,
+        );
+        Ok(self.proof.verify(bases.as_slice(), &rest, &challenge)?)
+    }
+}
+
+// The blinding base followed by the base of every position except `index`
+fn hidden_bases(index: usize, length: usize) -> G1Vector {
+    let mut bases = G1Vector::with_capacity(length);
+    bases.push(blinding_base());
+    for j in 0..length {
+        if j != index {
+            bases.push(element_base(j));
+        }
+    }
+    bases
+}
+
+fn position_challenge(
+    commitment: &G1,
+    index: usize,
+    element: &SignatureMessage,
+    length: usize,
+    proof_commitment: &G1,
+) -> FieldElement {
+    let mut bytes = commitment.to_bytes();
+    bytes.extend_from_slice(&(index as u32).to_be_bytes());
+    bytes.append(&mut element.to_bytes());
+    bytes.extend_from_slice(&(length as u32).to_be_bytes());
+    bytes.append(&mut proof_commitment.to_bytes());
+    FieldElement::from_msg_hash(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use signatures::bbs::keys::generate;
+
+    #[test]
+    fn vector_commitment_position() {
+        let elements = FieldElementVector::random(5);
+        let (verkey, signkey) = generate(1).unwrap();
+        let (sig, opening) =
+            Signature::new_vector_commitment(elements.as_slice(), &signkey, &verkey).unwrap();
+
+        let proof = prove_position(2, &elements[2], &opening).unwrap();
+        assert!(proof.verify(&sig, &verkey).unwrap());
+
+        // Claiming the element is elsewhere or another element is there
+        let mut moved = proof.clone();
+        moved.index = 3;
+        assert!(!moved.verify(&sig, &verkey).unwrap());
+        let mut replaced = proof.clone();
+        replaced.element = elements[3].clone();
+        assert!(!replaced.verify(&sig, &verkey).unwrap());
+        assert!(prove_position(2, &elements[3], &opening).is_err());
+
+        // The length is signed: a longer one, to claim a position past the end, or one too large
+        // to allocate, fails before any base is computed
+        let mut longer = prove_position(4, &elements[4], &opening).unwrap();
+        longer.length = 6;
+        longer.index = 5;
+        assert!(!longer.verify(&sig, &verkey).unwrap());
+        let mut huge = proof.clone();
+        huge.length = usize::MAX;
+        assert!(!huge.verify(&sig, &verkey).unwrap());
+        huge.length = u32::MAX as usize;
+        assert!(!huge.verify(&sig, &verkey).unwrap());
+        huge.index = u32::MAX as usize + 1;
+        huge.length = usize::MAX;
+        assert!(!huge.verify(&sig, &verkey).unwrap());
+
+        // Commitment not signed under this key
+        let (other_key, _) = generate(1).unwrap();
+        assert!(!proof.verify(&sig, &other_key).unwrap());
+
+        let (verkey, signkey) = generate(2).unwrap();
+        let err =
+            Signature::new_vector_commitment(elements.as_slice(), &signkey, &verkey).unwrap_err();
+        assert_eq!(
+            err.kind().to_string(),
+            "\"Key must have exactly 1 message base, has 2\""
+        );
+    }
+}